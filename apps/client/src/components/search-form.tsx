@@ -1,28 +1,116 @@
-import { Search } from "lucide-react"
+import { useEffect, useState } from "react";
+import { Search } from "lucide-react";
+import { useQuery } from "@tanstack/react-query";
 
-import { Label } from "@/components/ui/label"
+import { Label } from "@/components/ui/label";
 import {
   SidebarGroup,
   SidebarGroupContent,
   SidebarInput,
-} from "@/components/ui/sidebar"
+} from "@/components/ui/sidebar";
+import { api } from "@/lib/api";
+import { searchHitKindLabel, searchResponseSchema } from "@/models/search";
+
+const SEARCH_DEBOUNCE_MS = 300;
+
+function useDebouncedValue<T>(value: T, delay: number): T {
+  const [debounced, setDebounced] = useState(value);
+
+  useEffect(() => {
+    const timer = setTimeout(() => setDebounced(value), delay);
+    return () => clearTimeout(timer);
+  }, [value, delay]);
+
+  return debounced;
+}
 
 export function SearchForm({ ...props }: React.ComponentProps<"form">) {
+  const [text, setText] = useState("");
+  const [page, setPage] = useState(1);
+  const query = useDebouncedValue(text.trim(), SEARCH_DEBOUNCE_MS);
+
+  useEffect(() => setPage(1), [query]);
+
+  const { data, isFetching } = useQuery({
+    queryKey: ["search", query, page],
+    queryFn: async () => {
+      const res = await api.get("search", { params: { q: query, page } });
+      return searchResponseSchema.parse(res.data);
+    },
+    enabled: query.length > 0,
+    staleTime: 30_000,
+  });
+
+  const pages = data ? Math.ceil(data.total / data.per_page) : 0;
+
   return (
-    <form {...props}>
+    <form {...props} onSubmit={(e) => e.preventDefault()}>
       <SidebarGroup className="py-0">
         <SidebarGroupContent className="relative">
           <Label htmlFor="search" className="sr-only">
-            Search
+            Поиск
           </Label>
           <SidebarInput
             id="search"
-            placeholder="Search the docs..."
+            placeholder="Поиск по деревьям..."
             className="pl-8"
+            value={text}
+            onChange={(e) => setText(e.target.value)}
           />
           <Search className="pointer-events-none absolute top-1/2 left-2 size-4 -translate-y-1/2 opacity-50 select-none" />
         </SidebarGroupContent>
+        {query.length > 0 && (
+          <SidebarGroupContent className="mt-2 flex flex-col gap-1 text-sm">
+            {isFetching && !data && (
+              <span className="text-muted-foreground px-2">Поиск...</span>
+            )}
+            {data && data.hits.length === 0 && (
+              <span className="text-muted-foreground px-2">
+                Ничего не найдено
+              </span>
+            )}
+            {data?.hits.map((hit) => (
+              <div
+                key={`${hit.kind}-${hit.id}`}
+                className="hover:bg-sidebar-accent rounded-md px-2 py-1"
+              >
+                <div className="flex items-center justify-between gap-2">
+                  <span className="truncate font-medium">{hit.title}</span>
+                  <span className="text-muted-foreground shrink-0 text-xs">
+                    {searchHitKindLabel[hit.kind]}
+                  </span>
+                </div>
+                {/* snippet экранируется на сервере, совпадения размечены <mark> */}
+                <p
+                  className="text-muted-foreground line-clamp-2 text-xs"
+                  dangerouslySetInnerHTML={{ __html: hit.snippet }}
+                />
+              </div>
+            ))}
+            {pages > 1 && (
+              <div className="text-muted-foreground flex items-center justify-between px-2 text-xs">
+                <button
+                  type="button"
+                  disabled={page <= 1}
+                  onClick={() => setPage((p) => p - 1)}
+                >
+                  Назад
+                </button>
+                <span>
+                  {page} / {pages}
+                </span>
+                <button
+                  type="button"
+                  disabled={page >= pages}
+                  onClick={() => setPage((p) => p + 1)}
+                >
+                  Вперёд
+                </button>
+              </div>
+            )}
+          </SidebarGroupContent>
+        )}
       </SidebarGroup>
     </form>
-  )
+  );
 }
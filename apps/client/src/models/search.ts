@@ -0,0 +1,29 @@
+import { z } from "zod";
+
+export const searchHitSchema = z.object({
+  kind: z.enum(["person", "place", "source", "note"]),
+  id: z.string(),
+  tree_id: z.string(),
+  title: z.string(),
+  snippet: z.string(),
+  score: z.number(),
+});
+
+export type SearchHit = z.infer<typeof searchHitSchema>;
+
+export const searchResponseSchema = z.object({
+  status: z.literal("success"),
+  total: z.number(),
+  page: z.number(),
+  per_page: z.number(),
+  hits: z.array(searchHitSchema),
+});
+
+export type SearchResponse = z.infer<typeof searchResponseSchema>;
+
+export const searchHitKindLabel: Record<SearchHit["kind"], string> = {
+  person: "Персона",
+  place: "Место",
+  source: "Источник",
+  note: "Заметка",
+};
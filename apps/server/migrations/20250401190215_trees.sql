@@ -0,0 +1,19 @@
+-- Add migration script here
+CREATE TABLE trees (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP DEFAULT NOW(),
+    updated_at TIMESTAMP DEFAULT NOW()
+);
+
+-- Доступ к дереву: owner / editor / viewer
+CREATE TABLE tree_members (
+    tree_id UUID NOT NULL REFERENCES trees(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT NOW(),
+    PRIMARY KEY (tree_id, user_id)
+);
+
+CREATE INDEX tree_members_user_id_idx ON tree_members (user_id);
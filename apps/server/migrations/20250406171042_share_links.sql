@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE share_links (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    token TEXT UNIQUE NOT NULL,
+    tree_id UUID NOT NULL REFERENCES trees(id) ON DELETE CASCADE,
+    -- корень поддерева: по ссылке видны его потомки и их супруги
+    root_person_id TEXT,
+    -- bcrypt-хэш пароля ссылки
+    password TEXT,
+    expires_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX share_links_tree_id_idx ON share_links (tree_id);
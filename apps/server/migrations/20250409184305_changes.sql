@@ -0,0 +1,32 @@
+-- Add migration script here
+-- Журнал изменений персон и связей. Только добавление: строки не меняются,
+-- удаляются лишь вместе с деревом.
+CREATE TABLE changes (
+    id BIGSERIAL PRIMARY KEY,
+    tree_id UUID REFERENCES trees(id) ON DELETE CASCADE,
+    -- без внешнего ключа: запись должна пережить удаление пользователя
+    user_id UUID NOT NULL,
+    -- person / names / parent_link / sibling_link / partnership
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    -- все персоны, которых касается изменение (у связи их две)
+    person_ids TEXT[] NOT NULL,
+    -- create / update / delete
+    operation TEXT NOT NULL,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX changes_tree_id_idx ON changes (tree_id, id);
+CREATE INDEX changes_person_ids_idx ON changes USING GIN (person_ids);
+
+CREATE FUNCTION changes_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'changes is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER changes_no_update
+    BEFORE UPDATE ON changes
+    FOR EACH ROW EXECUTE FUNCTION changes_append_only();
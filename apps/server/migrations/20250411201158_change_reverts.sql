@@ -0,0 +1,6 @@
+-- Add migration script here
+-- Откат сам пишется в журнал отдельной записью со ссылкой на отменённое изменение
+ALTER TABLE changes ADD COLUMN revert_of BIGINT REFERENCES changes(id);
+
+-- каждое изменение можно отменить только один раз
+CREATE UNIQUE INDEX changes_revert_of_idx ON changes (revert_of);
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- Удалённое дерево сначала попадает в корзину и окончательно стирается фоновой задачей
+ALTER TABLE trees ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE trees ADD COLUMN deleted_by UUID;
+
+CREATE INDEX trees_deleted_at_idx ON trees (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,24 @@
+-- Add migration script here
+-- Предложенные правки: зритель описывает изменение, редактор принимает или отклоняет
+CREATE TABLE edit_proposals (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tree_id UUID NOT NULL REFERENCES trees(id) ON DELETE CASCADE,
+    -- person_update / person_names / parent_link / sibling_link /
+    -- partnership_create / partnership_update
+    kind TEXT NOT NULL,
+    -- персона или союз, к которому относится предложение
+    target_id TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    source TEXT,
+    comment TEXT,
+    -- pending / accepted / rejected
+    status TEXT NOT NULL DEFAULT 'pending',
+    proposed_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    review_comment TEXT,
+    reviewed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX edit_proposals_tree_id_idx ON edit_proposals (tree_id, status);
+CREATE INDEX edit_proposals_proposed_by_idx ON edit_proposals (proposed_by);
@@ -0,0 +1,22 @@
+-- Add migration script here
+-- Обсуждения персон, событий и источников. Узлы живут в Neo4j, сюда попадает только их id.
+CREATE TABLE comments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tree_id UUID NOT NULL REFERENCES trees(id) ON DELETE CASCADE,
+    -- person / event / source
+    subject_type TEXT NOT NULL,
+    subject_id TEXT NOT NULL,
+    -- ответ в ветке; у корневых комментариев NULL
+    parent_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    author_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    -- упомянутые участники дерева
+    mentions UUID[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    edited_at TIMESTAMP,
+    -- удалённый комментарий остаётся в ветке без текста, чтобы ответы не повисли
+    deleted_at TIMESTAMP,
+    deleted_by UUID REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX comments_subject_idx ON comments (subject_type, subject_id, created_at);
@@ -0,0 +1,27 @@
+-- Add migration script here
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- person_changed / relationship_changed / mention / new_device_login ...
+    event_type TEXT NOT NULL,
+    -- кто вызвал событие; NULL для системных
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    tree_id UUID REFERENCES trees(id) ON DELETE CASCADE,
+    -- детали события, формат зависит от event_type
+    data JSONB NOT NULL DEFAULT '{}',
+    read_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX notifications_user_id_idx ON notifications (user_id, created_at DESC);
+CREATE INDEX notifications_unread_idx ON notifications (user_id) WHERE read_at IS NULL;
+
+-- Устройства, с которых пользователь уже входил: вход с нового — повод для уведомления
+CREATE TABLE user_devices (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_agent TEXT NOT NULL,
+    last_ip TEXT,
+    first_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, user_agent)
+);
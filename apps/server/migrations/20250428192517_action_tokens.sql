@@ -0,0 +1,13 @@
+-- Add migration script here
+-- Одноразовые токены из писем (подтверждение почты и т.п.). Сам токен — подписанный JWT,
+-- здесь лежит его jti: по нему токен гасится после использования.
+CREATE TABLE action_tokens (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP
+);
+
+CREATE INDEX action_tokens_user_id_idx ON action_tokens (user_id, purpose, created_at);
@@ -0,0 +1,23 @@
+-- Add migration script here
+CREATE TABLE user_totp (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    -- base32; секрет нужен для проверки кодов, поэтому не хешируется
+    secret TEXT NOT NULL,
+    -- NULL — подключение начато, но код ещё не подтверждён
+    enabled_at TIMESTAMP,
+    -- последний принятый 30-секундный шаг: один код дважды не принимаем
+    last_used_step BIGINT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- коды восстановления храним только хешем (sha256), каждый годится один раз
+CREATE TABLE recovery_codes (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    used_at TIMESTAMP,
+    PRIMARY KEY (user_id, code_hash)
+);
+
+-- неудачные попытки по токену: pre-auth токен 2FA гаснет после нескольких неверных кодов
+ALTER TABLE action_tokens ADD COLUMN attempts INT NOT NULL DEFAULT 0;
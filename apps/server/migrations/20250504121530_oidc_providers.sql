@@ -0,0 +1,3 @@
+-- Add migration script here
+-- провайдер теперь хранится под именем из реестра OIDC, как в /sessions/oauth/{name}
+UPDATE users SET provider = 'google' WHERE provider = 'Google';
@@ -0,0 +1,18 @@
+-- Add migration script here
+-- Внешние способы входа пользователя. Пароль по-прежнему в users.password:
+-- пустой пароль значит, что входа по паролю у пользователя нет.
+CREATE TABLE user_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider TEXT NOT NULL,
+    subject TEXT NOT NULL, -- sub провайдера, в отличие от почты не меняется
+    email TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, subject)
+);
+
+CREATE INDEX user_identities_user_id_idx ON user_identities (user_id);
+
+-- sub старых аккаунтов Google неизвестен: такие аккаунты привязываются
+-- при первом входе, см. handlers/oauth.rs
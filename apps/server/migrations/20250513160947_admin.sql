@@ -0,0 +1,34 @@
+-- Add migration script here
+ALTER TABLE users ADD CONSTRAINT users_role_check CHECK (role IN ('user', 'admin'));
+
+-- Заблокированный пользователь не может войти, его сессии и токены не действуют
+ALTER TABLE users ADD COLUMN suspended_at TIMESTAMP;
+-- Вход по паролю закрыт, пока пользователь не задаст новый по ссылке из письма
+ALTER TABLE users ADD COLUMN password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Сессия, которую админ открыл от имени пользователя. Такие сессии не продлеваются.
+ALTER TABLE sessions ADD COLUMN impersonated_by UUID;
+
+-- Журнал действий админов. Только добавление, как и changes.
+CREATE TABLE admin_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    -- без внешних ключей: запись должна пережить удаление и админа, и пользователя
+    admin_id UUID NOT NULL,
+    action TEXT NOT NULL,
+    target_user_id UUID,
+    details JSONB NOT NULL DEFAULT '{}',
+    ip TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX admin_audit_log_target_idx ON admin_audit_log (target_user_id, id);
+
+CREATE FUNCTION admin_audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'admin_audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER admin_audit_log_no_change
+    BEFORE UPDATE OR DELETE ON admin_audit_log
+    FOR EACH ROW EXECUTE FUNCTION admin_audit_log_append_only();
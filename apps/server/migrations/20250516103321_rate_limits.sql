@@ -0,0 +1,13 @@
+-- Add migration script here
+-- Счётчики попыток входа и регистрации для нескольких экземпляров сервера.
+-- UNLOGGED: после сбоя базы счётчики можно потерять, зато запись дешевле.
+CREATE UNLOGGED TABLE rate_limits (
+    key TEXT PRIMARY KEY,
+    window_start TIMESTAMP NOT NULL,
+    attempts INT NOT NULL,
+    locked_until TIMESTAMP,
+    -- когда строку можно удалить: окно кончилось и блокировка снята
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX rate_limits_expires_at_idx ON rate_limits (expires_at);
@@ -0,0 +1,19 @@
+-- Add migration script here
+-- Журнал изменений нельзя ни править, ни чистить. Удалять строки можно только
+-- каскадом вместе с деревом: к моменту каскада строки дерева в trees уже нет.
+CREATE OR REPLACE FUNCTION changes_append_only() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE'
+       AND OLD.tree_id IS NOT NULL
+       AND NOT EXISTS (SELECT 1 FROM trees WHERE id = OLD.tree_id) THEN
+        RETURN OLD;
+    END IF;
+    RAISE EXCEPTION 'changes is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER changes_no_update ON changes;
+
+CREATE TRIGGER changes_no_change
+    BEFORE UPDATE OR DELETE ON changes
+    FOR EACH ROW EXECUTE FUNCTION changes_append_only();
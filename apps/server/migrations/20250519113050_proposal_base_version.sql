@@ -0,0 +1,5 @@
+-- Add migration script here
+-- Версия персоны или союза, которую видел автор предложения. При принятии правка
+-- пишется поверх неё: если цель с тех пор поправили, редактор получает 412.
+-- У предложений связей и у старых предложений версии нет.
+ALTER TABLE edit_proposals ADD COLUMN base_version BIGINT;
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Событий и источников в графе пока нет, поэтому обсуждать можно только персон.
+-- Когда такие узлы появятся, список расширяется вместе с CommentSubject.
+ALTER TABLE comments ADD CONSTRAINT comments_subject_type_check CHECK (subject_type IN ('person'));
@@ -1,12 +1,30 @@
+use std::time::Duration;
+
+use crate::{
+    oidc::{ClaimMapping, ProviderConfig},
+    rate_limit::{Backend, Policy, RateLimitConfig},
+};
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub client_origin: String,
     pub jwt_secret: String,
     pub jwt_max_age: i64,
-    pub google_oauth_client_id: String,
-    pub google_oauth_client_secret: String,
-    pub google_oauth_redirect_url: String,
+    pub refresh_token_max_age: i64, // дни; access-токен живёт jwt_max_age минут
+    // Google из GOOGLE_OAUTH_* плюс провайдеры из JSON-файла OIDC_PROVIDERS_FILE
+    pub oidc_providers: Vec<ProviderConfig>,
+    // пути клиента, куда можно вернуться после входа через провайдера
+    pub oauth_redirect_paths: Vec<String>,
     pub database_url: String,
+    // без SMTP_HOST письма только пишутся в лог, см. mail::Mailer
+    pub smtp_host: Option<String>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub mail_from: String,
+    // не пускать с неподтверждённой почтой на вход и к выдаче доступа к деревьям
+    pub require_verified_email: bool,
+    // лимиты на вход и регистрацию, см. rate_limit
+    pub rate_limit: RateLimitConfig,
 }
 
 impl Config {
@@ -15,22 +33,111 @@ impl Config {
         let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
 
         let jwt_max_age = std::env::var("TOKEN_MAXAGE").expect("TOKEN_MAXAGE must be set");
-        let google_oauth_client_id =
-            std::env::var("GOOGLE_OAUTH_CLIENT_ID").expect("GOOGLE_OAUTH_CLIENT_ID must be set");
-        let google_oauth_client_secret = std::env::var("GOOGLE_OAUTH_CLIENT_SECRET")
-            .expect("GOOGLE_OAUTH_CLIENT_SECRET must be set");
-        let google_oauth_redirect_url = std::env::var("GOOGLE_OAUTH_REDIRECT_URL")
-            .expect("GOOGLE_OAUTH_REDIRECT_URL must be set");
+        let refresh_token_max_age =
+            std::env::var("REFRESH_TOKEN_MAXAGE").unwrap_or_else(|_| "30".to_string());
+        let oidc_providers = oidc_providers();
+        let oauth_redirect_paths = std::env::var("OAUTH_REDIRECT_PATHS")
+            .unwrap_or_else(|_| "/dashboard".to_string())
+            .split(',')
+            .map(|path| path.trim().to_string())
+            .filter(|path| path.starts_with('/'))
+            .collect();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let mail_from = std::env::var("MAIL_FROM")
+            .unwrap_or_else(|_| "Family Tree <noreply@localhost>".to_string());
+        let require_verified_email = std::env::var("REQUIRE_VERIFIED_EMAIL")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
 
         Config {
             client_origin,
             jwt_secret,
             jwt_max_age: jwt_max_age.parse::<i64>().unwrap(),
-            google_oauth_client_id,
-            google_oauth_client_secret,
-            google_oauth_redirect_url,
+            refresh_token_max_age: refresh_token_max_age.parse::<i64>().unwrap(),
+            oidc_providers,
+            oauth_redirect_paths,
             database_url,
+            smtp_host: std::env::var("SMTP_HOST").ok(),
+            smtp_username: std::env::var("SMTP_USERNAME").ok(),
+            smtp_password: std::env::var("SMTP_PASSWORD").ok(),
+            mail_from,
+            require_verified_email,
+            rate_limit: rate_limit(),
+        }
+    }
+}
+
+fn oidc_providers() -> Vec<ProviderConfig> {
+    let mut providers: Vec<ProviderConfig> = match std::env::var("OIDC_PROVIDERS_FILE") {
+        Ok(path) => {
+            let file = std::fs::read_to_string(&path)
+                .unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e));
+            serde_json::from_str(&file)
+                .unwrap_or_else(|e| panic!("OIDC_PROVIDERS_FILE is not valid: {}", e))
         }
+        Err(_) => Vec::new(),
+    };
+
+    if let Ok(client_id) = std::env::var("GOOGLE_OAUTH_CLIENT_ID") {
+        let client_secret = std::env::var("GOOGLE_OAUTH_CLIENT_SECRET")
+            .expect("GOOGLE_OAUTH_CLIENT_SECRET must be set");
+        let redirect_url = std::env::var("GOOGLE_OAUTH_REDIRECT_URL")
+            .expect("GOOGLE_OAUTH_REDIRECT_URL must be set");
+
+        providers.push(ProviderConfig {
+            name: "google".to_string(),
+            display_name: Some("Google".to_string()),
+            issuer: Some("https://accounts.google.com".to_string()),
+            authorization_endpoint: None,
+            token_endpoint: None,
+            userinfo_endpoint: None,
+            jwks_uri: None,
+            client_id,
+            client_secret,
+            redirect_url,
+            scopes: vec![
+                "openid".to_string(),
+                "email".to_string(),
+                "profile".to_string(),
+            ],
+            claims: ClaimMapping::default(),
+            trust_email: false,
+        });
+    }
+
+    providers
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} is not valid", name)),
+        Err(_) => default,
+    }
+}
+
+fn rate_limit() -> RateLimitConfig {
+    let backend = match std::env::var("RATE_LIMIT_BACKEND").as_deref() {
+        Ok("postgres") => Backend::Postgres,
+        Ok("memory") | Err(_) => Backend::Memory,
+        Ok(other) => panic!("Unknown RATE_LIMIT_BACKEND: {}", other),
+    };
+    let window = Duration::from_secs(env_or("RATE_LIMIT_WINDOW_SECONDS", 15 * 60));
+    let lockout = Duration::from_secs(env_or("RATE_LIMIT_LOCKOUT_SECONDS", 15 * 60));
+    let policy = |max_attempts: u32| Policy {
+        max_attempts,
+        window,
+        lockout,
+    };
+
+    RateLimitConfig {
+        backend,
+        trust_forwarded: env_or("RATE_LIMIT_TRUST_FORWARDED", false),
+        login_ip: policy(env_or("LOGIN_MAX_ATTEMPTS_PER_IP", 20)),
+        login_account: policy(env_or("LOGIN_MAX_FAILURES_PER_ACCOUNT", 5)),
+        register_ip: policy(env_or("REGISTER_MAX_ATTEMPTS_PER_IP", 5)),
+        share_password_link: policy(env_or("SHARE_PASSWORD_MAX_FAILURES_PER_LINK", 10)),
+        share_password_ip: policy(env_or("SHARE_PASSWORD_MAX_FAILURES_PER_IP", 20)),
     }
 }
@@ -1,7 +1,15 @@
+pub mod name;
+pub mod node;
+pub mod partnership;
 pub mod person;
+pub mod privacy;
 pub mod schema;
+pub mod search;
 
-use neo4j::{Error, Graph};
-
+pub use name::PersonName;
+pub use node::node_tree_id;
+pub use partnership::{Partnership, PartnershipEndReason, PartnershipKind};
 pub use person::Person;
+pub use privacy::{Privacy, needs_redaction, redact, year_of};
 pub use schema::init_schema;
+pub use search::search_all;
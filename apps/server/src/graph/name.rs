@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameKind {
+    Birth,
+    Married,
+    Alias,
+    Religious,
+}
+
+impl NameKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NameKind::Birth => "birth",
+            NameKind::Married => "married",
+            NameKind::Alias => "alias",
+            NameKind::Religious => "religious",
+        }
+    }
+}
+
+/// Одно из имён персоны. Хранится узлом (:Name), привязанным к (:Person) ребром HAS_NAME.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonName {
+    pub id: String,
+    pub kind: NameKind,
+    pub surname: Option<String>,
+    pub given: Option<String>,
+    pub patronymic: Option<String>,
+    pub start_date: Option<String>, // с какого момента имя носилось
+    pub end_date: Option<String>,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+impl PersonName {
+    fn full(&self) -> String {
+        [&self.given, &self.patronymic, &self.surname]
+            .into_iter()
+            .flatten()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Имя, под которым персона показывается: явно отмеченное основным,
+/// иначе последнее действующее имя в браке, иначе имя при рождении.
+pub fn current_name(names: &[PersonName]) -> Option<&PersonName> {
+    names
+        .iter()
+        .find(|n| n.primary)
+        .or_else(|| {
+            names
+                .iter()
+                .filter(|n| n.kind == NameKind::Married && n.end_date.is_none())
+                .max_by(|a, b| a.start_date.cmp(&b.start_date))
+        })
+        .or_else(|| names.iter().find(|n| n.kind == NameKind::Birth))
+        .or_else(|| names.first())
+}
+
+/// "Мария Ивановна Петрова (урожд. Сидорова)"
+pub fn display_name(names: &[PersonName]) -> String {
+    let Some(current) = current_name(names) else {
+        return String::new();
+    };
+
+    let mut display = current.full();
+
+    let maiden = names
+        .iter()
+        .find(|n| n.kind == NameKind::Birth)
+        .and_then(|n| n.surname.as_deref())
+        .filter(|maiden| Some(*maiden) != current.surname.as_deref());
+
+    if current.kind != NameKind::Birth
+        && let Some(maiden) = maiden
+    {
+        display.push_str(&format!(" (урожд. {})", maiden));
+    }
+
+    display
+}
+
+/// Ключ сортировки "фамилия имя отчество" в нижнем регистре, ё приравнена к е.
+pub fn sort_key(names: &[PersonName]) -> String {
+    let Some(current) = current_name(names) else {
+        return String::new();
+    };
+
+    [&current.surname, &current.given, &current.patronymic]
+        .into_iter()
+        .flatten()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .replace('ё', "е")
+}
+
+// Окончания фамилий, изменяемых по роду: (мужская форма, женская форма)
+const SURNAME_ENDINGS: &[(&str, &str)] = &[
+    ("ский", "ская"),
+    ("цкий", "цкая"),
+    ("ской", "ская"),
+    ("цкой", "цкая"),
+    ("кий", "кая"),
+    ("гий", "гая"),
+    ("хий", "хая"),
+    ("ов", "ова"),
+    ("ев", "ева"),
+    ("ёв", "ёва"),
+    ("ин", "ина"),
+    ("ын", "ына"),
+    ("ый", "ая"),
+    ("ой", "ая"),
+    ("sky", "skaya"),
+    ("skiy", "skaya"),
+    ("skii", "skaya"),
+    ("ov", "ova"),
+    ("ev", "eva"),
+    ("in", "ina"),
+];
+
+/// Форма фамилии для указанного пола: Иванов -> Иванова, Иванова -> Иванов.
+/// Неизменяемые фамилии (Шевченко, Кравчук) возвращаются как есть.
+pub fn gendered_surname(surname: &str, gender: &str) -> String {
+    let lower = surname.to_lowercase();
+
+    for (masculine, feminine) in SURNAME_ENDINGS {
+        let (from, to) = match gender {
+            "female" => (*masculine, *feminine),
+            "male" => (*feminine, *masculine),
+            _ => return surname.to_string(),
+        };
+
+        // уже нужная форма
+        if lower.ends_with(to) {
+            return surname.to_string();
+        }
+
+        if lower.ends_with(from) && lower.chars().count() > from.chars().count() + 1 {
+            let stem_len = surname.chars().count() - from.chars().count();
+            let stem: String = surname.chars().take(stem_len).collect();
+            return format!("{}{}", stem, to);
+        }
+    }
+
+    surname.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(kind: NameKind, surname: &str, given: &str, patronymic: Option<&str>) -> PersonName {
+        PersonName {
+            id: surname.to_lowercase(),
+            kind,
+            surname: Some(surname.to_string()),
+            given: Some(given.to_string()),
+            patronymic: patronymic.map(str::to_string),
+            start_date: None,
+            end_date: None,
+            primary: false,
+        }
+    }
+
+    #[test]
+    fn gendered_surname_switches_declinable_endings() {
+        assert_eq!(gendered_surname("Иванов", "female"), "Иванова");
+        assert_eq!(gendered_surname("Иванова", "male"), "Иванов");
+        assert_eq!(gendered_surname("Соловьёв", "female"), "Соловьёва");
+        assert_eq!(gendered_surname("Пушкин", "female"), "Пушкина");
+        assert_eq!(gendered_surname("Достоевский", "female"), "Достоевская");
+        assert_eq!(gendered_surname("Толстой", "female"), "Толстая");
+        assert_eq!(gendered_surname("Ivanov", "female"), "Ivanova");
+        assert_eq!(gendered_surname("Kowalsky", "female"), "Kowalskaya");
+    }
+
+    #[test]
+    fn gendered_surname_keeps_the_rest() {
+        // уже нужная форма
+        assert_eq!(gendered_surname("Иванова", "female"), "Иванова");
+        // несклоняемые
+        assert_eq!(gendered_surname("Шевченко", "female"), "Шевченко");
+        assert_eq!(gendered_surname("Кравчук", "female"), "Кравчук");
+        // окончание совпадает со всей фамилией
+        assert_eq!(gendered_surname("Ов", "female"), "Ов");
+        // пол неизвестен
+        assert_eq!(gendered_surname("Иванов", "unknown"), "Иванов");
+    }
+
+    #[test]
+    fn sort_key_is_surname_first_with_yo_folded() {
+        let names = vec![name(NameKind::Birth, "Королёв", "Сергей", Some("Павлович"))];
+        assert_eq!(sort_key(&names), "королев сергей павлович");
+    }
+
+    #[test]
+    fn sort_key_uses_the_current_married_name() {
+        let mut married = name(NameKind::Married, "Петрова", "Мария", Some("Ивановна"));
+        married.start_date = Some("1950".to_string());
+        let names = vec![
+            name(NameKind::Birth, "Сидорова", "Мария", Some("Ивановна")),
+            married,
+        ];
+
+        assert_eq!(sort_key(&names), "петрова мария ивановна");
+        assert_eq!(
+            display_name(&names),
+            "Мария Ивановна Петрова (урожд. Сидорова)"
+        );
+    }
+
+    #[test]
+    fn sort_key_prefers_the_primary_name_and_skips_missing_parts() {
+        let mut alias = name(NameKind::Alias, "Smith", "John", None);
+        alias.primary = true;
+        let names = vec![name(NameKind::Birth, "Кузнецов", "Иван", None), alias];
+
+        assert_eq!(sort_key(&names), "smith john");
+        assert_eq!(sort_key(&[]), "");
+    }
+}
@@ -0,0 +1,31 @@
+use neo4rs::{Graph, query};
+use uuid::Uuid;
+
+/// Дерево узла с id и tree_id (пока только персоны). Метка подставляется
+/// в запрос текстом (параметром её не передать), поэтому сюда идут только константы.
+pub async fn node_tree_id(
+    graph: &Graph,
+    label: &'static str,
+    id: &str,
+) -> Result<Option<Uuid>, neo4rs::Error> {
+    let q = query(&format!(
+        "
+        MATCH (n:{} {{id: $id}})
+        WHERE n.deleted_at IS NULL
+        RETURN n.tree_id AS tree_id
+    ",
+        label
+    ))
+    .param("id", id);
+
+    let mut result = graph.execute(q).await?;
+    let Some(row) = result.next().await? else {
+        return Ok(None);
+    };
+
+    let tree_id: Option<String> = row
+        .get("tree_id")
+        .map_err(neo4rs::Error::DeserializationError)?;
+
+    Ok(tree_id.and_then(|id| Uuid::parse_str(&id).ok()))
+}
@@ -0,0 +1,262 @@
+use neo4rs::{Graph, Row, query};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartnershipKind {
+    CivilMarriage,
+    ChurchMarriage,
+    Cohabitation,
+    Engagement,
+}
+
+impl PartnershipKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PartnershipKind::CivilMarriage => "civil_marriage",
+            PartnershipKind::ChurchMarriage => "church_marriage",
+            PartnershipKind::Cohabitation => "cohabitation",
+            PartnershipKind::Engagement => "engagement",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<PartnershipKind> {
+        match value {
+            "civil_marriage" => Some(PartnershipKind::CivilMarriage),
+            "church_marriage" => Some(PartnershipKind::ChurchMarriage),
+            "cohabitation" => Some(PartnershipKind::Cohabitation),
+            "engagement" => Some(PartnershipKind::Engagement),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartnershipEndReason {
+    Divorce,
+    Death,
+    Annulment,
+}
+
+impl PartnershipEndReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PartnershipEndReason::Divorce => "divorce",
+            PartnershipEndReason::Death => "death",
+            PartnershipEndReason::Annulment => "annulment",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<PartnershipEndReason> {
+        match value {
+            "divorce" => Some(PartnershipEndReason::Divorce),
+            "death" => Some(PartnershipEndReason::Death),
+            "annulment" => Some(PartnershipEndReason::Annulment),
+            _ => None,
+        }
+    }
+}
+
+/// Союз двух персон. Хранится одним ребром PARTNER_OF (person1 -> person2),
+/// направление ребра значения не имеет и читается в обе стороны.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Partnership {
+    pub id: String,
+    pub person1_id: String,
+    pub person2_id: String,
+    pub kind: PartnershipKind,
+    pub start_date: Option<String>, // можно сделать chrono::NaiveDate
+    pub end_date: Option<String>,
+    pub end_reason: Option<PartnershipEndReason>,
+    // Порядковый номер союза у каждого из партнёров (первый брак, второй брак...).
+    // None при создании — поставить следующим по счёту.
+    pub person1_order: Option<i64>,
+    pub person2_order: Option<i64>,
+    // Растёт с каждой правкой союза, отдаётся клиенту как ETag
+    #[serde(default)]
+    pub version: i64,
+}
+
+impl Partnership {
+    pub async fn create(graph: &Graph, partnership: &Partnership) -> Result<(), neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p1:Person {id: $person1_id}), (p2:Person {id: $person2_id})
+            WHERE p1 <> p2
+            CREATE (p1)-[:PARTNER_OF {
+                id: $id,
+                kind: $kind,
+                start_date: $start_date,
+                end_date: $end_date,
+                end_reason: $end_reason,
+                person1_order: coalesce($person1_order, COUNT { (p1)-[:PARTNER_OF]-() } + 1),
+                person2_order: coalesce($person2_order, COUNT { (p2)-[:PARTNER_OF]-() } + 1),
+                version: 1
+            }]->(p2)
+        ",
+        )
+        .param("id", partnership.id.as_str())
+        .param("person1_id", partnership.person1_id.as_str())
+        .param("person2_id", partnership.person2_id.as_str())
+        .param("kind", partnership.kind.as_str())
+        .param("start_date", partnership.start_date.clone())
+        .param("end_date", partnership.end_date.clone())
+        .param("end_reason", partnership.end_reason.map(|r| r.as_str()))
+        .param("person1_order", partnership.person1_order)
+        .param("person2_order", partnership.person2_order);
+
+        graph.run(q).await?;
+        Ok(())
+    }
+
+    /// false — союз успели поправить после `expected_version` (или его нет).
+    /// None — без проверки версии, как у Person::update.
+    pub async fn update(
+        graph: &Graph,
+        partnership: &Partnership,
+        expected_version: Option<i64>,
+    ) -> Result<bool, neo4rs::Error> {
+        // person1/person2 берём из ребра, а не из запроса: переданная пара может быть перевёрнута
+        let q = query(
+            "
+            MATCH (p1:Person)-[r:PARTNER_OF {id: $id}]->(p2:Person)
+            WHERE $version IS NULL OR coalesce(r.version, 0) = $version
+            WITH r, p1.id = $person1_id AS same_direction
+            SET r.kind = $kind,
+                r.start_date = $start_date,
+                r.end_date = $end_date,
+                r.end_reason = $end_reason,
+                r.person1_order = CASE WHEN same_direction
+                    THEN coalesce($person1_order, r.person1_order)
+                    ELSE coalesce($person2_order, r.person1_order) END,
+                r.person2_order = CASE WHEN same_direction
+                    THEN coalesce($person2_order, r.person2_order)
+                    ELSE coalesce($person1_order, r.person2_order) END,
+                r.version = coalesce(r.version, 0) + 1
+            RETURN r.id AS id
+        ",
+        )
+        .param("id", partnership.id.as_str())
+        .param("person1_id", partnership.person1_id.as_str())
+        .param("kind", partnership.kind.as_str())
+        .param("start_date", partnership.start_date.clone())
+        .param("end_date", partnership.end_date.clone())
+        .param("end_reason", partnership.end_reason.map(|r| r.as_str()))
+        .param("person1_order", partnership.person1_order)
+        .param("person2_order", partnership.person2_order)
+        .param("version", expected_version);
+
+        let mut result = graph.execute(q).await?;
+        Ok(result.next().await?.is_some())
+    }
+
+    pub async fn delete(graph: &Graph, id: &str) -> Result<(), neo4rs::Error> {
+        let q = query(
+            "
+            MATCH ()-[r:PARTNER_OF {id: $id}]->()
+            DELETE r
+        ",
+        )
+        .param("id", id);
+
+        graph.run(q).await?;
+        Ok(())
+    }
+
+    pub async fn find(graph: &Graph, id: &str) -> Result<Option<Partnership>, neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p1:Person)-[r:PARTNER_OF {id: $id}]->(p2:Person)
+            RETURN r.id AS id,
+                   p1.id AS person1_id,
+                   p2.id AS person2_id,
+                   r.kind AS kind,
+                   r.start_date AS start_date,
+                   r.end_date AS end_date,
+                   r.end_reason AS end_reason,
+                   r.person1_order AS person1_order,
+                   r.person2_order AS person2_order,
+                   coalesce(r.version, 0) AS version
+        ",
+        )
+        .param("id", id);
+
+        let mut result = graph.execute(q).await?;
+        match result.next().await? {
+            Some(row) => Ok(Some(Partnership::from_row(&row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Все союзы персоны, развёрнутые так, что person1 — это сама персона.
+    /// Отсортированы по её порядковому номеру союза.
+    pub async fn list_for_person(
+        graph: &Graph,
+        person_id: &str,
+    ) -> Result<Vec<Partnership>, neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person {id: $person_id})-[r:PARTNER_OF]-(other:Person)
+            WHERE other.deleted_at IS NULL
+            WITH p, r, other, startNode(r) = p AS outgoing
+            RETURN r.id AS id,
+                   p.id AS person1_id,
+                   other.id AS person2_id,
+                   r.kind AS kind,
+                   r.start_date AS start_date,
+                   r.end_date AS end_date,
+                   r.end_reason AS end_reason,
+                   CASE WHEN outgoing THEN r.person1_order ELSE r.person2_order END AS person1_order,
+                   CASE WHEN outgoing THEN r.person2_order ELSE r.person1_order END AS person2_order,
+                   coalesce(r.version, 0) AS version
+            ORDER BY person1_order, start_date
+        ",
+        )
+        .param("person_id", person_id);
+
+        let mut result = graph.execute(q).await?;
+        let mut partnerships = Vec::new();
+        while let Some(row) = result.next().await? {
+            partnerships.push(Partnership::from_row(&row)?);
+        }
+
+        Ok(partnerships)
+    }
+
+    fn from_row(row: &Row) -> Result<Partnership, neo4rs::Error> {
+        let kind: String = row
+            .get("kind")
+            .map_err(neo4rs::Error::DeserializationError)?;
+        let end_reason: Option<String> = row
+            .get("end_reason")
+            .map_err(neo4rs::Error::DeserializationError)?;
+
+        Ok(Partnership {
+            id: row.get("id").map_err(neo4rs::Error::DeserializationError)?,
+            person1_id: row
+                .get("person1_id")
+                .map_err(neo4rs::Error::DeserializationError)?,
+            person2_id: row
+                .get("person2_id")
+                .map_err(neo4rs::Error::DeserializationError)?,
+            kind: PartnershipKind::parse(&kind).ok_or(neo4rs::Error::ConversionError)?,
+            start_date: row
+                .get("start_date")
+                .map_err(neo4rs::Error::DeserializationError)?,
+            end_date: row
+                .get("end_date")
+                .map_err(neo4rs::Error::DeserializationError)?,
+            end_reason: end_reason.as_deref().and_then(PartnershipEndReason::parse),
+            person1_order: row
+                .get("person1_order")
+                .map_err(neo4rs::Error::DeserializationError)?,
+            person2_order: row
+                .get("person2_order")
+                .map_err(neo4rs::Error::DeserializationError)?,
+            version: row
+                .get("version")
+                .map_err(neo4rs::Error::DeserializationError)?,
+        })
+    }
+}
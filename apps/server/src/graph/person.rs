@@ -1,44 +1,449 @@
-use neo4j::{Graph, query};
+use std::collections::HashMap;
+
+use neo4rs::{BoltType, Graph, query};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::name::{PersonName, display_name, gendered_surname, sort_key};
+use super::privacy::{DescendantBirth, Privacy, is_living};
+use crate::text;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Person {
     pub id: String,
-    pub name: String,
-    pub birth_date: String,       // можно сделать chrono::NaiveDate
+    pub name: String, // отображаемое имя, выводится из names
+    #[serde(default)]
+    pub sort_name: String, // "фамилия имя отчество" для сортировки
+    #[serde(default)]
+    pub names: Vec<PersonName>, // узлы (:Name), в свойствах узла не хранятся
+    pub birth_date: String, // можно сделать chrono::NaiveDate
+    #[serde(default)]
+    pub death_date: Option<String>,
     pub gender: String,           // male / female / other
     pub created_by_user_id: Uuid, // user_id из Postgres
+    #[serde(default)]
+    pub tree_id: Option<Uuid>, // trees.id из Postgres
+    #[serde(default)]
+    pub privacy: Privacy,
+    #[serde(default)]
+    pub living: bool, // вычисляется при чтении, см. privacy::is_living
+    #[serde(default)]
+    pub redacted: bool,
+    #[serde(default)]
+    pub deleted_at: Option<i64>, // мс с эпохи; персона в корзине, см. trash
+    #[serde(default)]
+    pub version: i64, // растёт с каждой правкой, отдаётся клиенту как ETag
+}
+
+// Общий RETURN для чтения персоны: имена и даты рождения потомков для privacy::is_living
+const PERSON_RETURN: &str = "
+    RETURN p,
+           [(p)-[:HAS_NAME]->(n:Name) | n {.*}] AS names,
+           [path = (p)-[:PARENT_OF*1..6]->(d:Person) WHERE d.deleted_at IS NULL |
+               {depth: length(path), birth_date: d.birth_date}] AS descendants
+";
+
+#[derive(Debug, Serialize)]
+pub struct PersonSearchHit {
+    pub person: Person,
+    pub score: i64,
 }
 
 impl Person {
-    pub async fn create(graph: &Graph, person: &Person) -> Result<(), neo4j::Error> {
+    pub async fn create(graph: &Graph, person: &Person) -> Result<(), neo4rs::Error> {
         let q = query(
             "
-            CREATE (:Person {
+            CREATE (p:Person {
                 id: $id,
                 name: $name,
+                sort_name: $sort_name,
+                search_keys: $search_keys,
                 birth_date: $birth_date,
+                death_date: $death_date,
                 gender: $gender,
-                created_by_user_id: $created_by_user_id
+                created_by_user_id: $created_by_user_id,
+                tree_id: $tree_id,
+                privacy: $privacy,
+                version: 1
             })
+            WITH p
+            CALL {
+                WITH p
+                UNWIND $names AS n
+                CREATE (p)-[:HAS_NAME]->(:Name {
+                    id: n.id,
+                    kind: n.kind,
+                    surname: n.surname,
+                    given: n.given,
+                    patronymic: n.patronymic,
+                    start_date: n.start_date,
+                    end_date: n.end_date,
+                    primary: n.primary
+                })
+            }
+            CALL {
+                WITH p
+                UNWIND $phonetic_keys AS key
+                MERGE (k:PhoneticKey {key: key})
+                CREATE (p)-[:HAS_PHONETIC_KEY]->(k)
+            }
         ",
         )
-        .param("id", &person.id)
-        .param("name", &person.name)
-        .param("birth_date", &person.birth_date)
-        .param("gender", &person.gender)
-        .param("created_by_user_id", &person.created_by_user_id);
+        .param("id", person.id.as_str())
+        .param("name", person.display_name())
+        .param("sort_name", person.sort_key())
+        .param(
+            "search_keys",
+            text::search_keys(name_words(&person.name, &person.names)),
+        )
+        .param(
+            "phonetic_keys",
+            text::phonetic_keys(name_words(&person.name, &person.names)),
+        )
+        .param("names", names_param(&person.names))
+        .param("birth_date", person.birth_date.as_str())
+        .param("death_date", person.death_date.clone())
+        .param("gender", person.gender.as_str())
+        .param("created_by_user_id", person.created_by_user_id.to_string())
+        .param("tree_id", person.tree_id.map(|id| id.to_string()))
+        .param("privacy", person.privacy.as_str());
 
         graph.run(q).await?;
         Ok(())
     }
 
+    /// Персона с именами и вычисленным признаком living.
+    pub async fn find(graph: &Graph, id: &str) -> Result<Option<Person>, neo4rs::Error> {
+        let q = query(&format!(
+            "
+            MATCH (p:Person {{id: $id}})
+            WHERE p.deleted_at IS NULL
+            {}
+        ",
+            PERSON_RETURN
+        ))
+        .param("id", id);
+
+        let mut result = graph.execute(q).await?;
+        match result.next().await? {
+            Some(row) => Ok(Some(Person::from_read_row(&row)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_for_tree(
+        graph: &Graph,
+        tree_id: &Uuid,
+    ) -> Result<Vec<Person>, neo4rs::Error> {
+        let q = query(&format!(
+            "
+            MATCH (p:Person {{tree_id: $tree_id}})
+            WHERE p.deleted_at IS NULL
+            WITH p ORDER BY p.sort_name
+            {}
+        ",
+            PERSON_RETURN
+        ))
+        .param("tree_id", tree_id.to_string());
+
+        let mut result = graph.execute(q).await?;
+        let mut persons = Vec::new();
+        while let Some(row) = result.next().await? {
+            persons.push(Person::from_read_row(&row)?);
+        }
+
+        Ok(persons)
+    }
+
+    /// Поддерево: сама персона, все её потомки и их супруги.
+    pub async fn list_subtree(graph: &Graph, root_id: &str) -> Result<Vec<Person>, neo4rs::Error> {
+        let q = query(&format!(
+            "
+            MATCH path = (root:Person {{id: $root_id}})-[:PARENT_OF*0..]->(d:Person)
+            WHERE all(n IN nodes(path) WHERE n.deleted_at IS NULL)
+            WITH collect(DISTINCT d) AS descendants
+            UNWIND descendants AS d
+            OPTIONAL MATCH (d)-[:PARTNER_OF]-(partner:Person)
+            WHERE partner.deleted_at IS NULL
+            WITH descendants, collect(DISTINCT partner) AS partners
+            UNWIND descendants + partners AS p
+            WITH DISTINCT p ORDER BY p.sort_name
+            {}
+        ",
+            PERSON_RETURN
+        ))
+        .param("root_id", root_id);
+
+        let mut result = graph.execute(q).await?;
+        let mut persons = Vec::new();
+        while let Some(row) = result.next().await? {
+            persons.push(Person::from_read_row(&row)?);
+        }
+
+        Ok(persons)
+    }
+
+    /// Правки персоны с `expected_version` применяются, только если её версия
+    /// не изменилась; false — персону успели поправить (или её нет).
+    /// None — без проверки, для отката, который сверяет состояние сам.
+    pub async fn set_privacy(
+        graph: &Graph,
+        id: &str,
+        privacy: Privacy,
+        expected_version: Option<i64>,
+    ) -> Result<bool, neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person {id: $id})
+            WHERE $version IS NULL OR coalesce(p.version, 0) = $version
+            SET p.privacy = $privacy, p.version = coalesce(p.version, 0) + 1
+            RETURN p.id AS id
+        ",
+        )
+        .param("id", id)
+        .param("privacy", privacy.as_str())
+        .param("version", expected_version);
+
+        let mut result = graph.execute(q).await?;
+        Ok(result.next().await?.is_some())
+    }
+
+    /// Обновляет даты и пол. Имена меняются через set_names, приватность — через set_privacy.
+    pub async fn update(
+        graph: &Graph,
+        id: &str,
+        birth_date: &str,
+        death_date: Option<&str>,
+        gender: &str,
+        expected_version: Option<i64>,
+    ) -> Result<bool, neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person {id: $id})
+            WHERE $version IS NULL OR coalesce(p.version, 0) = $version
+            SET p.birth_date = $birth_date, p.death_date = $death_date, p.gender = $gender,
+                p.version = coalesce(p.version, 0) + 1
+            RETURN p.id AS id
+        ",
+        )
+        .param("id", id)
+        .param("birth_date", birth_date)
+        .param("death_date", death_date)
+        .param("gender", gender)
+        .param("version", expected_version);
+
+        let mut result = graph.execute(q).await?;
+        Ok(result.next().await?.is_some())
+    }
+
+    fn from_read_row(row: &neo4rs::Row) -> Result<Person, neo4rs::Error> {
+        let mut person: Person = row.get("p").map_err(neo4rs::Error::DeserializationError)?;
+        person.names = row
+            .get("names")
+            .map_err(neo4rs::Error::DeserializationError)?;
+        let descendants: Vec<DescendantBirth> = row
+            .get("descendants")
+            .map_err(neo4rs::Error::DeserializationError)?;
+        person.living = is_living(&person, &descendants);
+
+        Ok(person)
+    }
+
+    /// Заменяет все имена персоны и пересчитывает отображаемое имя, ключ сортировки
+    /// и поисковые ключи.
+    pub async fn set_names(
+        graph: &Graph,
+        person_id: &str,
+        names: &[PersonName],
+        expected_version: Option<i64>,
+    ) -> Result<bool, neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person {id: $person_id})
+            WHERE $version IS NULL OR coalesce(p.version, 0) = $version
+            OPTIONAL MATCH (p)-[:HAS_NAME]->(old:Name)
+            DETACH DELETE old
+            WITH DISTINCT p
+            OPTIONAL MATCH (p)-[old_key:HAS_PHONETIC_KEY]->(:PhoneticKey)
+            DELETE old_key
+            WITH DISTINCT p
+            SET p.name = $name, p.sort_name = $sort_name, p.search_keys = $search_keys,
+                p.version = coalesce(p.version, 0) + 1
+            WITH p
+            CALL {
+                WITH p
+                UNWIND $names AS n
+                CREATE (p)-[:HAS_NAME]->(:Name {
+                    id: n.id,
+                    kind: n.kind,
+                    surname: n.surname,
+                    given: n.given,
+                    patronymic: n.patronymic,
+                    start_date: n.start_date,
+                    end_date: n.end_date,
+                    primary: n.primary
+                })
+            }
+            CALL {
+                WITH p
+                UNWIND $phonetic_keys AS key
+                MERGE (k:PhoneticKey {key: key})
+                CREATE (p)-[:HAS_PHONETIC_KEY]->(k)
+            }
+            RETURN p.id AS id
+        ",
+        )
+        .param("person_id", person_id)
+        .param("name", display_name(names))
+        .param("sort_name", sort_key(names))
+        .param("search_keys", text::search_keys(name_words("", names)))
+        .param("phonetic_keys", text::phonetic_keys(name_words("", names)))
+        .param("names", names_param(names))
+        .param("version", expected_version);
+
+        let mut result = graph.execute(q).await?;
+        Ok(result.next().await?.is_some())
+    }
+
+    /// Поиск по имени без учёта алфавита: "Шевченко" находит и "Shevchenko",
+    /// и "Sevcenko", и "Schewtschenko". Каждое слово запроса должно совпасть с началом
+    /// одного из ключей персоны. Ищет в деревьях `tree_ids` и среди персон без дерева,
+    /// созданных `owner_id`.
+    pub async fn search(
+        graph: &Graph,
+        tree_ids: &[Uuid],
+        owner_id: Option<&Uuid>,
+        text: &str,
+        limit: i64,
+    ) -> Result<Vec<Person>, neo4rs::Error> {
+        // фамилия ищется в обеих родовых формах: "Иванова" находит и "Иванов"
+        let tokens: Vec<Vec<String>> = text::tokenize(text)
+            .iter()
+            .map(|token| {
+                let mut variants: Vec<String> = [
+                    token.to_string(),
+                    gendered_surname(token, "male"),
+                    gendered_surname(token, "female"),
+                ]
+                .iter()
+                .flat_map(|form| text::search_variants(form))
+                .collect();
+                variants.sort();
+                variants.dedup();
+                variants
+            })
+            .filter(|variants| !variants.is_empty())
+            .collect();
+
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let q = query(&format!(
+            "
+            MATCH (p:Person)
+            WHERE (p.tree_id IN $tree_ids
+                   OR (p.tree_id IS NULL AND p.created_by_user_id = $owner_id))
+              AND p.deleted_at IS NULL
+              AND all(variants IN $tokens WHERE
+                    any(v IN variants WHERE
+                        any(k IN coalesce(p.search_keys, []) WHERE k STARTS WITH v)))
+            {}
+            ORDER BY p.sort_name
+            LIMIT $limit
+        ",
+            PERSON_RETURN
+        ))
+        .param(
+            "tree_ids",
+            tree_ids.iter().map(Uuid::to_string).collect::<Vec<_>>(),
+        )
+        .param("owner_id", owner_id.map(Uuid::to_string))
+        .param("tokens", tokens)
+        .param("limit", limit);
+
+        let mut result = graph.execute(q).await?;
+        let mut persons = Vec::new();
+        while let Some(row) = result.next().await? {
+            persons.push(Person::from_read_row(&row)?);
+        }
+
+        Ok(persons)
+    }
+
+    /// Фонетический поиск: персоны, у которых совпал хотя бы один ключ
+    /// Daitch–Mokotoff или Double Metaphone. Чем больше совпавших ключей, тем выше.
+    pub async fn search_phonetic(
+        graph: &Graph,
+        tree_ids: &[Uuid],
+        owner_id: Option<&Uuid>,
+        text: &str,
+        limit: i64,
+    ) -> Result<Vec<PersonSearchHit>, neo4rs::Error> {
+        let keys = text::phonetic_keys([text]);
+
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let q = query(&format!(
+            "
+            MATCH (k:PhoneticKey)
+            WHERE k.key IN $keys
+            MATCH (p:Person)-[:HAS_PHONETIC_KEY]->(k)
+            WHERE (p.tree_id IN $tree_ids
+                   OR (p.tree_id IS NULL AND p.created_by_user_id = $owner_id))
+              AND p.deleted_at IS NULL
+            WITH p, count(DISTINCT k) AS score
+            {}, score
+            ORDER BY score DESC, p.sort_name
+            LIMIT $limit
+        ",
+            PERSON_RETURN.trim_end()
+        ))
+        .param("keys", keys)
+        .param(
+            "tree_ids",
+            tree_ids.iter().map(Uuid::to_string).collect::<Vec<_>>(),
+        )
+        .param("owner_id", owner_id.map(Uuid::to_string))
+        .param("limit", limit);
+
+        let mut result = graph.execute(q).await?;
+        let mut hits = Vec::new();
+        while let Some(row) = result.next().await? {
+            hits.push(PersonSearchHit {
+                person: Person::from_read_row(&row)?,
+                score: row
+                    .get("score")
+                    .map_err(neo4rs::Error::DeserializationError)?,
+            });
+        }
+
+        Ok(hits)
+    }
+
+    pub fn display_name(&self) -> String {
+        if self.names.is_empty() {
+            self.name.clone()
+        } else {
+            display_name(&self.names)
+        }
+    }
+
+    pub fn sort_key(&self) -> String {
+        if self.names.is_empty() {
+            self.name.to_lowercase().replace('ё', "е")
+        } else {
+            sort_key(&self.names)
+        }
+    }
+
     pub async fn link_parent(
         graph: &Graph,
         parent_id: &str,
         child_id: &str,
-    ) -> Result<(), neo4j::Error> {
+    ) -> Result<(), neo4rs::Error> {
         let q = query(
             "
             MATCH (parent:Person {id: $parent_id}), (child:Person {id: $child_id})
@@ -52,16 +457,16 @@ impl Person {
         Ok(())
     }
 
-    pub async fn link_marriage(
+    pub async fn link_siblings(
         graph: &Graph,
         person1_id: &str,
         person2_id: &str,
-    ) -> Result<(), neo4j::Error> {
+    ) -> Result<(), neo4rs::Error> {
         let q = query(
             "
             MATCH (p1:Person {id: $person1_id}), (p2:Person {id: $person2_id})
-            CREATE (p1)-[:MARRIED_TO]->(p2),
-                   (p2)-[:MARRIED_TO]->(p1)
+            CREATE (p1)-[:SIBLING_OF]->(p2),
+                   (p2)-[:SIBLING_OF]->(p1)
         ",
         )
         .param("person1_id", person1_id)
@@ -71,16 +476,33 @@ impl Person {
         Ok(())
     }
 
-    pub async fn link_siblings(
+    pub async fn unlink_parent(
+        graph: &Graph,
+        parent_id: &str,
+        child_id: &str,
+    ) -> Result<(), neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (:Person {id: $parent_id})-[r:PARENT_OF]->(:Person {id: $child_id})
+            DELETE r
+        ",
+        )
+        .param("parent_id", parent_id)
+        .param("child_id", child_id);
+
+        graph.run(q).await?;
+        Ok(())
+    }
+
+    pub async fn unlink_siblings(
         graph: &Graph,
         person1_id: &str,
         person2_id: &str,
-    ) -> Result<(), neo4j::Error> {
+    ) -> Result<(), neo4rs::Error> {
         let q = query(
             "
-            MATCH (p1:Person {id: $person1_id}), (p2:Person {id: $person2_id})
-            CREATE (p1)-[:SIBLING_OF]->(p2),
-                   (p2)-[:SIBLING_OF]->(p1)
+            MATCH (:Person {id: $person1_id})-[r:SIBLING_OF]-(:Person {id: $person2_id})
+            DELETE r
         ",
         )
         .param("person1_id", person1_id)
@@ -89,4 +511,233 @@ impl Person {
         graph.run(q).await?;
         Ok(())
     }
+
+    /// Переносит персону в корзину. Имена и связи остаются на месте,
+    /// но все чтения её пропускают, пока её не восстановят.
+    pub async fn trash(graph: &Graph, id: &str, user_id: &Uuid) -> Result<(), neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person {id: $id})
+            WHERE p.deleted_at IS NULL
+            SET p.deleted_at = timestamp(), p.deleted_by = $user_id
+        ",
+        )
+        .param("id", id)
+        .param("user_id", user_id.to_string());
+
+        graph.run(q).await?;
+        Ok(())
+    }
+
+    pub async fn restore(graph: &Graph, id: &str) -> Result<(), neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person {id: $id})
+            REMOVE p.deleted_at, p.deleted_by
+            SET p.version = coalesce(p.version, 0) + 1
+        ",
+        )
+        .param("id", id);
+
+        graph.run(q).await?;
+        Ok(())
+    }
+
+    /// Авторы персон, включая лежащих в корзине: им уходят уведомления о правках.
+    pub async fn creators(graph: &Graph, ids: &[String]) -> Result<Vec<Uuid>, neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person)
+            WHERE p.id IN $ids
+            RETURN DISTINCT p.created_by_user_id AS user_id
+        ",
+        )
+        .param("ids", ids.to_vec());
+
+        let mut result = graph.execute(q).await?;
+        let mut creators = Vec::new();
+        while let Some(row) = result.next().await? {
+            let user_id: String = row
+                .get("user_id")
+                .map_err(neo4rs::Error::DeserializationError)?;
+            if let Ok(user_id) = Uuid::parse_str(&user_id) {
+                creators.push(user_id);
+            }
+        }
+
+        Ok(creators)
+    }
+
+    /// Персона из корзины — для восстановления.
+    pub async fn find_trashed(graph: &Graph, id: &str) -> Result<Option<Person>, neo4rs::Error> {
+        let q = query(&format!(
+            "
+            MATCH (p:Person {{id: $id}})
+            WHERE p.deleted_at IS NOT NULL
+            {}
+        ",
+            PERSON_RETURN
+        ))
+        .param("id", id);
+
+        let mut result = graph.execute(q).await?;
+        match result.next().await? {
+            Some(row) => Ok(Some(Person::from_read_row(&row)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_trash(graph: &Graph, tree_id: &Uuid) -> Result<Vec<Person>, neo4rs::Error> {
+        let q = query(&format!(
+            "
+            MATCH (p:Person {{tree_id: $tree_id}})
+            WHERE p.deleted_at IS NOT NULL
+            WITH p ORDER BY p.deleted_at DESC
+            {}
+        ",
+            PERSON_RETURN
+        ))
+        .param("tree_id", tree_id.to_string());
+
+        let mut result = graph.execute(q).await?;
+        let mut persons = Vec::new();
+        while let Some(row) = result.next().await? {
+            persons.push(Person::from_read_row(&row)?);
+        }
+
+        Ok(persons)
+    }
+
+    /// Окончательно стирает персон, пролежавших в корзине дольше `retention_days`.
+    /// Возвращает число удалённых.
+    pub async fn purge_trashed(graph: &Graph, retention_days: i64) -> Result<i64, neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person)
+            WHERE p.deleted_at < timestamp() - $retention_ms
+            OPTIONAL MATCH (p)-[:HAS_NAME]->(n:Name)
+            DETACH DELETE n, p
+            RETURN count(DISTINCT p) AS purged
+        ",
+        )
+        .param("retention_ms", retention_days * 24 * 60 * 60 * 1000);
+
+        let mut result = graph.execute(q).await?;
+        let purged = match result.next().await? {
+            Some(row) => row
+                .get("purged")
+                .map_err(neo4rs::Error::DeserializationError)?,
+            None => 0,
+        };
+
+        delete_orphan_phonetic_keys(graph).await?;
+        Ok(purged)
+    }
+
+    /// Стирает всех персон дерева — при окончательном удалении дерева из корзины.
+    pub async fn purge_tree(graph: &Graph, tree_id: &Uuid) -> Result<(), neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person {tree_id: $tree_id})
+            OPTIONAL MATCH (p)-[:HAS_NAME]->(n:Name)
+            DETACH DELETE n, p
+        ",
+        )
+        .param("tree_id", tree_id.to_string());
+
+        graph.run(q).await?;
+        delete_orphan_phonetic_keys(graph).await
+    }
+
+    /// Сколько персон занимает пользователь: в его деревьях и без дерева, включая корзину.
+    pub async fn count_owned(
+        graph: &Graph,
+        user_id: &Uuid,
+        tree_ids: &[Uuid],
+    ) -> Result<i64, neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person)
+            WHERE p.tree_id IN $tree_ids
+               OR (p.tree_id IS NULL AND p.created_by_user_id = $user_id)
+            RETURN count(p) AS persons
+        ",
+        )
+        .param(
+            "tree_ids",
+            tree_ids.iter().map(Uuid::to_string).collect::<Vec<_>>(),
+        )
+        .param("user_id", user_id.to_string());
+
+        let mut result = graph.execute(q).await?;
+        match result.next().await? {
+            Some(row) => row
+                .get("persons")
+                .map_err(neo4rs::Error::DeserializationError),
+            None => Ok(0),
+        }
+    }
+
+    /// Стирает персон без дерева, созданных пользователем, — при удалении его аккаунта.
+    pub async fn purge_created_by(graph: &Graph, user_id: &Uuid) -> Result<(), neo4rs::Error> {
+        let q = query(
+            "
+            MATCH (p:Person {created_by_user_id: $user_id})
+            WHERE p.tree_id IS NULL
+            OPTIONAL MATCH (p)-[:HAS_NAME]->(n:Name)
+            DETACH DELETE n, p
+        ",
+        )
+        .param("user_id", user_id.to_string());
+
+        graph.run(q).await?;
+        delete_orphan_phonetic_keys(graph).await
+    }
+}
+
+// Ключ общий для всех персон с похожим именем, поэтому удаляется только
+// когда на него больше никто не ссылается
+async fn delete_orphan_phonetic_keys(graph: &Graph) -> Result<(), neo4rs::Error> {
+    graph
+        .run(query(
+            "
+            MATCH (k:PhoneticKey)
+            WHERE NOT ()-[:HAS_PHONETIC_KEY]->(k)
+            DELETE k
+        ",
+        ))
+        .await
+}
+
+// Слова, по которым персону ищут: все части всех имён, а без структурных имён — name
+fn name_words<'a>(name: &'a str, names: &'a [PersonName]) -> Vec<&'a str> {
+    if names.is_empty() {
+        return vec![name];
+    }
+
+    names
+        .iter()
+        .flat_map(|n| [&n.surname, &n.given, &n.patronymic])
+        .flatten()
+        .map(|part| part.as_str())
+        .collect()
+}
+
+fn names_param(names: &[PersonName]) -> BoltType {
+    names
+        .iter()
+        .map(|n| {
+            let mut map: HashMap<String, BoltType> = HashMap::new();
+            map.insert("id".into(), n.id.as_str().into());
+            map.insert("kind".into(), n.kind.as_str().into());
+            map.insert("surname".into(), n.surname.clone().into());
+            map.insert("given".into(), n.given.clone().into());
+            map.insert("patronymic".into(), n.patronymic.clone().into());
+            map.insert("start_date".into(), n.start_date.clone().into());
+            map.insert("end_date".into(), n.end_date.clone().into());
+            map.insert("primary".into(), n.primary.into());
+            map
+        })
+        .collect::<Vec<_>>()
+        .into()
 }
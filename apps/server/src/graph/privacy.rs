@@ -0,0 +1,97 @@
+use chrono::{Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::person::Person;
+
+// Без даты смерти персона считается живой, если родилась меньше LIVING_YEARS лет назад
+pub const LIVING_YEARS: i32 = 100;
+
+// Минимальная разница в возрасте между поколениями для оценки года рождения по потомкам
+const MIN_GENERATION_YEARS: i32 = 15;
+
+/// Ручная настройка приватности персоны поверх автоматического определения "жив".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Privacy {
+    #[default]
+    Auto,
+    Public,
+    Private,
+}
+
+impl Privacy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Privacy::Auto => "auto",
+            Privacy::Public => "public",
+            Privacy::Private => "private",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DescendantBirth {
+    pub depth: i64,
+    pub birth_date: Option<String>,
+}
+
+/// Год из даты в любом из принятых форматов: "1931-05-02", "1931", "abt 1931".
+pub fn year_of(date: &str) -> Option<i32> {
+    let bytes = date.as_bytes();
+    (0..bytes.len().saturating_sub(3))
+        .find(|&i| {
+            bytes[i..i + 4].iter().all(u8::is_ascii_digit)
+                && (i == 0 || !bytes[i - 1].is_ascii_digit())
+                && bytes.get(i + 4).is_none_or(|b| !b.is_ascii_digit())
+        })
+        .and_then(|i| date[i..i + 4].parse().ok())
+}
+
+/// Предполагаем, что персона жива: нет даты смерти и она родилась меньше 100 лет назад.
+/// Если дата рождения неизвестна, оцениваем её по потомкам: родитель старше ребёнка
+/// минимум на 15 лет, и если даже такая поздняя оценка укладывается в 100 лет —
+/// персона могла дожить до сегодня.
+pub fn is_living(person: &Person, descendants: &[DescendantBirth]) -> bool {
+    if person.death_date.as_deref().is_some_and(|d| !d.is_empty()) {
+        return false;
+    }
+
+    let threshold = Utc::now().year() - LIVING_YEARS;
+
+    if let Some(year) = year_of(&person.birth_date) {
+        return year > threshold;
+    }
+
+    let latest_possible_birth = descendants
+        .iter()
+        .filter_map(|d| {
+            let year = year_of(d.birth_date.as_deref()?)?;
+            Some(year - MIN_GENERATION_YEARS * d.depth as i32)
+        })
+        .max();
+
+    match latest_possible_birth {
+        Some(year) => year > threshold,
+        // ни своей даты, ни датированных потомков — перестраховываемся
+        None => true,
+    }
+}
+
+pub fn needs_redaction(person: &Person) -> bool {
+    match person.privacy {
+        Privacy::Public => false,
+        Privacy::Private => true,
+        Privacy::Auto => person.living,
+    }
+}
+
+/// Убирает из персоны даты: имя и пол остаются, чтобы дерево читалось.
+pub fn redact(person: &mut Person) {
+    person.birth_date = String::new();
+    person.death_date = None;
+    for name in person.names.iter_mut() {
+        name.start_date = None;
+        name.end_date = None;
+    }
+    person.redacted = true;
+}
@@ -1,6 +1,6 @@
-use neo4j::{Graph, query};
+use neo4rs::{Graph, query};
 
-pub async fn init_schema(graph: &Graph) -> Result<(), neo4j::Error> {
+pub async fn init_schema(graph: &Graph) -> Result<(), neo4rs::Error> {
     // Уникальность Person.id
     graph
         .run(query(
@@ -23,5 +23,154 @@ pub async fn init_schema(graph: &Graph) -> Result<(), neo4j::Error> {
         ))
         .await?;
 
+    // Индекс по tree_id: все выборки в рамках дерева
+    graph
+        .run(query(
+            "
+        CREATE INDEX IF NOT EXISTS
+        FOR (p:Person)
+        ON (p.tree_id)
+    ",
+        ))
+        .await?;
+
+    // Индекс по deleted_at: фоновая очистка корзины
+    graph
+        .run(query(
+            "
+        CREATE INDEX IF NOT EXISTS
+        FOR (p:Person)
+        ON (p.deleted_at)
+    ",
+        ))
+        .await?;
+
+    // Уникальность Name.id
+    graph
+        .run(query(
+            "
+        CREATE CONSTRAINT IF NOT EXISTS
+        FOR (n:Name)
+        REQUIRE n.id IS UNIQUE
+    ",
+        ))
+        .await?;
+
+    // Индекс по sort_name для списков, отсортированных по фамилии
+    graph
+        .run(query(
+            "
+        CREATE INDEX IF NOT EXISTS
+        FOR (p:Person)
+        ON (p.sort_name)
+    ",
+        ))
+        .await?;
+
+    // Уникальность фонетического ключа ("dm:474650", "mp:XFXN"), заодно индекс для поиска
+    graph
+        .run(query(
+            "
+        CREATE CONSTRAINT IF NOT EXISTS
+        FOR (k:PhoneticKey)
+        REQUIRE k.key IS UNIQUE
+    ",
+        ))
+        .await?;
+
+    // Уникальность id союза (ребро PARTNER_OF)
+    graph
+        .run(query(
+            "
+        CREATE CONSTRAINT IF NOT EXISTS
+        FOR ()-[r:PARTNER_OF]-()
+        REQUIRE r.id IS UNIQUE
+    ",
+        ))
+        .await?;
+
+    // Full-text индекс для общего поиска (graph::search). Мест, источников и заметок
+    // в графе пока нет, их индексы появятся вместе с узлами
+    graph
+        .run(query(
+            "
+        CREATE FULLTEXT INDEX person_fulltext IF NOT EXISTS
+        FOR (n:Person) ON EACH [n.name]
+    ",
+        ))
+        .await?;
+
+    // Отметки о выполненных разовых миграциях данных
+    graph
+        .run(query(
+            "
+        CREATE CONSTRAINT IF NOT EXISTS
+        FOR (m:SchemaMigration)
+        REQUIRE m.name IS UNIQUE
+    ",
+        ))
+        .await?;
+
+    migrate_married_to(graph).await?;
+
     Ok(())
 }
+
+/// Старые пары зеркальных рёбер MARRIED_TO переводит в одно ребро PARTNER_OF.
+/// Выполняется один раз и целиком в одной транзакции; отметка SchemaMigration
+/// берётся в той же транзакции, так что параллельный старт второго экземпляра её ждёт.
+async fn migrate_married_to(graph: &Graph) -> Result<(), neo4rs::Error> {
+    let mut txn = graph.start_txn().await?;
+
+    let mut claimed = txn
+        .execute(query(
+            "
+        MERGE (m:SchemaMigration {name: 'married_to_partner_of'})
+        WITH m WHERE m.applied_at IS NULL
+        SET m.applied_at = datetime()
+        RETURN m.name AS name
+    ",
+        ))
+        .await?;
+    if claimed.next(txn.handle()).await?.is_none() {
+        return txn.rollback().await;
+    }
+
+    txn.run_queries([
+        "
+        MATCH (p1:Person)-[:MARRIED_TO]->(p2:Person)
+        WHERE p1.id < p2.id
+        CREATE (p1)-[:PARTNER_OF {
+            id: randomUUID(),
+            kind: 'civil_marriage',
+            version: 1,
+            migrated: true
+        }]->(p2)
+    ",
+        // дат у MARRIED_TO не было: номера союзов у каждого партнёра идут по порядку
+        // после уже существующих союзов, среди перенесённых — по id второго партнёра
+        "
+        MATCH (p:Person)-[r:PARTNER_OF {migrated: true}]-(other:Person)
+        WITH p, r ORDER BY other.id
+        WITH p, collect(r) AS unions,
+             COUNT { (p)-[x:PARTNER_OF]-() WHERE x.migrated IS NULL } AS existing
+        UNWIND range(0, size(unions) - 1) AS i
+        WITH p, unions[i] AS r, existing + i + 1 AS position
+        FOREACH (_ IN CASE WHEN startNode(r) = p THEN [1] ELSE [] END |
+            SET r.person1_order = position)
+        FOREACH (_ IN CASE WHEN endNode(r) = p THEN [1] ELSE [] END |
+            SET r.person2_order = position)
+    ",
+        "
+        MATCH ()-[r:PARTNER_OF {migrated: true}]->()
+        REMOVE r.migrated
+    ",
+        "
+        MATCH ()-[m:MARRIED_TO]->()
+        DELETE m
+    ",
+    ])
+    .await?;
+
+    txn.commit().await
+}
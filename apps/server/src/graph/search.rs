@@ -0,0 +1,145 @@
+use neo4rs::{Graph, query};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::text;
+
+const SNIPPET_RADIUS: usize = 60;
+// дальше листать поиск незачем; столько же максимум считает total
+const MAX_RESULTS: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchHitKind {
+    // места, источники и заметки добавятся сюда вместе со своими узлами
+    Person,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub kind: SearchHitKind,
+    pub id: String,
+    pub tree_id: String,
+    pub title: String,
+    pub snippet: String, // HTML: текст экранирован, совпадения в <mark>
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchPage {
+    pub total: i64,
+    pub hits: Vec<SearchHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawHit {
+    kind: SearchHitKind,
+    id: String,
+    tree_id: String,
+    title: String,
+    body: String,
+    score: f64,
+}
+
+/// Полнотекстовый поиск по персонам деревьев `tree_ids`.
+/// Использует full-text индекс из init_schema, результаты отсортированы по релевантности.
+pub async fn search_all(
+    graph: &Graph,
+    tree_ids: &[Uuid],
+    text: &str,
+    skip: i64,
+    limit: i64,
+) -> Result<SearchPage, neo4rs::Error> {
+    // tokenize оставляет только буквы и цифры, так что спецсимволы Lucene сюда не попадут
+    let terms: Vec<String> = text::tokenize(text)
+        .into_iter()
+        .map(|t| t.to_lowercase())
+        .collect();
+
+    if terms.is_empty() || tree_ids.is_empty() {
+        return Ok(SearchPage {
+            total: 0,
+            hits: Vec::new(),
+        });
+    }
+
+    let lucene = terms
+        .iter()
+        .map(|t| format!("{}*", t))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+
+    let tree_ids: Vec<String> = tree_ids.iter().map(|id| id.to_string()).collect();
+    let total = count_matches(graph, &lucene, &tree_ids).await?;
+
+    let limit = limit.min(MAX_RESULTS - skip);
+    if limit <= 0 {
+        return Ok(SearchPage {
+            total,
+            hits: Vec::new(),
+        });
+    }
+
+    let q = query(
+        "
+        CALL db.index.fulltext.queryNodes('person_fulltext', $query) YIELD node, score
+        WHERE node.tree_id IN $tree_ids AND node.deleted_at IS NULL
+        RETURN 'person' AS kind, node.id AS id, node.tree_id AS tree_id,
+               node.name AS title, node.name AS body, score
+        ORDER BY score DESC
+        SKIP $skip LIMIT $limit
+    ",
+    )
+    .param("query", lucene)
+    .param("tree_ids", tree_ids)
+    .param("skip", skip)
+    .param("limit", limit);
+
+    let mut result = graph.execute(q).await?;
+    let mut hits = Vec::new();
+    while let Some(row) = result.next().await? {
+        let raw: RawHit = row.to().map_err(neo4rs::Error::DeserializationError)?;
+        hits.push(SearchHit {
+            kind: raw.kind,
+            id: raw.id,
+            tree_id: raw.tree_id,
+            title: raw.title,
+            snippet: text::highlight(&raw.body, &terms, SNIPPET_RADIUS),
+            score: raw.score,
+        });
+    }
+
+    Ok(SearchPage { total, hits })
+}
+
+/// Число совпадений для пагинации, не больше MAX_RESULTS: узлы не материализуются,
+/// и индекс читается не дальше этой границы.
+async fn count_matches(
+    graph: &Graph,
+    lucene: &str,
+    tree_ids: &[String],
+) -> Result<i64, neo4rs::Error> {
+    let q = query(
+        "
+        CALL {
+            CALL db.index.fulltext.queryNodes('person_fulltext', $query) YIELD node
+            WHERE node.tree_id IN $tree_ids AND node.deleted_at IS NULL
+            RETURN node.id AS id LIMIT $max
+        }
+        RETURN count(id) AS total
+    ",
+    )
+    .param("query", lucene)
+    .param("tree_ids", tree_ids.to_vec())
+    .param("max", MAX_RESULTS);
+
+    let mut result = graph.execute(q).await?;
+    let total: i64 = match result.next().await? {
+        Some(row) => row
+            .get("total")
+            .map_err(neo4rs::Error::DeserializationError)?,
+        None => 0,
+    };
+
+    Ok(total.min(MAX_RESULTS))
+}
@@ -0,0 +1,131 @@
+use actix_web::{HttpResponse, Responder, delete, get, post, web};
+use chrono::{Duration, prelude::*};
+use uuid::Uuid;
+
+use super::auth::{ACCESS_TOKEN_PREFIX, AuthenticationGuard};
+use crate::{
+    model::{AppState, CreateAccessTokenSchema},
+    repo::{create_access_token, get_access_tokens, get_tree_role, revoke_access_token},
+};
+
+const DEFAULT_TOKEN_DAYS: i64 = 90;
+const MAX_TOKEN_DAYS: i64 = 365;
+
+#[get("/tokens")]
+async fn get_access_tokens_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    match get_access_tokens(&data.pool, user_id).await {
+        Ok(tokens) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "tokens": tokens}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Выпускает токен. Значение возвращается один раз, в базе хранится только хеш.
+#[post("/tokens")]
+async fn create_access_token_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<CreateAccessTokenSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_own_session() {
+        return response;
+    }
+
+    let name = body.name.trim();
+    if name.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"status": "fail", "message": "Token name is required"}));
+    }
+    if body.scopes.is_empty() {
+        return HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "At least one tree scope is required"}),
+        );
+    }
+
+    // выдать токену можно только те права, что есть у самого пользователя
+    for scope in &body.scopes {
+        match get_tree_role(&data.pool, scope.tree_id, user_id).await {
+            Ok(Some(role)) if role >= scope.access.max_role() => {}
+            Ok(_) => {
+                return HttpResponse::Forbidden().json(serde_json::json!({
+                    "status": "fail",
+                    "message": format!("Not enough rights in tree {}", scope.tree_id)
+                }));
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": "error",
+                    "info": e.to_string()
+                }));
+            }
+        }
+    }
+
+    let days = body
+        .expires_in_days
+        .unwrap_or(DEFAULT_TOKEN_DAYS)
+        .clamp(1, MAX_TOKEN_DAYS);
+    let expires_at = (Utc::now() + Duration::days(days)).naive_utc();
+    let token = format!(
+        "{}{}{}",
+        ACCESS_TOKEN_PREFIX,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    );
+
+    match create_access_token(&data.pool, user_id, name, &token, &body.scopes, expires_at).await {
+        Ok(access_token) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "token": token,
+            "access_token": access_token
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[delete("/tokens/{id}")]
+async fn revoke_access_token_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    match revoke_access_token(&data.pool, path.into_inner(), user_id).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Ok(false) => HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Token not found"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
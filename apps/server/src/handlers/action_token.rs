@@ -0,0 +1,79 @@
+use chrono::{Duration, prelude::*};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use uuid::Uuid;
+
+use crate::{
+    model::{ActionPurpose, ActionTokenClaims, AppState},
+    repo::{consume_action_token, create_action_token},
+};
+
+/// Выдаёт одноразовый токен для ссылки из письма: подписанный JWT, чей jti
+/// записан в action_tokens.
+pub(super) async fn issue_action_token(
+    data: &AppState,
+    user_id: Uuid,
+    purpose: ActionPurpose,
+    ttl: Duration,
+) -> Result<String, sqlx::Error> {
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let expires_at = now + ttl;
+
+    create_action_token(&data.pool, id, user_id, purpose, expires_at.naive_utc()).await?;
+
+    let claims = ActionTokenClaims {
+        sub: user_id.to_string(),
+        jti: id.to_string(),
+        purpose,
+        iat: now.timestamp() as usize,
+        exp: expires_at.timestamp() as usize,
+    };
+
+    Ok(encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(data.env.jwt_secret.as_ref()),
+    )
+    .unwrap())
+}
+
+/// Проверяет подпись, срок и назначение токена, не гася его. Возвращает (jti, user_id).
+pub(super) fn decode_token(
+    data: &AppState,
+    token: &str,
+    purpose: ActionPurpose,
+) -> Option<(Uuid, Uuid)> {
+    let decoded = decode::<ActionTokenClaims>(
+        token,
+        &DecodingKey::from_secret(data.env.jwt_secret.as_ref()),
+        &Validation::new(Algorithm::HS256),
+    )
+    .ok()?;
+
+    let claims = decoded.claims;
+    if claims.purpose != purpose {
+        return None;
+    }
+
+    Some((
+        Uuid::parse_str(&claims.jti).ok()?,
+        Uuid::parse_str(&claims.sub).ok()?,
+    ))
+}
+
+/// Проверяет токен и гасит его. None — токен поддельный, для другого действия,
+/// просрочен или уже использован.
+pub(super) async fn consume_token(
+    data: &AppState,
+    token: &str,
+    purpose: ActionPurpose,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let Some((id, user_id)) = decode_token(data, token, purpose) else {
+        return Ok(None);
+    };
+
+    match consume_action_token(&data.pool, id, purpose).await? {
+        Some(owner) if owner == user_id => Ok(Some(user_id)),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,496 @@
+use actix_web::{
+    FromRequest, HttpRequest, HttpResponse, Responder, delete,
+    dev::Payload,
+    error::{Error as ActixWebError, ErrorForbidden, ErrorInternalServerError},
+    get, post, web,
+};
+use serde_json::json;
+use std::{future::Future, pin::Pin};
+use uuid::Uuid;
+
+use super::{
+    auth::{AuthenticationGuard, user_to_response},
+    password::send_password_reset_email,
+    session::{client_info, start_impersonation},
+};
+use crate::{
+    graph::Person,
+    model::{AdminAction, AdminUsersQuery, AppState, AuditQuery, SuspendUserSchema, User},
+    repo::{
+        delete_user, get_audit_log, get_owned_tree_ids, get_storage_usage, get_user_by_id,
+        record_admin_action, require_password_reset, search_users, suspend_user, unsuspend_user,
+    },
+};
+
+const ADMIN_ROLE: &str = "admin";
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 200;
+
+/// Пропускает только админов, вошедших через сессию: ни токен доступа,
+/// ни сессия от имени пользователя прав админа не дают.
+pub struct AdminGuard {
+    pub admin_id: Uuid,
+}
+
+impl FromRequest for AdminGuard {
+    type Error = ActixWebError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let auth_guard = AuthenticationGuard::from_request(req, payload);
+        let data = req.app_data::<web::Data<AppState>>().unwrap().clone();
+
+        Box::pin(async move {
+            let auth_guard = auth_guard.await?;
+            let forbidden =
+                || ErrorForbidden(json!({"status": "fail", "message": "Admin role is required"}));
+
+            if auth_guard.session_id.is_none() {
+                return Err(forbidden());
+            }
+
+            match get_user_by_id(&data.pool, &auth_guard.user_id).await {
+                Ok(Some(user)) if user.role == ADMIN_ROLE => Ok(AdminGuard {
+                    admin_id: user.id.unwrap(),
+                }),
+                Ok(_) => Err(forbidden()),
+                Err(e) => Err(ErrorInternalServerError(
+                    json!({"status": "error", "info": e.to_string()}),
+                )),
+            }
+        })
+    }
+}
+
+/// Пишет действие в журнал до того, как его выполнить: без записи действия не будет.
+async fn audit(
+    data: &AppState,
+    req: &HttpRequest,
+    admin: &AdminGuard,
+    action: AdminAction,
+    target_user_id: Option<Uuid>,
+    details: serde_json::Value,
+) -> Result<(), HttpResponse> {
+    let (_, ip) = client_info(data, req);
+
+    match record_admin_action(
+        &data.pool,
+        admin.admin_id,
+        action,
+        target_user_id,
+        details,
+        ip.as_deref(),
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            log::error!("Failed to audit admin action {}: {}", action.as_str(), e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            })))
+        }
+    }
+}
+
+async fn find_user(data: &AppState, user_id: Uuid) -> Result<User, HttpResponse> {
+    match get_user_by_id(&data.pool, &user_id.to_string()).await {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "User not found"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+/// Пользователь, над которым админ что-то делает. Других админов (и себя)
+/// так не трогаем: роль снимают в базе.
+async fn find_target(data: &AppState, user_id: Uuid) -> Result<User, HttpResponse> {
+    let user = find_user(data, user_id).await?;
+    if user.role == ADMIN_ROLE {
+        return Err(HttpResponse::Forbidden().json(
+            serde_json::json!({"status": "fail", "message": "This action is not allowed on admins"}),
+        ));
+    }
+
+    Ok(user)
+}
+
+#[get("/admin/users")]
+async fn admin_get_users_handler(
+    admin: AdminGuard,
+    req: HttpRequest,
+    query: web::Query<AdminUsersQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let q = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    if let Err(response) = audit(
+        &data,
+        &req,
+        &admin,
+        AdminAction::ListUsers,
+        None,
+        serde_json::json!({"q": q, "page": page}),
+    )
+    .await
+    {
+        return response;
+    }
+
+    match search_users(&data.pool, q, per_page, (page - 1) * per_page).await {
+        Ok(users) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "page": page,
+            "per_page": per_page,
+            "users": users
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[get("/admin/users/{id}/storage")]
+async fn admin_get_storage_handler(
+    admin: AdminGuard,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    if let Err(response) = find_user(&data, user_id).await {
+        return response;
+    }
+
+    if let Err(response) = audit(
+        &data,
+        &req,
+        &admin,
+        AdminAction::ViewStorage,
+        Some(user_id),
+        serde_json::json!({}),
+    )
+    .await
+    {
+        return response;
+    }
+
+    let usage = match get_storage_usage(&data.pool, user_id).await {
+        Ok(usage) => usage,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+    let tree_ids = match get_owned_tree_ids(&data.pool, user_id).await {
+        Ok(tree_ids) => tree_ids,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+    let persons = match Person::count_owned(&data.graph, &user_id, &tree_ids).await {
+        Ok(persons) => persons,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "storage": {
+            "trees": usage.trees,
+            "persons": persons,
+            "changes": usage.changes,
+            "history_bytes": usage.history_bytes,
+            "comments": usage.comments
+        }
+    }))
+}
+
+#[post("/admin/users/{id}/suspend")]
+async fn admin_suspend_user_handler(
+    admin: AdminGuard,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: web::Json<SuspendUserSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let user = match find_target(&data, user_id).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if user.suspended_at.is_some() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"status": "fail", "message": "User is already suspended"}));
+    }
+
+    if let Err(response) = audit(
+        &data,
+        &req,
+        &admin,
+        AdminAction::Suspend,
+        Some(user_id),
+        serde_json::json!({"email": user.email, "reason": body.reason}),
+    )
+    .await
+    {
+        return response;
+    }
+
+    match suspend_user(&data.pool, user_id).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[post("/admin/users/{id}/unsuspend")]
+async fn admin_unsuspend_user_handler(
+    admin: AdminGuard,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let user = match find_target(&data, user_id).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if user.suspended_at.is_none() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"status": "fail", "message": "User is not suspended"}));
+    }
+
+    if let Err(response) = audit(
+        &data,
+        &req,
+        &admin,
+        AdminAction::Unsuspend,
+        Some(user_id),
+        serde_json::json!({"email": user.email}),
+    )
+    .await
+    {
+        return response;
+    }
+
+    match unsuspend_user(&data.pool, user_id).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Удаляет пользователя вместе с его деревьями и персонами без дерева.
+/// Записи журнала правок в чужих деревьях остаются.
+#[delete("/admin/users/{id}")]
+async fn admin_delete_user_handler(
+    admin: AdminGuard,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let user = match find_target(&data, user_id).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = audit(
+        &data,
+        &req,
+        &admin,
+        AdminAction::Delete,
+        Some(user_id),
+        serde_json::json!({"email": user.email, "name": user.name}),
+    )
+    .await
+    {
+        return response;
+    }
+
+    let tree_ids = match get_owned_tree_ids(&data.pool, user_id).await {
+        Ok(tree_ids) => tree_ids,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    // сначала граф, потом Postgres: если упадём посередине, пользователь
+    // останется и удаление можно повторить
+    for tree_id in &tree_ids {
+        if let Err(e) = Person::purge_tree(&data.graph, tree_id).await {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+    if let Err(e) = Person::purge_created_by(&data.graph, &user_id).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+
+    match delete_user(&data.pool, user_id).await {
+        Ok(()) => {
+            log::info!("Admin {} deleted user {}", admin.admin_id, user_id);
+            HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Входит от имени пользователя: cookie админа заменяются cookie новой сессии.
+/// Чтобы вернуться, админ выходит и входит заново.
+#[post("/admin/users/{id}/impersonate")]
+async fn admin_impersonate_user_handler(
+    admin: AdminGuard,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let user = match find_target(&data, user_id).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = audit(
+        &data,
+        &req,
+        &admin,
+        AdminAction::Impersonate,
+        Some(user_id),
+        serde_json::json!({"email": user.email}),
+    )
+    .await
+    {
+        return response;
+    }
+
+    let (access, refresh) = match start_impersonation(&data, &req, user_id, admin.admin_id).await {
+        Ok(cookies) => cookies,
+        Err(response) => return response,
+    };
+
+    HttpResponse::Ok()
+        .cookie(access)
+        .cookie(refresh)
+        .json(serde_json::json!({"status": "success", "user": user_to_response(&user)}))
+}
+
+/// Закрывает вход по паролю, отзывает сессии и шлёт ссылку на сброс.
+#[post("/admin/users/{id}/force_password_reset")]
+async fn admin_force_password_reset_handler(
+    admin: AdminGuard,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let user = match find_target(&data, user_id).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if user.password.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"status": "fail", "message": "User has no password"}));
+    }
+
+    if let Err(response) = audit(
+        &data,
+        &req,
+        &admin,
+        AdminAction::ForcePasswordReset,
+        Some(user_id),
+        serde_json::json!({"email": user.email}),
+    )
+    .await
+    {
+        return response;
+    }
+
+    if let Err(e) = require_password_reset(&data.pool, user_id).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+    if let Err(response) = send_password_reset_email(&data, user_id, &user).await {
+        return response;
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
+}
+
+#[get("/admin/audit")]
+async fn admin_get_audit_log_handler(
+    admin: AdminGuard,
+    req: HttpRequest,
+    query: web::Query<AuditQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    if let Err(response) = audit(
+        &data,
+        &req,
+        &admin,
+        AdminAction::ViewAudit,
+        query.user_id,
+        serde_json::json!({"page": page}),
+    )
+    .await
+    {
+        return response;
+    }
+
+    match get_audit_log(&data.pool, query.user_id, per_page, (page - 1) * per_page).await {
+        Ok(entries) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "page": page,
+            "per_page": per_page,
+            "entries": entries
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
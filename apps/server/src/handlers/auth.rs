@@ -1,67 +1,203 @@
 use crate::{
     handlers::model::{UserData, UserResponse},
-    model::{AppState, LoginUserSchema, RegisterUserSchema, TokenClaims, User},
-    repo::{create_user, get_user_by_email_and_password, get_user_by_id, user_exists},
+    model::{
+        AppState, LoginUserSchema, RegisterUserSchema, TokenAccess, TokenClaims, TokenScope,
+        TreeRole, User,
+    },
+    repo::{
+        authenticate_access_token, create_user, get_tree_role, get_user_by_email_and_password,
+        get_user_by_id, revoke_session, touch_session, user_exists,
+    },
 };
 use actix_web::{
     FromRequest, HttpRequest,
     dev::Payload,
-    error::{Error as ActixWebError, ErrorUnauthorized},
+    error::{Error as ActixWebError, ErrorInternalServerError, ErrorUnauthorized},
     http::{self},
 };
-use actix_web::{
-    HttpResponse, Responder,
-    cookie::{Cookie, time::Duration as ActixWebDuration},
-    get, post, web,
-};
-use chrono::{Duration, prelude::*};
-use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use actix_web::{HttpResponse, Responder, get, post, web};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
 use serde_json::json;
-use std::future::{Ready, ready};
+use std::{future::Future, pin::Pin};
+use uuid::Uuid;
+
+use super::{
+    model::FilteredUser,
+    notification::notify_login,
+    rate_limit::{account_key, ensure_not_locked, ip_key, limit, reset},
+    session::{ACCESS_COOKIE, clear_session_cookies, start_session},
+    two_factor::begin_two_factor_login,
+    verification::send_verification_email,
+};
 
-use super::model::FilteredUser;
+// Так начинаются персональные токены доступа — по префиксу их легко отличить от JWT
+pub(super) const ACCESS_TOKEN_PREFIX: &str = "ftpat_";
 
 pub struct AuthenticationGuard {
     pub user_id: String,
+    pub session_id: Option<Uuid>, // None — запрос по персональному токену доступа
+    pub token_scopes: Option<Vec<TokenScope>>,
+    pub impersonated_by: Option<Uuid>, // сессия админа от имени пользователя
+}
+
+impl AuthenticationGuard {
+    /// Сессия текущего запроса. Аккаунтом (сессии, пароль, 2FA, токены) управляют
+    /// только из сессии: токен для скриптов этого не может.
+    pub fn require_session(&self) -> Result<Uuid, HttpResponse> {
+        self.session_id.ok_or_else(|| {
+            HttpResponse::Forbidden().json(serde_json::json!({
+                "status": "fail",
+                "message": "This action is not available with an access token"
+            }))
+        })
+    }
+
+    /// Сессия самого пользователя. Входы, пароль, 2FA и токены админ от его имени
+    /// не трогает: иначе доступ пережил бы короткую и записанную в журнал сессию.
+    pub fn require_own_session(&self) -> Result<Uuid, HttpResponse> {
+        let session_id = self.require_session()?;
+        if self.impersonated_by.is_some() {
+            return Err(HttpResponse::Forbidden().json(serde_json::json!({
+                "status": "fail",
+                "message": "This action is not available while impersonating a user"
+            })));
+        }
+
+        Ok(session_id)
+    }
+
+    /// Роль в дереве с учётом токена: не выше, чем разрешает его scope.
+    pub async fn tree_role(
+        &self,
+        data: &AppState,
+        tree_id: Uuid,
+    ) -> Result<Option<TreeRole>, sqlx::Error> {
+        let Ok(user_id) = Uuid::parse_str(&self.user_id) else {
+            return Ok(None);
+        };
+        let role = get_tree_role(&data.pool, tree_id, user_id).await?;
+
+        let Some(scopes) = &self.token_scopes else {
+            return Ok(role);
+        };
+        let max_role = scopes
+            .iter()
+            .find(|scope| scope.tree_id == tree_id)
+            .map(|scope| scope.access.max_role());
+
+        Ok(role
+            .zip(max_role)
+            .map(|(role, max_role)| role.min(max_role)))
+    }
+
+    /// Входит ли дерево в scope токена. Сессии доступны все деревья пользователя.
+    pub fn allows_tree(&self, tree_id: Uuid) -> bool {
+        self.token_scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|scope| scope.tree_id == tree_id))
+    }
+
+    /// Запись в дереве, доступную даже зрителю (комментарии, предложения правок),
+    /// токен с доступом только на чтение делать не может.
+    pub fn require_write(&self, tree_id: Uuid) -> Result<(), HttpResponse> {
+        let writable = self.token_scopes.as_ref().is_none_or(|scopes| {
+            scopes
+                .iter()
+                .any(|scope| scope.tree_id == tree_id && scope.access == TokenAccess::Write)
+        });
+        if writable {
+            Ok(())
+        } else {
+            Err(HttpResponse::Forbidden().json(serde_json::json!({
+                "status": "fail",
+                "message": "This access token is read-only"
+            })))
+        }
+    }
 }
 
 impl FromRequest for AuthenticationGuard {
     type Error = ActixWebError;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let bearer = req
+            .headers()
+            .get(http::header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .map(str::to_string);
+        let access_token = bearer
+            .clone()
+            .filter(|token| token.starts_with(ACCESS_TOKEN_PREFIX));
         let token = req
-            .cookie("token")
+            .cookie(ACCESS_COOKIE)
             .map(|c| c.value().to_string())
-            .or_else(|| {
-                req.headers()
-                    .get(http::header::AUTHORIZATION)
-                    .map(|h| h.to_str().unwrap().split_at(7).1.to_string())
-            });
-
-        if token.is_none() {
-            return ready(Err(ErrorUnauthorized(
-                json!({"status": "fail", "message": "You are not logged in, please provide token"}),
-            )));
-        }
+            .or(bearer);
 
-        let data = req.app_data::<web::Data<AppState>>().unwrap();
-
-        let jwt_secret = data.env.jwt_secret.to_owned();
-        let decode = decode::<TokenClaims>(
-            token.unwrap().as_str(),
-            &DecodingKey::from_secret(jwt_secret.as_ref()),
-            &Validation::new(Algorithm::HS256),
-        );
-
-        match decode {
-            Ok(token) => ready(Ok(AuthenticationGuard {
-                user_id: token.claims.sub,
-            })),
-            Err(_) => ready(Err(ErrorUnauthorized(
-                json!({"status": "fail", "message": "Invalid token or usre doesn't exists"}),
-            ))),
-        }
+        let data = req.app_data::<web::Data<AppState>>().unwrap().clone();
+
+        Box::pin(async move {
+            if let Some(access_token) = access_token {
+                return match authenticate_access_token(&data.pool, &access_token).await {
+                    Ok(Some((user_id, scopes))) => Ok(AuthenticationGuard {
+                        user_id: user_id.to_string(),
+                        session_id: None,
+                        token_scopes: Some(scopes),
+                        impersonated_by: None,
+                    }),
+                    Ok(None) => Err(ErrorUnauthorized(
+                        json!({"status": "fail", "message": "Access token is invalid, expired or revoked"}),
+                    )),
+                    Err(e) => Err(ErrorInternalServerError(
+                        json!({"status": "error", "info": e.to_string()}),
+                    )),
+                };
+            }
+
+            let Some(token) = token else {
+                return Err(ErrorUnauthorized(
+                    json!({"status": "fail", "message": "You are not logged in, please provide token"}),
+                ));
+            };
+
+            let jwt_secret = data.env.jwt_secret.to_owned();
+            let decode = decode::<TokenClaims>(
+                token.as_str(),
+                &DecodingKey::from_secret(jwt_secret.as_ref()),
+                &Validation::new(Algorithm::HS256),
+            );
+
+            let claims = match decode {
+                Ok(token) => token.claims,
+                Err(_) => {
+                    return Err(ErrorUnauthorized(
+                        json!({"status": "fail", "message": "Invalid token or usre doesn't exists"}),
+                    ));
+                }
+            };
+            let Ok(session_id) = Uuid::parse_str(&claims.sid) else {
+                return Err(ErrorUnauthorized(
+                    json!({"status": "fail", "message": "Invalid token"}),
+                ));
+            };
+
+            // подпись ещё действует, но сессию могли отозвать — проверяем по базе
+            match touch_session(&data.pool, session_id).await {
+                Ok(Some(session)) => Ok(AuthenticationGuard {
+                    user_id: claims.sub,
+                    session_id: Some(session_id),
+                    token_scopes: None,
+                    impersonated_by: session.impersonated_by,
+                }),
+                Ok(None) => Err(ErrorUnauthorized(
+                    json!({"status": "fail", "message": "Session has been revoked or expired"}),
+                )),
+                Err(e) => Err(ErrorInternalServerError(
+                    json!({"status": "error", "info": e.to_string()}),
+                )),
+            }
+        })
     }
 }
 
@@ -81,11 +217,17 @@ pub fn user_to_response(user: &User) -> FilteredUser {
 
 #[post("/auth/register")]
 async fn register_user_handler(
+    req: HttpRequest,
     body: web::Json<RegisterUserSchema>,
     data: web::Data<AppState>,
 ) -> impl Responder {
     let pool = data.pool.clone();
 
+    let ip_limit = ip_key(&data, &req, "register");
+    if let Err(response) = limit(&data, &ip_limit, &data.env.rate_limit.register_ip).await {
+        return response;
+    }
+
     if let Ok(true) = user_exists(&pool, &body.email).await {
         return HttpResponse::Conflict()
             .json(serde_json::json!({"status": "fail","message": "Email already exist"}));
@@ -93,6 +235,11 @@ async fn register_user_handler(
 
     match create_user(pool, &body.email, &body.password, &body.name).await {
         Ok(user) => {
+            // письмо можно запросить повторно, поэтому регистрацию из-за него не роняем
+            if let Err(e) = send_verification_email(&data, &user).await {
+                log::error!("Failed to send verification email to {}: {}", user.email, e);
+            }
+
             let json_response = UserResponse {
                 status: "success".to_string(),
                 data: UserData {
@@ -111,14 +258,30 @@ async fn register_user_handler(
 
 #[post("/auth/login")]
 async fn login_user_handler(
+    req: HttpRequest,
     body: web::Json<LoginUserSchema>,
     data: web::Data<AppState>,
 ) -> impl Responder {
     let pool = data.pool.clone();
 
+    // по IP считаем все попытки, по аккаунту — только неудачные
+    let ip_limit = ip_key(&data, &req, "login");
+    if let Err(response) = limit(&data, &ip_limit, &data.env.rate_limit.login_ip).await {
+        return response;
+    }
+    let account_limit = account_key("login", &body.email);
+    if let Err(response) = ensure_not_locked(&data, &account_limit).await {
+        return response;
+    }
+
     let user = match get_user_by_email_and_password(&pool, &body.email, &body.password).await {
         Ok(Some(user)) => user,
         Ok(None) => {
+            if let Err(response) =
+                limit(&data, &account_limit, &data.env.rate_limit.login_account).await
+            {
+                return response;
+            }
             return HttpResponse::Unauthorized().json(
                 serde_json::json!({"status": "fail", "message": "Invalid email or password"}),
             );
@@ -132,52 +295,75 @@ async fn login_user_handler(
         }
     };
 
-    if user.provider == "Google" {
-        return HttpResponse::Unauthorized()
-            .json(serde_json::json!({"status": "fail", "message": "Use Google OAuth2 instead"}));
+    if data.env.require_verified_email && !user.verified {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({"status": "fail", "message": "Email is not verified"}));
+    }
+    if user.suspended_at.is_some() {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({"status": "fail", "message": "Account is suspended"}));
+    }
+    // админ потребовал сменить пароль: ссылка на сброс уже ушла на почту
+    if user.password_reset_required {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "status": "fail",
+            "message": "Password reset is required, check your email"
+        }));
     }
 
-    let jwt_secret = data.env.jwt_secret.to_owned();
-    let now = Utc::now();
-    let iat = now.timestamp() as usize;
-    let exp = (now + Duration::minutes(data.env.jwt_max_age)).timestamp() as usize;
-    let claims: TokenClaims = TokenClaims {
-        sub: user.id.unwrap().to_string(),
-        exp,
-        iat,
-    };
+    let user_id = user.id.unwrap();
+    reset(&data, &account_limit).await;
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(jwt_secret.as_ref()),
-    )
-    .unwrap();
+    // с включённой 2FA сессию выдаст /auth/2fa/login после проверки кода
+    match begin_two_factor_login(&data, user_id).await {
+        Ok(Some(pre_auth_token)) => {
+            return HttpResponse::Ok().json(serde_json::json!({
+                "status": "2fa_required",
+                "pre_auth_token": pre_auth_token
+            }));
+        }
+        Ok(None) => {}
+        Err(response) => return response,
+    }
 
-    let cookie = Cookie::build("token", token)
-        .path("/")
-        .max_age(ActixWebDuration::new(60 * data.env.jwt_max_age, 0))
-        .http_only(true)
-        .finish();
+    notify_login(&data, &req, user_id, "password").await;
 
-    println!("{}", cookie);
+    let (access, refresh) = match start_session(&data, &req, user_id).await {
+        Ok(cookies) => cookies,
+        Err(response) => return response,
+    };
 
     HttpResponse::Ok()
-        .cookie(cookie)
+        .cookie(access)
+        .cookie(refresh)
         .json(serde_json::json!({"status": "success", "user": user_to_response(&user)}))
 }
 
 #[get("/auth/logout")]
-async fn logout_handler(_: AuthenticationGuard) -> impl Responder {
-    let cookie = Cookie::build("token", "")
-        .path("/")
-        .max_age(ActixWebDuration::new(-1, 0))
-        .http_only(true)
-        .finish();
+async fn logout_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
 
-    HttpResponse::Ok()
-        .cookie(cookie)
-        .json(serde_json::json!({"status": "success"}))
+    let current_session = match auth_guard.require_session() {
+        Ok(session_id) => session_id,
+        Err(response) => return response,
+    };
+
+    if let Err(e) = revoke_session(&data.pool, current_session, user_id).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+
+    let mut response = HttpResponse::Ok();
+    clear_session_cookies(&mut response);
+    response.json(serde_json::json!({"status": "success"}))
 }
 
 #[get("/users/me")]
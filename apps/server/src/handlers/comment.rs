@@ -0,0 +1,327 @@
+use actix_web::{HttpResponse, Responder, delete, get, post, put, web};
+use uuid::Uuid;
+
+use super::{auth::AuthenticationGuard, notification::notify};
+use crate::{
+    graph::node_tree_id,
+    model::{
+        AppState, Comment, CommentSubject, CommentsQuery, CreateCommentSchema, NewNotification,
+        NotificationEvent, TreeRole, UpdateCommentSchema,
+    },
+    repo::{
+        create_comment, delete_comment, filter_tree_members, get_comment, get_comments_for_subject,
+        update_comment,
+    },
+};
+
+const MAX_COMMENT_LENGTH: usize = 10_000;
+
+/// Уведомляет упомянутых, кроме автора и тех, кто уже был упомянут раньше.
+async fn notify_mentions(data: &AppState, comment: &Comment, already_mentioned: &[Uuid]) {
+    let recipients: Vec<Uuid> = comment
+        .mentions
+        .iter()
+        .filter(|id| **id != comment.author_id && !already_mentioned.contains(id))
+        .copied()
+        .collect();
+
+    notify(
+        data,
+        &recipients,
+        NewNotification {
+            event: NotificationEvent::Mention,
+            actor_id: Some(comment.author_id),
+            tree_id: Some(comment.tree_id),
+            data: serde_json::json!({
+                "comment_id": comment.id,
+                "subject_type": comment.subject_type,
+                "subject_id": comment.subject_id
+            }),
+        },
+    )
+    .await;
+}
+
+async fn subject_tree(
+    data: &AppState,
+    subject_type: CommentSubject,
+    subject_id: &str,
+) -> Result<Uuid, HttpResponse> {
+    match node_tree_id(&data.graph, subject_type.label(), subject_id).await {
+        Ok(Some(tree_id)) => Ok(tree_id),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Subject not found"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+/// Читать и писать комментарии может любой участник дерева.
+/// Токену для записи нужен ещё и доступ write (см. require_write).
+async fn member_role(
+    data: &AppState,
+    tree_id: Uuid,
+    auth_guard: &AuthenticationGuard,
+) -> Result<TreeRole, HttpResponse> {
+    match auth_guard.tree_role(data, tree_id).await {
+        Ok(Some(role)) => Ok(role),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Subject not found"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+fn validate_body(body: &str) -> Result<(), HttpResponse> {
+    if body.trim().is_empty() {
+        return Err(HttpResponse::BadRequest()
+            .json(serde_json::json!({"status": "fail", "message": "Comment is empty"})));
+    }
+    if body.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(HttpResponse::BadRequest()
+            .json(serde_json::json!({"status": "fail", "message": "Comment is too long"})));
+    }
+
+    Ok(())
+}
+
+/// Упоминать можно только участников дерева: иначе через упоминания
+/// уведомления уходили бы посторонним.
+async fn validate_mentions(
+    data: &AppState,
+    tree_id: Uuid,
+    mentions: &mut Vec<Uuid>,
+) -> Result<(), HttpResponse> {
+    mentions.sort();
+    mentions.dedup();
+
+    if mentions.is_empty() {
+        return Ok(());
+    }
+
+    match filter_tree_members(&data.pool, tree_id, mentions).await {
+        Ok(members) if members.len() == mentions.len() => Ok(()),
+        Ok(_) => Err(HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "Mentioned users must be tree members"}),
+        )),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+#[get("/comments")]
+async fn get_comments_handler(
+    auth_guard: AuthenticationGuard,
+    query: web::Query<CommentsQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let tree_id = match subject_tree(&data, query.subject_type, &query.subject_id).await {
+        Ok(tree_id) => tree_id,
+        Err(response) => return response,
+    };
+    if let Err(response) = member_role(&data, tree_id, &auth_guard).await {
+        return response;
+    }
+
+    match get_comments_for_subject(&data.pool, query.subject_type.as_str(), &query.subject_id).await
+    {
+        Ok(comments) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "comments": comments}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[post("/comments")]
+async fn create_comment_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<CreateCommentSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    let mut body = body.into_inner();
+
+    if let Err(response) = validate_body(&body.body) {
+        return response;
+    }
+
+    let tree_id = match subject_tree(&data, body.subject_type, &body.subject_id).await {
+        Ok(tree_id) => tree_id,
+        Err(response) => return response,
+    };
+    if let Err(response) = member_role(&data, tree_id, &auth_guard).await {
+        return response;
+    }
+    if let Err(response) = auth_guard.require_write(tree_id) {
+        return response;
+    }
+
+    // ответ должен относиться к тому же узлу, что и родитель
+    if let Some(parent_id) = body.parent_id {
+        match get_comment(&data.pool, parent_id).await {
+            Ok(Some(parent))
+                if parent.subject_type == body.subject_type.as_str()
+                    && parent.subject_id == body.subject_id => {}
+            Ok(_) => {
+                return HttpResponse::BadRequest().json(
+                    serde_json::json!({"status": "fail", "message": "Parent comment not found"}),
+                );
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": "error",
+                    "info": e.to_string()
+                }));
+            }
+        }
+    }
+
+    if let Err(response) = validate_mentions(&data, tree_id, &mut body.mentions).await {
+        return response;
+    }
+
+    let comment = match create_comment(&data.pool, tree_id, user_id, &body).await {
+        Ok(id) => get_comment(&data.pool, id).await,
+        Err(e) => Err(e),
+    };
+
+    match comment {
+        Ok(comment) => {
+            if let Some(comment) = &comment {
+                notify_mentions(&data, comment, &[]).await;
+            }
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "comment": comment}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[put("/comments/{id}")]
+async fn update_comment_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdateCommentSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    let mut body = body.into_inner();
+
+    if let Err(response) = validate_body(&body.body) {
+        return response;
+    }
+
+    let comment = match get_comment(&data.pool, path.into_inner()).await {
+        Ok(Some(comment)) if comment.deleted_at.is_none() => comment,
+        Ok(_) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Comment not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    // править текст может только автор, даже владелец дерева — лишь удалить
+    if comment.author_id != user_id {
+        return HttpResponse::Forbidden().json(
+            serde_json::json!({"status": "fail", "message": "Only the author can edit a comment"}),
+        );
+    }
+    if let Err(response) = member_role(&data, comment.tree_id, &auth_guard).await {
+        return response;
+    }
+    if let Err(response) = auth_guard.require_write(comment.tree_id) {
+        return response;
+    }
+    if let Err(response) = validate_mentions(&data, comment.tree_id, &mut body.mentions).await {
+        return response;
+    }
+
+    let updated = match update_comment(&data.pool, comment.id, &body.body, &body.mentions).await {
+        Ok(()) => get_comment(&data.pool, comment.id).await,
+        Err(e) => Err(e),
+    };
+
+    match updated {
+        Ok(updated) => {
+            if let Some(updated) = &updated {
+                notify_mentions(&data, updated, &comment.mentions).await;
+            }
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "comment": updated}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[delete("/comments/{id}")]
+async fn delete_comment_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let comment = match get_comment(&data.pool, path.into_inner()).await {
+        Ok(Some(comment)) if comment.deleted_at.is_none() => comment,
+        Ok(_) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Comment not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    // удалить может автор или владелец дерева (модерация)
+    let role = match member_role(&data, comment.tree_id, &auth_guard).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    if let Err(response) = auth_guard.require_write(comment.tree_id) {
+        return response;
+    }
+    if comment.author_id != user_id && role != TreeRole::Owner {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "status": "fail",
+            "message": "Only the author or the tree owner can delete a comment"
+        }));
+    }
+
+    match delete_comment(&data.pool, comment.id, user_id).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
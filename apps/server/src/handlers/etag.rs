@@ -0,0 +1,58 @@
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::{
+        StatusCode,
+        header::{self, ETag, EntityTag, Header, IfMatch},
+    },
+};
+use serde::Serialize;
+
+/// ETag персоны или союза — номер версии в кавычках.
+pub(super) fn etag(version: i64) -> ETag {
+    ETag(EntityTag::new_strong(version.to_string()))
+}
+
+/// Ответ 412: правка опоздала. Отдаём текущее состояние под ключом `key`,
+/// чтобы клиент показал слияние без лишнего запроса.
+pub(super) fn precondition_failed<T: Serialize>(
+    key: &str,
+    current: &T,
+    version: i64,
+) -> HttpResponse {
+    let mut body = serde_json::json!({
+        "status": "fail",
+        "message": "Resource was modified by someone else"
+    });
+    body[key] = serde_json::to_value(current).unwrap_or_default();
+
+    HttpResponse::PreconditionFailed()
+        .insert_header(etag(version))
+        .json(body)
+}
+
+/// Правки принимаются только с If-Match текущей версии. Без заголовка — 428:
+/// иначе клиент, не знающий о версиях, молча затрёт чужую правку.
+pub(super) fn check_if_match<T: Serialize>(
+    req: &HttpRequest,
+    key: &str,
+    current: &T,
+    version: i64,
+) -> Result<(), HttpResponse> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return Err(HttpResponse::build(StatusCode::PRECONDITION_REQUIRED).json(
+            serde_json::json!({"status": "fail", "message": "If-Match header is required"}),
+        ));
+    }
+
+    let matches = match IfMatch::parse(req) {
+        Ok(IfMatch::Any) => true,
+        Ok(IfMatch::Items(tags)) => tags.iter().any(|tag| tag.strong_eq(&etag(version).0)),
+        Err(_) => false,
+    };
+
+    if matches {
+        Ok(())
+    } else {
+        Err(precondition_failed(key, current, version))
+    }
+}
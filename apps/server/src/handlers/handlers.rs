@@ -1,9 +1,64 @@
 use actix_web::web;
 
 use super::{
+    access_token::{
+        create_access_token_handler, get_access_tokens_handler, revoke_access_token_handler,
+    },
+    admin::{
+        admin_delete_user_handler, admin_force_password_reset_handler, admin_get_audit_log_handler,
+        admin_get_storage_handler, admin_get_users_handler, admin_impersonate_user_handler,
+        admin_suspend_user_handler, admin_unsuspend_user_handler,
+    },
     auth::{get_me_handler, login_user_handler, logout_handler, register_user_handler},
+    comment::{
+        create_comment_handler, delete_comment_handler, get_comments_handler,
+        update_comment_handler,
+    },
     common::health_checker_handler,
-    oauth::google_oauth_handler,
+    history::{get_person_history_handler, get_tree_history_handler},
+    identity::{
+        get_identities_handler, link_identity_handler, remove_password_handler,
+        set_password_handler, unlink_identity_handler,
+    },
+    notification::{
+        get_notifications_handler, get_unread_count_handler, mark_all_notifications_read_handler,
+        mark_notification_read_handler,
+    },
+    oauth::{get_oauth_providers_handler, oauth_authorize_handler, oauth_callback_handler},
+    password::{change_password_handler, forgot_password_handler, reset_password_handler},
+    person::{
+        create_person_handler, get_person_handler, get_tree_persons_handler,
+        search_persons_handler, set_person_names_handler, update_person_handler,
+        update_person_privacy_handler,
+    },
+    proposal::{
+        accept_proposal_handler, create_proposal_handler, get_my_proposals_handler,
+        get_tree_proposals_handler, reject_proposal_handler,
+    },
+    realtime::realtime_handler,
+    relationship::{
+        create_partnership_handler, delete_partnership_handler, get_person_partnerships_handler,
+        link_parent_handler, link_siblings_handler, update_partnership_handler,
+    },
+    revert::{revert_change_handler, rollback_tree_handler},
+    search::search_handler,
+    session::{
+        get_sessions_handler, refresh_handler, revoke_all_sessions_handler, revoke_session_handler,
+    },
+    share::{
+        create_share_link_handler, get_share_links_handler, get_shared_tree_handler,
+        revoke_share_link_handler,
+    },
+    trash::{
+        delete_person_handler, delete_tree_handler, get_trashed_trees_handler,
+        get_tree_trash_handler, restore_person_handler, restore_tree_handler,
+    },
+    tree::{add_tree_member_handler, create_tree_handler, get_trees_handler},
+    two_factor::{
+        disable_two_factor_handler, enable_two_factor_handler, regenerate_recovery_codes_handler,
+        setup_two_factor_handler, two_factor_login_handler,
+    },
+    verification::{resend_verification_handler, verify_email_handler},
 };
 
 pub fn config(conf: &mut web::ServiceConfig) {
@@ -11,9 +66,86 @@ pub fn config(conf: &mut web::ServiceConfig) {
         .service(health_checker_handler)
         .service(register_user_handler)
         .service(login_user_handler)
-        .service(google_oauth_handler)
+        .service(get_oauth_providers_handler)
+        .service(oauth_authorize_handler)
+        .service(oauth_callback_handler)
         .service(logout_handler)
-        .service(get_me_handler);
+        .service(refresh_handler)
+        .service(verify_email_handler)
+        .service(resend_verification_handler)
+        .service(forgot_password_handler)
+        .service(reset_password_handler)
+        .service(change_password_handler)
+        .service(get_identities_handler)
+        .service(link_identity_handler)
+        .service(set_password_handler)
+        .service(remove_password_handler)
+        .service(unlink_identity_handler)
+        .service(two_factor_login_handler)
+        .service(setup_two_factor_handler)
+        .service(enable_two_factor_handler)
+        .service(disable_two_factor_handler)
+        .service(regenerate_recovery_codes_handler)
+        .service(get_sessions_handler)
+        .service(revoke_all_sessions_handler)
+        .service(revoke_session_handler)
+        .service(get_access_tokens_handler)
+        .service(create_access_token_handler)
+        .service(revoke_access_token_handler)
+        .service(get_me_handler)
+        .service(search_persons_handler)
+        .service(get_person_handler)
+        .service(update_person_privacy_handler)
+        .service(create_person_handler)
+        .service(update_person_handler)
+        .service(set_person_names_handler)
+        .service(get_person_history_handler)
+        .service(link_parent_handler)
+        .service(link_siblings_handler)
+        .service(get_person_partnerships_handler)
+        .service(create_partnership_handler)
+        .service(update_partnership_handler)
+        .service(delete_partnership_handler)
+        .service(search_handler)
+        .service(create_tree_handler)
+        .service(get_trees_handler)
+        .service(get_tree_persons_handler)
+        .service(add_tree_member_handler)
+        .service(get_tree_history_handler)
+        .service(revert_change_handler)
+        .service(rollback_tree_handler)
+        .service(delete_person_handler)
+        .service(restore_person_handler)
+        .service(get_tree_trash_handler)
+        .service(delete_tree_handler)
+        .service(get_trashed_trees_handler)
+        .service(restore_tree_handler)
+        .service(create_proposal_handler)
+        .service(get_my_proposals_handler)
+        .service(get_tree_proposals_handler)
+        .service(accept_proposal_handler)
+        .service(reject_proposal_handler)
+        .service(get_comments_handler)
+        .service(create_comment_handler)
+        .service(update_comment_handler)
+        .service(delete_comment_handler)
+        .service(get_notifications_handler)
+        .service(get_unread_count_handler)
+        .service(mark_all_notifications_read_handler)
+        .service(mark_notification_read_handler)
+        .service(realtime_handler)
+        .service(create_share_link_handler)
+        .service(get_share_links_handler)
+        .service(revoke_share_link_handler)
+        .service(get_shared_tree_handler)
+        .service(admin_get_users_handler)
+        .service(admin_get_storage_handler)
+        .service(admin_suspend_user_handler)
+        .service(admin_unsuspend_user_handler)
+        .service(admin_delete_user_handler)
+        .service(admin_impersonate_user_handler)
+        .service(admin_force_password_reset_handler)
+        .service(admin_get_audit_log_handler);
 
     conf.service(scope);
 }
@@ -0,0 +1,148 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::{
+    auth::AuthenticationGuard, notification::notify_person_creators, person::find_editable_person,
+    realtime::publish_change, revert::undo_change,
+};
+use crate::{
+    model::{AppState, HistoryQuery, NewChange, TreeRole},
+    repo::{get_person_history, get_tree_history, record_change},
+};
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 200;
+
+pub(super) fn snapshot<T: Serialize>(value: &T) -> Option<serde_json::Value> {
+    serde_json::to_value(value).ok()
+}
+
+/// Пишет изменение в журнал, рассылает его подписчикам дерева и уведомляет авторов
+/// затронутых персон. Правка в графе к этому моменту уже применена: если журнал
+/// записать не удалось, она откатывается, чтобы граф не разошёлся с историей.
+pub(super) async fn journal_change(data: &AppState, change: NewChange) -> Result<i64, sqlx::Error> {
+    match record_change(&data.pool, &change).await {
+        Ok(change_id) => {
+            announce_change(data, &change, change_id).await;
+            Ok(change_id)
+        }
+        Err(e) => {
+            log::error!(
+                "Failed to record {} {} of {}, undoing it: {}",
+                change.operation.as_str(),
+                change.entity.as_str(),
+                change.entity_id,
+                e
+            );
+            if let Err(undo_error) = undo_change(data, &change).await {
+                log::error!(
+                    "Failed to undo unrecorded {} {} of {}: {}",
+                    change.operation.as_str(),
+                    change.entity.as_str(),
+                    change.entity_id,
+                    undo_error
+                );
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Рассылает записанное изменение подписчикам дерева и уведомляет авторов персон.
+pub(super) async fn announce_change(data: &AppState, change: &NewChange, change_id: i64) {
+    publish_change(data, change, Some(change_id));
+    notify_person_creators(data, change, change_id).await;
+}
+
+/// journal_change для обработчиков: ошибка журнала сразу превращается в ответ 500.
+pub(super) async fn log_change(data: &AppState, change: NewChange) -> Result<(), HttpResponse> {
+    journal_change(data, change).await.map(|_| ()).map_err(|e| {
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))
+    })
+}
+
+fn paging(query: &HistoryQuery) -> (i64, i64, i64) {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    (page, per_page, (page - 1) * per_page)
+}
+
+// Снимки в журнале не проходят через скрытие живых, поэтому история — только редакторам
+#[get("/persons/{id}/history")]
+async fn get_person_history_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let person = match find_editable_person(&data, &path, &auth_guard).await {
+        Ok(person) => person,
+        Err(response) => return response,
+    };
+
+    let (page, per_page, offset) = paging(&query);
+
+    match get_person_history(&data.pool, &person.id, per_page, offset).await {
+        Ok(changes) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "page": page,
+            "per_page": per_page,
+            "changes": changes
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[get("/trees/{tree_id}/history")]
+async fn get_tree_history_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    query: web::Query<HistoryQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let tree_id = path.into_inner();
+
+    match auth_guard.tree_role(&data, tree_id).await {
+        Ok(Some(role)) if role >= TreeRole::Editor => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Forbidden()
+                .json(serde_json::json!({"status": "fail", "message": "Editor role is required"}));
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Tree not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    let (page, per_page, offset) = paging(&query);
+
+    match get_tree_history(&data.pool, tree_id, per_page, offset).await {
+        Ok(changes) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "page": page,
+            "per_page": per_page,
+            "changes": changes
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
@@ -0,0 +1,200 @@
+use actix_web::{HttpResponse, Responder, delete, get, post, web};
+use uuid::Uuid;
+
+use super::{auth::AuthenticationGuard, oauth::start_flow, password::validate_password};
+use crate::{
+    model::{AppState, OAuthAuthorizeQuery, SetPasswordSchema},
+    repo::{
+        count_login_methods, get_identities, get_user_by_id, remove_user_password, unlink_identity,
+        update_user_password,
+    },
+};
+
+fn last_method() -> HttpResponse {
+    HttpResponse::Conflict().json(serde_json::json!({
+        "status": "fail",
+        "message": "Cannot remove the last login method"
+    }))
+}
+
+/// Способы входа пользователя: пароль и привязанные внешние аккаунты.
+#[get("/auth/identities")]
+async fn get_identities_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    let has_password = match get_user_by_id(&data.pool, &auth_guard.user_id).await {
+        Ok(Some(user)) => !user.password.is_empty(),
+        Ok(None) => {
+            return HttpResponse::Unauthorized()
+                .json(serde_json::json!({"status": "fail", "message": "User not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    match get_identities(&data.pool, user_id).await {
+        Ok(identities) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "has_password": has_password,
+            "identities": identities
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Привязка внешнего аккаунта: тот же вход через провайдера, но без новой сессии.
+#[get("/auth/identities/{provider}/link")]
+async fn link_identity_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<String>,
+    query: web::Query<OAuthAuthorizeQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_own_session() {
+        return response;
+    }
+
+    start_flow(&data, &path, query.redirect.as_deref(), Some(user_id)).await
+}
+
+#[delete("/auth/identities/{id}")]
+async fn unlink_identity_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_own_session() {
+        return response;
+    }
+    let identity_id = path.into_inner();
+
+    match get_identities(&data.pool, user_id).await {
+        Ok(identities) if identities.iter().any(|i| i.id == identity_id) => {}
+        Ok(_) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Identity not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match unlink_identity(&data.pool, identity_id, user_id).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Ok(false) => last_method(),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Вход по паролю для того, кто заходил только через провайдеров.
+/// Сменить существующий пароль — /auth/change_password.
+#[post("/auth/identities/password")]
+async fn set_password_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<SetPasswordSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_own_session() {
+        return response;
+    }
+
+    match get_user_by_id(&data.pool, &auth_guard.user_id).await {
+        Ok(Some(user)) if user.password.is_empty() => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "status": "fail",
+                "message": "Password is already set, use change password instead"
+            }));
+        }
+        Ok(None) => {
+            return HttpResponse::Unauthorized()
+                .json(serde_json::json!({"status": "fail", "message": "User not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    if let Err(response) = validate_password(&body.password) {
+        return response;
+    }
+
+    match update_user_password(&data.pool, user_id, &body.password).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[delete("/auth/identities/password")]
+async fn remove_password_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_own_session() {
+        return response;
+    }
+
+    match count_login_methods(&data.pool, user_id).await {
+        Ok(count) if count > 1 => {}
+        Ok(_) => return last_method(),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match remove_user_password(&data.pool, user_id).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Ok(false) => last_method(),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
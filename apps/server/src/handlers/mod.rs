@@ -1,8 +1,34 @@
+// Вспомогательные проверки возвращают готовый ответ об ошибке (Result<_, HttpResponse>),
+// чтобы обработчик просто отдал его; размер HttpResponse здесь не важен
+#![allow(clippy::result_large_err)]
+
+mod access_token;
+mod action_token;
+mod admin;
 mod auth;
+mod comment;
 mod common;
+mod etag;
 mod handlers;
+mod history;
+mod identity;
 mod model;
+mod notification;
 mod oauth;
+mod password;
+mod person;
+mod proposal;
+mod rate_limit;
+mod realtime;
+mod relationship;
+mod revert;
+mod search;
+mod session;
+mod share;
+mod trash;
+mod tree;
+mod two_factor;
+mod verification;
 
 pub use handlers::config;
-pub use oauth::GoogleUserResult;
+pub use share::SHARE_PASSWORD_HEADER;
@@ -0,0 +1,217 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use uuid::Uuid;
+
+use super::{auth::AuthenticationGuard, session::client_info};
+use crate::{
+    graph::Person,
+    model::{
+        AppState, ChangeEntity, NewChange, NewNotification, NotificationEvent, NotificationsQuery,
+    },
+    repo::{
+        create_notifications, get_notifications, get_unread_notification_count,
+        mark_all_notifications_read, mark_notification_read, register_device,
+    },
+};
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 200;
+
+/// Рассылает уведомление. Само действие к этому моменту уже выполнено,
+/// поэтому ошибку только логируем.
+pub(super) async fn notify(data: &AppState, recipients: &[Uuid], notification: NewNotification) {
+    if recipients.is_empty() {
+        return;
+    }
+
+    if let Err(e) = create_notifications(&data.pool, recipients, &notification).await {
+        log::error!(
+            "Failed to create {} notification: {}",
+            notification.event.as_str(),
+            e
+        );
+    }
+}
+
+/// Авторам затронутых персон — о чужой правке. Свои правки не уведомляют.
+pub(super) async fn notify_person_creators(data: &AppState, change: &NewChange, change_id: i64) {
+    let creators = match Person::creators(&data.graph, &change.person_ids).await {
+        Ok(creators) => creators,
+        Err(e) => {
+            log::error!("Failed to load creators of change {}: {}", change_id, e);
+            return;
+        }
+    };
+
+    let recipients: Vec<Uuid> = creators
+        .into_iter()
+        .filter(|id| *id != change.user_id)
+        .collect();
+
+    let event = match change.entity {
+        ChangeEntity::Person | ChangeEntity::Names => NotificationEvent::PersonChanged,
+        ChangeEntity::ParentLink | ChangeEntity::SiblingLink | ChangeEntity::Partnership => {
+            NotificationEvent::RelationshipChanged
+        }
+    };
+
+    notify(
+        data,
+        &recipients,
+        NewNotification {
+            event,
+            actor_id: Some(change.user_id),
+            tree_id: change.tree_id,
+            data: serde_json::json!({
+                "change_id": change_id,
+                "entity_type": change.entity.as_str(),
+                "entity_id": change.entity_id,
+                "operation": change.operation.as_str(),
+                "person_ids": change.person_ids
+            }),
+        },
+    )
+    .await;
+}
+
+/// Запоминает устройство входа и, если оно новое, предупреждает владельца аккаунта.
+/// Устройство различаем по User-Agent: отпечаток грубый, но без клиентского кода лучше не сделать.
+pub(super) async fn notify_login(data: &AppState, req: &HttpRequest, user_id: Uuid, method: &str) {
+    let (user_agent, ip) = client_info(data, req);
+
+    match register_device(&data.pool, user_id, &user_agent, ip.as_deref()).await {
+        Ok(true) => {
+            notify(
+                data,
+                &[user_id],
+                NewNotification {
+                    event: NotificationEvent::NewDeviceLogin,
+                    actor_id: None,
+                    tree_id: None,
+                    data: serde_json::json!({
+                        "user_agent": user_agent,
+                        "ip": ip,
+                        "method": method
+                    }),
+                },
+            )
+            .await
+        }
+        Ok(false) => {}
+        Err(e) => log::error!("Failed to register device of user {}: {}", user_id, e),
+    }
+}
+
+#[get("/notifications")]
+async fn get_notifications_handler(
+    auth_guard: AuthenticationGuard,
+    query: web::Query<NotificationsQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    match get_notifications(
+        &data.pool,
+        user_id,
+        query.unread_only,
+        per_page,
+        (page - 1) * per_page,
+    )
+    .await
+    {
+        Ok(notifications) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "page": page,
+            "per_page": per_page,
+            "notifications": notifications
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[get("/notifications/unread_count")]
+async fn get_unread_count_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    match get_unread_notification_count(&data.pool, user_id).await {
+        Ok(count) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "count": count}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[post("/notifications/{id}/read")]
+async fn mark_notification_read_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    match mark_notification_read(&data.pool, path.into_inner(), user_id).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Ok(false) => HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Notification not found"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[post("/notifications/read_all")]
+async fn mark_all_notifications_read_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    match mark_all_notifications_read(&data.pool, user_id).await {
+        Ok(marked) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "marked": marked}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
@@ -1,90 +1,280 @@
-use std::error::Error;
-
-use crate::model::{AppState, QueryCode, TokenClaims};
-use crate::repo::{get_user_by_email, insert_google_user, update_google_user};
-use actix_web::http::header::LOCATION;
-use actix_web::{
-    HttpResponse, Responder,
-    cookie::{Cookie, time::Duration as ActixWebDuration},
-    get, web,
+use super::{
+    notification::notify_login, session::start_session, two_factor::begin_two_factor_login,
+};
+use crate::model::{AppState, OAuthAuthorizeQuery, OAuthFlowClaims, QueryCode};
+use crate::oidc::{AuthRequest, OidcUser, Provider};
+use crate::repo::{
+    get_identities, get_user_by_email, insert_oauth_user, link_identity, use_identity,
 };
+use actix_web::cookie::{Cookie, SameSite, time::Duration as ActixWebDuration};
+use actix_web::http::header::LOCATION;
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, Responder, get, web};
 use chrono::{Duration, prelude::*};
-use jsonwebtoken::{EncodingKey, Header, encode};
-use reqwest::{Client, Url};
-use serde::Deserialize;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use uuid::Uuid;
 
-#[derive(Deserialize)]
-pub struct OAuthResponse {
-    pub access_token: String,
-    pub id_token: String,
+const OAUTH_FLOW_COOKIE: &str = "oauth_flow";
+const OAUTH_FLOW_COOKIE_PATH: &str = "/api/sessions/oauth";
+const OAUTH_FLOW_TTL_MINUTES: i64 = 10;
+
+fn find_provider<'a>(data: &'a AppState, name: &str) -> Result<&'a Provider, HttpResponse> {
+    data.oidc.get(name).ok_or_else(|| {
+        HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Unknown login provider"}))
+    })
+}
+
+/// Путь на клиенте, куда вернуть после входа, если он есть в allow-list.
+/// Принимаем только путь от корня: "//evil.com" или "@evil.com" после
+/// client_origin увели бы на чужой сайт.
+fn allowed_redirect(data: &AppState, redirect: Option<&str>) -> Option<String> {
+    let allowed = &data.env.oauth_redirect_paths;
+    let Some(redirect) = redirect else {
+        return Some(allowed.first().cloned().unwrap_or_else(|| "/".to_string()));
+    };
+
+    if !redirect.starts_with('/')
+        || redirect.starts_with("//")
+        || redirect.contains('\\')
+        || redirect.chars().any(char::is_control)
+    {
+        return None;
+    }
+
+    let path = redirect.split(['?', '#']).next().unwrap_or_default();
+    allowed
+        .iter()
+        .any(|prefix| {
+            path == prefix || path.starts_with(&format!("{}/", prefix.trim_end_matches('/')))
+        })
+        .then(|| redirect.to_string())
 }
 
-#[derive(Deserialize)]
-pub struct GoogleUserResult {
-    pub email: String,
-    pub verified_email: bool,
-    pub name: String,
-    pub picture: String,
+fn flow_cookie(value: String, max_age: ActixWebDuration) -> Cookie<'static> {
+    // Lax: cookie должна прийти с редиректом от провайдера, это навигация с чужого сайта
+    Cookie::build(OAUTH_FLOW_COOKIE, value)
+        .path(OAUTH_FLOW_COOKIE_PATH)
+        .max_age(max_age)
+        .same_site(SameSite::Lax)
+        .http_only(true)
+        .finish()
 }
 
-pub async fn request_token(
-    authorization_code: &str,
-    data: &web::Data<AppState>,
-) -> Result<OAuthResponse, Box<dyn Error>> {
-    let redirect_url = data.env.google_oauth_redirect_url.to_owned();
-    let client_secret = data.env.google_oauth_client_secret.to_owned();
-    let client_id = data.env.google_oauth_client_id.to_owned();
-
-    let root_url = "https://oauth2.googleapis.com/token";
-    let client = Client::new();
-
-    let params = [
-        ("grant_type", "authorization_code"),
-        ("redirect_uri", redirect_url.as_str()),
-        ("client_id", client_id.as_str()),
-        ("code", authorization_code),
-        ("client_secret", client_secret.as_str()),
-    ];
-    let response = client.post(root_url).form(&params).send().await?;
-
-    if response.status().is_success() {
-        let oauth_response = response.json::<OAuthResponse>().await?;
-        Ok(oauth_response)
-    } else {
-        let message = "An error occurred while trying to retrieve access token.";
-        Err(From::from(message))
+fn clear_flow_cookie(response: &mut HttpResponseBuilder) {
+    response.cookie(flow_cookie(String::new(), ActixWebDuration::new(-1, 0)));
+}
+
+/// Сверяет ответ провайдера с cookie, выданной при старте входа.
+fn check_flow(
+    req: &HttpRequest,
+    data: &AppState,
+    provider: &str,
+    state: &str,
+) -> Result<OAuthFlowClaims, HttpResponse> {
+    let invalid = || {
+        let mut response = HttpResponse::BadRequest();
+        clear_flow_cookie(&mut response);
+        response.json(serde_json::json!({
+            "status": "fail",
+            "message": "Invalid or expired login attempt, please try again"
+        }))
+    };
+
+    let Some(cookie) = req.cookie(OAUTH_FLOW_COOKIE) else {
+        return Err(invalid());
+    };
+    let Ok(decoded) = decode::<OAuthFlowClaims>(
+        cookie.value(),
+        &DecodingKey::from_secret(data.env.jwt_secret.as_ref()),
+        &Validation::new(Algorithm::HS256),
+    ) else {
+        return Err(invalid());
+    };
+
+    let claims = decoded.claims;
+    if claims.provider != provider || claims.state != state {
+        return Err(invalid());
     }
+
+    Ok(claims)
 }
 
-pub async fn get_google_user(
-    access_token: &str,
-    id_token: &str,
-) -> Result<GoogleUserResult, Box<dyn Error>> {
-    let client = Client::new();
-    let mut url = Url::parse("https://www.googleapis.com/oauth2/v1/userinfo").unwrap();
-    url.query_pairs_mut().append_pair("alt", "json");
-    url.query_pairs_mut()
-        .append_pair("access_token", access_token);
-
-    let response = client.get(url).bearer_auth(id_token).send().await?;
-
-    if response.status().is_success() {
-        let user_info = response.json::<GoogleUserResult>().await?;
-        Ok(user_info)
-    } else {
-        let message = "An error occurred while trying to retrieve user information.";
-        Err(From::from(message))
+/// Отправляет на страницу входа провайдера. state, nonce и PKCE-верификатор
+/// запоминаются в подписанной cookie; redirect — путь на клиенте после входа.
+/// С `link_user` вход привяжет аккаунт провайдера к этому пользователю.
+pub(super) async fn start_flow(
+    data: &AppState,
+    provider: &str,
+    redirect: Option<&str>,
+    link_user: Option<Uuid>,
+) -> HttpResponse {
+    let provider = match find_provider(data, provider) {
+        Ok(provider) => provider,
+        Err(response) => return response,
+    };
+
+    let Some(redirect) = allowed_redirect(data, redirect) else {
+        return HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "Redirect path is not allowed"}),
+        );
+    };
+
+    let request = AuthRequest::generate();
+    let url = match provider.authorization_url(&request).await {
+        Ok(url) => url,
+        Err(e) => {
+            return HttpResponse::BadGateway()
+                .json(serde_json::json!({"status": "fail", "message": e.to_string()}));
+        }
+    };
+
+    let claims = OAuthFlowClaims {
+        provider: provider.config.name.clone(),
+        state: request.state,
+        nonce: request.nonce,
+        code_verifier: request.code_verifier,
+        redirect,
+        link_user,
+        exp: (Utc::now() + Duration::minutes(OAUTH_FLOW_TTL_MINUTES)).timestamp() as usize,
+    };
+    let flow = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(data.env.jwt_secret.as_ref()),
+    )
+    .unwrap();
+
+    HttpResponse::Found()
+        .append_header((LOCATION, url))
+        .cookie(flow_cookie(
+            flow,
+            ActixWebDuration::minutes(OAUTH_FLOW_TTL_MINUTES),
+        ))
+        .finish()
+}
+
+/// Пользователь, который входит через провайдера. Аккаунт с тем же адресом
+/// молча не присваиваем: его надо привязать явно, войдя прежним способом.
+async fn resolve_login_user(
+    data: &AppState,
+    provider: &str,
+    oidc_user: OidcUser,
+) -> Result<Uuid, HttpResponse> {
+    let internal = |e: sqlx::Error| {
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))
+    };
+
+    if let Some(user_id) = use_identity(&data.pool, provider, &oidc_user.subject)
+        .await
+        .map_err(internal)?
+    {
+        return Ok(user_id);
+    }
+
+    let Some(user) = get_user_by_email(&data.pool, &oidc_user.email)
+        .await
+        .map_err(internal)?
+    else {
+        let id = Uuid::new_v4();
+        log::info!(
+            "Creating user {} for {} account {}",
+            id,
+            provider,
+            oidc_user.subject
+        );
+        insert_oauth_user(&data.pool, id, provider, oidc_user)
+            .await
+            .map_err(internal)?;
+        return Ok(id);
+    };
+    let user_id = user.id.unwrap();
+
+    // аккаунты, заведённые через провайдера до таблицы user_identities: sub тогда
+    // не хранили, поэтому привязываем при первом входе по подтверждённому адресу
+    let legacy = user.provider == provider
+        && oidc_user.email_verified
+        && get_identities(&data.pool, user_id)
+            .await
+            .map_err(internal)?
+            .iter()
+            .all(|identity| identity.provider != provider);
+    if !legacy {
+        return Err(HttpResponse::Conflict().json(serde_json::json!({
+            "status": "fail",
+            "message": format!(
+                "An account with this email already exists. Sign in and link {} in settings",
+                provider
+            )
+        })));
     }
+
+    link_identity(
+        &data.pool,
+        user_id,
+        provider,
+        &oidc_user.subject,
+        &oidc_user.email,
+    )
+    .await
+    .map_err(internal)?;
+
+    Ok(user_id)
+}
+
+/// Провайдеры для кнопок входа на клиенте.
+#[get("/auth/providers")]
+async fn get_oauth_providers_handler(data: web::Data<AppState>) -> impl Responder {
+    let providers: Vec<serde_json::Value> = data
+        .oidc
+        .providers()
+        .into_iter()
+        .map(|provider| {
+            serde_json::json!({
+                "name": provider.name,
+                "display_name": provider.display_name.as_deref().unwrap_or(&provider.name)
+            })
+        })
+        .collect();
+
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "providers": providers}))
+}
+
+#[get("/sessions/oauth/{provider}/authorize")]
+async fn oauth_authorize_handler(
+    path: web::Path<String>,
+    query: web::Query<OAuthAuthorizeQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    start_flow(&data, &path, query.redirect.as_deref(), None).await
 }
 
-#[get("/sessions/oauth/google")]
-async fn google_oauth_handler(
+#[get("/sessions/oauth/{provider}")]
+async fn oauth_callback_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
     query: web::Query<QueryCode>,
     data: web::Data<AppState>,
 ) -> impl Responder {
     let code = &query.code;
-    let state = &query.state;
+
+    let provider = match find_provider(&data, &path) {
+        Ok(provider) => provider,
+        Err(response) => return response,
+    };
+    let provider_name = provider.config.name.as_str();
+
+    let flow = match check_flow(&req, &data, provider_name, &query.state) {
+        Ok(flow) => flow,
+        Err(response) => return response,
+    };
+    let request = AuthRequest {
+        state: flow.state,
+        nonce: flow.nonce,
+        code_verifier: flow.code_verifier,
+    };
 
     if code.is_empty() {
         return HttpResponse::Unauthorized().json(
@@ -92,86 +282,76 @@ async fn google_oauth_handler(
         );
     }
 
-    let token_response = request_token(code.as_str(), &data).await;
-    if token_response.is_err() {
-        let message = token_response.err().unwrap().to_string();
-        return HttpResponse::BadGateway()
-            .json(serde_json::json!({"status": "fail", "message": message}));
-    }
-
-    let token_response = token_response.unwrap();
-    let google_user = get_google_user(&token_response.access_token, &token_response.id_token).await;
-    if google_user.is_err() {
-        let message = google_user.err().unwrap().to_string();
-        return HttpResponse::BadGateway()
-            .json(serde_json::json!({"status": "fail", "message": message}));
-    }
-
-    let google_user = google_user.unwrap();
-
-    let pool = data.pool.clone();
-    let email = google_user.email.to_lowercase();
-    let user = match get_user_by_email(&pool, &email).await {
-        Ok(Some(user)) => Some(user),
-        Ok(None) => None,
+    let oidc_user = match provider.fetch_user(code, &request).await {
+        Ok(oidc_user) => oidc_user,
         Err(e) => {
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "status": "error",
-                "info": e.to_string()
-            }));
+            return HttpResponse::BadGateway()
+                .json(serde_json::json!({"status": "fail", "message": e.to_string()}));
         }
     };
 
-    let user_id: String;
-
-    if let Some(user) = user {
-        user_id = user.id.unwrap().to_string();
+    let frontend_origin = data.env.client_origin.to_owned();
+    let mut response = HttpResponse::Found();
+    clear_flow_cookie(&mut response);
 
-        if let Err(e) = update_google_user(&pool, &user_id, &email, &google_user.picture).await {
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "status": "error",
-                "info": e.to_string()
-            }));
+    if let Some(user_id) = flow.link_user {
+        match link_identity(
+            &data.pool,
+            user_id,
+            provider_name,
+            &oidc_user.subject,
+            &oidc_user.email,
+        )
+        .await
+        {
+            Ok(owner) if owner == user_id => {}
+            Ok(_) => {
+                return HttpResponse::Conflict().json(serde_json::json!({
+                    "status": "fail",
+                    "message": "This account is already linked to another user"
+                }));
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": "error",
+                    "info": e.to_string()
+                }));
+            }
         }
-    } else {
-        let id = Uuid::new_v4();
-        user_id = id.to_owned().to_string();
 
-        if let Err(e) = insert_google_user(&pool, id, google_user).await {
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "status": "error",
-                "info": e.to_string()
-            }));
-        }
+        response.append_header((LOCATION, format!("{}{}", frontend_origin, flow.redirect)));
+        return response.finish();
     }
 
-    let jwt_secret = data.env.jwt_secret.to_owned();
-    let now = Utc::now();
-    let iat = now.timestamp() as usize;
-    let exp = (now + Duration::minutes(data.env.jwt_max_age)).timestamp() as usize;
-    let claims: TokenClaims = TokenClaims {
-        sub: user_id,
-        exp,
-        iat,
+    let user_id = match resolve_login_user(&data, provider_name, oidc_user).await {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(jwt_secret.as_ref()),
-    )
-    .unwrap();
+    match begin_two_factor_login(&data, user_id).await {
+        Ok(Some(pre_auth_token)) => {
+            response.append_header((
+                LOCATION,
+                format!(
+                    "{}/login/2fa?pre_auth_token={}",
+                    frontend_origin, pre_auth_token
+                ),
+            ));
+            return response.finish();
+        }
+        Ok(None) => {}
+        Err(response) => return response,
+    }
 
-    let cookie = Cookie::build("token", token)
-        .path("/")
-        .max_age(ActixWebDuration::new(60 * data.env.jwt_max_age, 0))
-        .http_only(true)
-        .finish();
+    notify_login(&data, &req, user_id, provider_name).await;
 
-    let frontend_origin = data.env.client_origin.to_owned();
-    let mut response = HttpResponse::Found();
+    let (access, refresh) = match start_session(&data, &req, user_id).await {
+        Ok(cookies) => cookies,
+        Err(response) => return response,
+    };
 
-    response.append_header((LOCATION, format!("{}{}", frontend_origin, state)));
-    response.cookie(cookie);
+    response.append_header((LOCATION, format!("{}{}", frontend_origin, flow.redirect)));
+    response.cookie(access);
+    response.cookie(refresh);
     response.finish()
 }
@@ -0,0 +1,265 @@
+use actix_web::{HttpResponse, Responder, post, web};
+use chrono::{Duration, prelude::*};
+use uuid::Uuid;
+
+use super::{
+    action_token::{consume_token, issue_action_token},
+    auth::AuthenticationGuard,
+    session::clear_session_cookies,
+};
+use crate::{
+    model::{
+        ActionPurpose, AppState, ChangePasswordSchema, ForgotPasswordSchema, ResetPasswordSchema,
+        User,
+    },
+    repo::{
+        check_user_password, get_recent_action_tokens, get_user_by_email, get_user_by_id,
+        revoke_all_access_tokens, revoke_all_sessions, update_user_password,
+    },
+};
+
+const RESET_TOKEN_TTL_MINUTES: i64 = 60;
+const RESET_COOLDOWN_SECONDS: i64 = 60;
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+pub(super) fn validate_password(password: &str) -> Result<(), HttpResponse> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "status": "fail",
+            "message": format!("Password must be at least {} characters long", MIN_PASSWORD_LENGTH)
+        })));
+    }
+
+    Ok(())
+}
+
+/// Письмо со ссылкой на сброс пароля — по запросу пользователя или по требованию админа.
+pub(super) async fn send_password_reset_email(
+    data: &AppState,
+    user_id: Uuid,
+    user: &User,
+) -> Result<(), HttpResponse> {
+    let token = match issue_action_token(
+        data,
+        user_id,
+        ActionPurpose::ResetPassword,
+        Duration::minutes(RESET_TOKEN_TTL_MINUTES),
+    )
+    .await
+    {
+        Ok(token) => token,
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            })));
+        }
+    };
+    let link = format!("{}/reset-password?token={}", data.env.client_origin, token);
+
+    let mail = data
+        .mailer
+        .send(
+            &user.email,
+            "Reset your password",
+            format!(
+                "Hi {},\n\nTo set a new password, open this link:\n{}\n\n\
+                 The link is valid for {} minutes. If you did not request a reset, \
+                 ignore this email.",
+                user.name, link, RESET_TOKEN_TTL_MINUTES
+            ),
+        )
+        .await;
+    if let Err(e) = mail {
+        log::error!("Failed to send password reset email to {}: {}", user_id, e);
+        return Err(HttpResponse::InternalServerError().json(
+            serde_json::json!({"status": "error", "message": "Failed to send password reset email"}),
+        ));
+    }
+
+    Ok(())
+}
+
+// Сессии здесь не отзываем: иначе любой, кто знает адрес, мог бы разлогинивать
+// владельца. Сессии гаснут, когда по ссылке из письма задают новый пароль.
+// Ответ одинаковый для любых адресов, чтобы по нему нельзя было их перебирать.
+#[post("/auth/forgot_password")]
+async fn forgot_password_handler(
+    body: web::Json<ForgotPasswordSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let sent = HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "If the account exists, a password reset email has been sent"
+    }));
+
+    let user = match get_user_by_email(&data.pool, &body.email.to_lowercase()).await {
+        Ok(Some(user)) if !user.password.is_empty() => user,
+        Ok(_) => return sent,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+    let Some(user_id) = user.id else {
+        return sent;
+    };
+
+    // не чаще письма в минуту, молча: 429 выдал бы, что адрес зарегистрирован
+    let now = Utc::now().naive_utc();
+    match get_recent_action_tokens(
+        &data.pool,
+        user_id,
+        ActionPurpose::ResetPassword,
+        now - Duration::seconds(RESET_COOLDOWN_SECONDS),
+    )
+    .await
+    {
+        Ok((0, _, _)) => {}
+        Ok(_) => return sent,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    if let Err(response) = send_password_reset_email(&data, user_id, &user).await {
+        return response;
+    }
+
+    sent
+}
+
+#[post("/auth/reset_password")]
+async fn reset_password_handler(
+    body: web::Json<ResetPasswordSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = validate_password(&body.password) {
+        return response;
+    }
+
+    let user_id = match consume_token(&data, &body.token, ActionPurpose::ResetPassword).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": "fail",
+                "message": "Invalid or expired reset token"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    if let Err(e) = update_user_password(&data.pool, user_id, &body.password).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+    if let Err(e) = revoke_all_sessions(&data.pool, user_id, None).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+    if let Err(e) = revoke_all_access_tokens(&data.pool, user_id).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+
+    let mut response = HttpResponse::Ok();
+    clear_session_cookies(&mut response);
+    response.json(serde_json::json!({"status": "success"}))
+}
+
+/// Смена пароля из настроек. Текущая сессия остаётся, остальные сессии и все токены отзываются.
+#[post("/auth/change_password")]
+async fn change_password_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<ChangePasswordSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let current_session = match auth_guard.require_own_session() {
+        Ok(session_id) => session_id,
+        Err(response) => return response,
+    };
+
+    match get_user_by_id(&data.pool, &auth_guard.user_id).await {
+        Ok(Some(user)) if !user.password.is_empty() => {}
+        Ok(Some(_)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": "fail",
+                "message": "Account has no password, it signs in with an external provider"
+            }));
+        }
+        Ok(None) => {
+            return HttpResponse::Unauthorized()
+                .json(serde_json::json!({"status": "fail", "message": "User not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match check_user_password(&data.pool, user_id, &body.current_password).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Forbidden().json(
+                serde_json::json!({"status": "fail", "message": "Current password is incorrect"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    if let Err(response) = validate_password(&body.new_password) {
+        return response;
+    }
+
+    if let Err(e) = update_user_password(&data.pool, user_id, &body.new_password).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+
+    if let Err(e) = revoke_all_access_tokens(&data.pool, user_id).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+
+    match revoke_all_sessions(&data.pool, user_id, Some(current_session)).await {
+        Ok(revoked) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "revoked": revoked}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
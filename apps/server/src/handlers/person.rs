@@ -0,0 +1,571 @@
+use std::collections::HashMap;
+
+use actix_web::{HttpRequest, HttpResponse, Responder, get, patch, post, put, web};
+use uuid::Uuid;
+
+use super::{
+    auth::AuthenticationGuard,
+    etag::{check_if_match, etag, precondition_failed},
+    history::{log_change, snapshot},
+};
+use crate::{
+    graph::{Person, PersonName, needs_redaction, redact},
+    model::{
+        AppState, ChangeEntity, ChangeOperation, CreatePersonSchema, NewChange, PersonSearchMode,
+        SearchPersonQuery, TreeRole, UpdatePersonSchema, UpdatePrivacySchema,
+    },
+    repo::get_accessible_tree_ids,
+};
+
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 100;
+
+#[get("/persons/search")]
+async fn search_persons_handler(
+    auth_guard: AuthenticationGuard,
+    query: web::Query<SearchPersonQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = match Uuid::parse_str(&auth_guard.user_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::Unauthorized()
+                .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+        }
+    };
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+
+    // персоны деревьев из корзины в поиск не попадают
+    let tree_ids = match get_accessible_tree_ids(&data.pool, user_id).await {
+        Ok(ids) => ids
+            .into_iter()
+            .filter(|id| auth_guard.allows_tree(*id))
+            .collect::<Vec<Uuid>>(),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+    // персоны без дерева видны только из сессии, у токена scope по деревьям
+    let owner_id = auth_guard.token_scopes.is_none().then_some(&user_id);
+
+    let result = match query.mode {
+        PersonSearchMode::Name => {
+            match Person::search(&data.graph, &tree_ids, owner_id, &query.q, limit).await {
+                Ok(mut persons) => redact_search_hits(&data, persons.iter_mut(), &auth_guard)
+                    .await
+                    .map(|_| serde_json::json!({"status": "success", "persons": persons}))
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+        PersonSearchMode::Phonetic => {
+            match Person::search_phonetic(&data.graph, &tree_ids, owner_id, &query.q, limit).await {
+                Ok(mut hits) => redact_search_hits(
+                    &data,
+                    hits.iter_mut().map(|hit| &mut hit.person),
+                    &auth_guard,
+                )
+                .await
+                .map(|_| serde_json::json!({"status": "success", "results": hits}))
+                .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+    };
+
+    match result {
+        Ok(body) => HttpResponse::Ok().json(body),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e
+        })),
+    }
+}
+
+/// Скрывает живых в результатах поиска так же, как get_person_handler:
+/// по роли пользователя (или scope токена) в дереве каждой найденной персоны.
+async fn redact_search_hits(
+    data: &AppState,
+    persons: impl Iterator<Item = &mut Person>,
+    auth_guard: &AuthenticationGuard,
+) -> Result<(), sqlx::Error> {
+    let mut roles: HashMap<Option<Uuid>, Option<TreeRole>> = HashMap::new();
+    for person in persons {
+        let role = match person.tree_id {
+            Some(tree_id) => match roles.get(&Some(tree_id)) {
+                Some(role) => *role,
+                None => {
+                    let role = auth_guard.tree_role(data, tree_id).await?;
+                    roles.insert(Some(tree_id), role);
+                    role
+                }
+            },
+            None => role_for_person(data, person, auth_guard).await?,
+        };
+        // без роли персону показывать не должны были вовсе — скрываем всё, что можно
+        apply_privacy(person, role.unwrap_or(TreeRole::Viewer));
+    }
+    Ok(())
+}
+
+/// Роль пользователя в дереве персоны. У персон без дерева доступ есть только у автора.
+/// Токену доступа персоны без дерева недоступны: его scope задан по деревьям.
+pub(super) async fn role_for_person(
+    data: &AppState,
+    person: &Person,
+    auth_guard: &AuthenticationGuard,
+) -> Result<Option<TreeRole>, sqlx::Error> {
+    match person.tree_id {
+        Some(tree_id) => auth_guard.tree_role(data, tree_id).await,
+        None if auth_guard.token_scopes.is_none()
+            && Uuid::parse_str(&auth_guard.user_id)
+                .is_ok_and(|id| id == person.created_by_user_id) =>
+        {
+            Ok(Some(TreeRole::Owner))
+        }
+        None => Ok(None),
+    }
+}
+
+// Живых скрываем от всех, кто ниже редактора
+fn apply_privacy(person: &mut Person, role: TreeRole) {
+    if role < TreeRole::Editor && needs_redaction(person) {
+        redact(person);
+    }
+}
+
+#[get("/persons/{id}")]
+async fn get_person_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let mut person = match Person::find(&data.graph, &path).await {
+        Ok(Some(person)) => person,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Person not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    let role = match role_for_person(&data, &person, &auth_guard).await {
+        Ok(Some(role)) => role,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Person not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    apply_privacy(&mut person, role);
+
+    HttpResponse::Ok()
+        .insert_header(etag(person.version))
+        .json(serde_json::json!({"status": "success", "person": person}))
+}
+
+#[get("/trees/{tree_id}/persons")]
+async fn get_tree_persons_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let tree_id = path.into_inner();
+
+    let role = match auth_guard.tree_role(&data, tree_id).await {
+        Ok(Some(role)) => role,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Tree not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    match Person::list_for_tree(&data.graph, &tree_id).await {
+        Ok(mut persons) => {
+            for person in persons.iter_mut() {
+                apply_privacy(person, role);
+            }
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "persons": persons}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Персона, которую пользователь вправе править: роль в её дереве не ниже редактора.
+pub(super) async fn find_editable_person(
+    data: &AppState,
+    person_id: &str,
+    auth_guard: &AuthenticationGuard,
+) -> Result<Person, HttpResponse> {
+    let person = match Person::find(&data.graph, person_id).await {
+        Ok(Some(person)) => person,
+        Ok(None) => {
+            return Err(HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Person not found"})));
+        }
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            })));
+        }
+    };
+
+    match role_for_person(data, &person, auth_guard).await {
+        Ok(Some(role)) if role >= TreeRole::Editor => Ok(person),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(serde_json::json!({"status": "fail", "message": "Editor role is required"}))),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Person not found"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+/// Перечитывает персону после записи — для ответа и снимка в журнале.
+pub(super) async fn reload(data: &AppState, person_id: &str) -> Result<Person, HttpResponse> {
+    match Person::find(&data.graph, person_id).await {
+        Ok(Some(person)) => Ok(person),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Person not found"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+/// Итог условной записи: false — персону поправили после того, как её прочитали.
+async fn check_written(
+    data: &AppState,
+    person_id: &str,
+    written: Result<bool, neo4rs::Error>,
+) -> Result<(), HttpResponse> {
+    match written {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            let current = reload(data, person_id).await?;
+            Err(precondition_failed("person", &current, current.version))
+        }
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+/// Запись правки персоны в граф и журнал. Общая для прямой правки и принятого предложения.
+/// Пишется поверх `expected_version` — версии, которую видел автор правки, так что
+/// правка, сделанная с тех пор, даёт 412, а не затирается.
+pub(super) async fn write_person_update(
+    data: &AppState,
+    user_id: Uuid,
+    before: &Person,
+    expected_version: i64,
+    update: &UpdatePersonSchema,
+) -> Result<Person, HttpResponse> {
+    let written = Person::update(
+        &data.graph,
+        &before.id,
+        &update.birth_date,
+        update.death_date.as_deref(),
+        &update.gender,
+        Some(expected_version),
+    )
+    .await;
+    check_written(data, &before.id, written).await?;
+
+    let after = reload(data, &before.id).await?;
+
+    log_change(
+        data,
+        NewChange {
+            tree_id: before.tree_id,
+            user_id,
+            entity: ChangeEntity::Person,
+            entity_id: before.id.clone(),
+            person_ids: vec![before.id.clone()],
+            operation: ChangeOperation::Update,
+            before: snapshot(before),
+            after: snapshot(&after),
+            revert_of: None,
+        },
+    )
+    .await?;
+
+    Ok(after)
+}
+
+pub(super) async fn write_person_names(
+    data: &AppState,
+    user_id: Uuid,
+    before: &Person,
+    expected_version: i64,
+    names: &[PersonName],
+) -> Result<Person, HttpResponse> {
+    let written = Person::set_names(&data.graph, &before.id, names, Some(expected_version)).await;
+    check_written(data, &before.id, written).await?;
+
+    let after = reload(data, &before.id).await?;
+
+    log_change(
+        data,
+        NewChange {
+            tree_id: before.tree_id,
+            user_id,
+            entity: ChangeEntity::Names,
+            entity_id: before.id.clone(),
+            person_ids: vec![before.id.clone()],
+            operation: ChangeOperation::Update,
+            before: snapshot(&before.names),
+            after: snapshot(&after.names),
+            revert_of: None,
+        },
+    )
+    .await?;
+
+    Ok(after)
+}
+
+#[post("/persons")]
+async fn create_person_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<CreatePersonSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    let body = body.into_inner();
+
+    if body.name.trim().is_empty() && body.names.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"status": "fail", "message": "Person name is required"}));
+    }
+
+    if let Some(tree_id) = body.tree_id {
+        match auth_guard.tree_role(&data, tree_id).await {
+            Ok(Some(role)) if role >= TreeRole::Editor => {}
+            Ok(Some(_)) => {
+                return HttpResponse::Forbidden().json(
+                    serde_json::json!({"status": "fail", "message": "Editor role is required"}),
+                );
+            }
+            Ok(None) => {
+                return HttpResponse::NotFound()
+                    .json(serde_json::json!({"status": "fail", "message": "Tree not found"}));
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": "error",
+                    "info": e.to_string()
+                }));
+            }
+        }
+    }
+
+    let person = Person {
+        id: Uuid::new_v4().to_string(),
+        name: body.name.trim().to_string(),
+        sort_name: String::new(),
+        names: body.names,
+        birth_date: body.birth_date,
+        death_date: body.death_date,
+        gender: body.gender,
+        created_by_user_id: user_id,
+        tree_id: body.tree_id,
+        privacy: body.privacy,
+        living: false,
+        redacted: false,
+        deleted_at: None,
+        version: 1,
+    };
+
+    if let Err(e) = Person::create(&data.graph, &person).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+
+    let person = match reload(&data, &person.id).await {
+        Ok(person) => person,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = log_change(
+        &data,
+        NewChange {
+            tree_id: person.tree_id,
+            user_id,
+            entity: ChangeEntity::Person,
+            entity_id: person.id.clone(),
+            person_ids: vec![person.id.clone()],
+            operation: ChangeOperation::Create,
+            before: None,
+            after: snapshot(&person),
+            revert_of: None,
+        },
+    )
+    .await
+    {
+        return response;
+    }
+
+    HttpResponse::Ok()
+        .insert_header(etag(person.version))
+        .json(serde_json::json!({"status": "success", "person": person}))
+}
+
+#[put("/persons/{id}")]
+async fn update_person_handler(
+    auth_guard: AuthenticationGuard,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdatePersonSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let before = match find_editable_person(&data, &path, &auth_guard).await {
+        Ok(person) => person,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = check_if_match(&req, "person", &before, before.version) {
+        return response;
+    }
+
+    match write_person_update(&data, user_id, &before, before.version, &body).await {
+        Ok(after) => HttpResponse::Ok()
+            .insert_header(etag(after.version))
+            .json(serde_json::json!({"status": "success", "person": after})),
+        Err(response) => response,
+    }
+}
+
+#[put("/persons/{id}/names")]
+async fn set_person_names_handler(
+    auth_guard: AuthenticationGuard,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<Vec<PersonName>>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    if body.is_empty() {
+        return HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "At least one name is required"}),
+        );
+    }
+
+    let before = match find_editable_person(&data, &path, &auth_guard).await {
+        Ok(person) => person,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = check_if_match(&req, "person", &before, before.version) {
+        return response;
+    }
+
+    match write_person_names(&data, user_id, &before, before.version, &body).await {
+        Ok(after) => HttpResponse::Ok()
+            .insert_header(etag(after.version))
+            .json(serde_json::json!({"status": "success", "person": after})),
+        Err(response) => response,
+    }
+}
+
+#[patch("/persons/{id}/privacy")]
+async fn update_person_privacy_handler(
+    auth_guard: AuthenticationGuard,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdatePrivacySchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let before = match find_editable_person(&data, &path, &auth_guard).await {
+        Ok(person) => person,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = check_if_match(&req, "person", &before, before.version) {
+        return response;
+    }
+
+    let written =
+        Person::set_privacy(&data.graph, &before.id, body.privacy, Some(before.version)).await;
+    if let Err(response) = check_written(&data, &before.id, written).await {
+        return response;
+    }
+
+    let after = match reload(&data, &before.id).await {
+        Ok(person) => person,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = log_change(
+        &data,
+        NewChange {
+            tree_id: before.tree_id,
+            user_id,
+            entity: ChangeEntity::Person,
+            entity_id: before.id.clone(),
+            person_ids: vec![before.id.clone()],
+            operation: ChangeOperation::Update,
+            before: snapshot(&before),
+            after: snapshot(&after),
+            revert_of: None,
+        },
+    )
+    .await
+    {
+        return response;
+    }
+
+    HttpResponse::Ok()
+        .insert_header(etag(after.version))
+        .json(serde_json::json!({"status": "success"}))
+}
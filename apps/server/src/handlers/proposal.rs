@@ -0,0 +1,453 @@
+use actix_web::{HttpResponse, Responder, get, post, web};
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use super::{
+    auth::AuthenticationGuard,
+    person::{find_editable_person, write_person_names, write_person_update},
+    relationship::{
+        find_editable_pair, find_partnership, validate_partnership, write_parent_link,
+        write_partnership_create, write_partnership_update, write_siblings_link,
+    },
+};
+use crate::{
+    graph::{Partnership, Person, PersonName},
+    model::{
+        AppState, CreateProposalSchema, EditProposal, LinkParentSchema, LinkSiblingsSchema,
+        PartnershipSchema, ProposalKind, ProposalQuery, ProposalStatus, ReviewProposalSchema,
+        TreeRole, UpdatePersonSchema,
+    },
+    repo::{
+        create_proposal, get_proposal, get_proposals_by_user, get_proposals_for_tree,
+        reopen_proposal, review_proposal,
+    },
+};
+
+fn parse_payload<T: DeserializeOwned>(payload: &serde_json::Value) -> Result<T, HttpResponse> {
+    serde_json::from_value(payload.clone()).map_err(|e| {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "status": "fail",
+            "message": format!("Invalid proposal payload: {}", e)
+        }))
+    })
+}
+
+/// Дерево и текущая версия персоны. Предлагать правки можно только в деревьях:
+/// у персон без дерева нет зрителей.
+async fn person_tree(data: &AppState, person_id: &str) -> Result<(Uuid, i64), HttpResponse> {
+    match Person::find(&data.graph, person_id).await {
+        Ok(Some(Person {
+            tree_id: Some(tree_id),
+            version,
+            ..
+        })) => Ok((tree_id, version)),
+        Ok(_) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Person not found"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+async fn pair_tree(
+    data: &AppState,
+    person1_id: &str,
+    person2_id: &str,
+) -> Result<Uuid, HttpResponse> {
+    let (tree1, _) = person_tree(data, person1_id).await?;
+    let (tree2, _) = person_tree(data, person2_id).await?;
+
+    if tree1 != tree2 {
+        return Err(HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "Persons belong to different trees"}),
+        ));
+    }
+
+    Ok(tree1)
+}
+
+/// У связей цель — одна из связываемых персон: по target_id предложения
+/// ищут и показывают, и он не должен указывать на постороннего.
+fn require_link_target(target: &str, ids: [&str; 2]) -> Result<(), HttpResponse> {
+    if ids.contains(&target) {
+        return Ok(());
+    }
+
+    Err(HttpResponse::BadRequest().json(serde_json::json!({
+        "status": "fail",
+        "message": "Target must be one of the linked persons"
+    })))
+}
+
+/// Проверяет, что payload разбирается в схему своего вида, и находит дерево,
+/// к которому относится предложение, и версию цели, поверх которой описана правка.
+/// У связей версий нет.
+async fn proposal_tree(
+    data: &AppState,
+    proposal: &CreateProposalSchema,
+) -> Result<(Uuid, Option<i64>), HttpResponse> {
+    let target = proposal.target_id.as_str();
+
+    match proposal.kind {
+        ProposalKind::PersonUpdate => {
+            parse_payload::<UpdatePersonSchema>(&proposal.payload)?;
+            let (tree_id, version) = person_tree(data, target).await?;
+            Ok((tree_id, Some(version)))
+        }
+        ProposalKind::PersonNames => {
+            let names: Vec<PersonName> = parse_payload(&proposal.payload)?;
+            if names.is_empty() {
+                return Err(HttpResponse::BadRequest().json(
+                    serde_json::json!({"status": "fail", "message": "At least one name is required"}),
+                ));
+            }
+            let (tree_id, version) = person_tree(data, target).await?;
+            Ok((tree_id, Some(version)))
+        }
+        ProposalKind::ParentLink => {
+            let link: LinkParentSchema = parse_payload(&proposal.payload)?;
+            require_link_target(target, [&link.parent_id, &link.child_id])?;
+            Ok((
+                pair_tree(data, &link.parent_id, &link.child_id).await?,
+                None,
+            ))
+        }
+        ProposalKind::SiblingLink => {
+            let link: LinkSiblingsSchema = parse_payload(&proposal.payload)?;
+            require_link_target(target, [&link.person1_id, &link.person2_id])?;
+            Ok((
+                pair_tree(data, &link.person1_id, &link.person2_id).await?,
+                None,
+            ))
+        }
+        ProposalKind::PartnershipCreate => {
+            let partnership: PartnershipSchema = parse_payload(&proposal.payload)?;
+            validate_partnership(&partnership)?;
+            require_link_target(target, [&partnership.person1_id, &partnership.person2_id])?;
+            Ok((
+                pair_tree(data, &partnership.person1_id, &partnership.person2_id).await?,
+                None,
+            ))
+        }
+        ProposalKind::PartnershipUpdate => {
+            validate_partnership(&parse_payload(&proposal.payload)?)?;
+            let Partnership {
+                person1_id,
+                version,
+                ..
+            } = find_partnership(data, target).await?;
+            let (tree_id, _) = person_tree(data, &person1_id).await?;
+            Ok((tree_id, Some(version)))
+        }
+    }
+}
+
+async fn apply_proposal(
+    data: &AppState,
+    auth_guard: &AuthenticationGuard,
+    user_id: Uuid,
+    proposal: &EditProposal,
+) -> Result<serde_json::Value, HttpResponse> {
+    let Some(kind) = ProposalKind::parse(&proposal.kind) else {
+        return Err(HttpResponse::UnprocessableEntity()
+            .json(serde_json::json!({"status": "fail", "message": "Unknown proposal kind"})));
+    };
+
+    match kind {
+        ProposalKind::PersonUpdate => {
+            let update: UpdatePersonSchema = parse_payload(&proposal.payload)?;
+            let before = find_editable_person(data, &proposal.target_id, auth_guard).await?;
+            let base_version = proposal.base_version.unwrap_or(before.version);
+            let after = write_person_update(data, user_id, &before, base_version, &update).await?;
+            Ok(serde_json::json!({"person": after}))
+        }
+        ProposalKind::PersonNames => {
+            let names: Vec<PersonName> = parse_payload(&proposal.payload)?;
+            let before = find_editable_person(data, &proposal.target_id, auth_guard).await?;
+            let base_version = proposal.base_version.unwrap_or(before.version);
+            let after = write_person_names(data, user_id, &before, base_version, &names).await?;
+            Ok(serde_json::json!({"person": after}))
+        }
+        ProposalKind::ParentLink => {
+            let link: LinkParentSchema = parse_payload(&proposal.payload)?;
+            let (parent, child) =
+                find_editable_pair(data, &link.parent_id, &link.child_id, auth_guard).await?;
+            write_parent_link(data, user_id, &parent, &child).await?;
+            Ok(serde_json::json!({}))
+        }
+        ProposalKind::SiblingLink => {
+            let link: LinkSiblingsSchema = parse_payload(&proposal.payload)?;
+            let (person1, person2) =
+                find_editable_pair(data, &link.person1_id, &link.person2_id, auth_guard).await?;
+            write_siblings_link(data, user_id, &person1, &person2).await?;
+            Ok(serde_json::json!({}))
+        }
+        ProposalKind::PartnershipCreate => {
+            let body: PartnershipSchema = parse_payload(&proposal.payload)?;
+            let (person1, person2) =
+                find_editable_pair(data, &body.person1_id, &body.person2_id, auth_guard).await?;
+            let partnership =
+                write_partnership_create(data, user_id, &person1, &person2, &body).await?;
+            Ok(serde_json::json!({"partnership": partnership}))
+        }
+        ProposalKind::PartnershipUpdate => {
+            let body: PartnershipSchema = parse_payload(&proposal.payload)?;
+            let before = find_partnership(data, &proposal.target_id).await?;
+            let (person1, person2) =
+                find_editable_pair(data, &before.person1_id, &before.person2_id, auth_guard)
+                    .await?;
+            let base_version = proposal.base_version.unwrap_or(before.version);
+            let after = write_partnership_update(
+                data,
+                user_id,
+                &before,
+                base_version,
+                &person1,
+                &person2,
+                &body,
+            )
+            .await?;
+            Ok(serde_json::json!({"partnership": after}))
+        }
+    }
+}
+
+/// Предложение и роль пользователя в его дереве; рецензировать может только редактор.
+async fn find_reviewable_proposal(
+    data: &AppState,
+    id: Uuid,
+    auth_guard: &AuthenticationGuard,
+) -> Result<EditProposal, HttpResponse> {
+    let proposal = match get_proposal(&data.pool, id).await {
+        Ok(Some(proposal)) => proposal,
+        Ok(None) => {
+            return Err(HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Proposal not found"})));
+        }
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            })));
+        }
+    };
+
+    match auth_guard.tree_role(data, proposal.tree_id).await {
+        Ok(Some(role)) if role >= TreeRole::Editor => Ok(proposal),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(serde_json::json!({"status": "fail", "message": "Editor role is required"}))),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Proposal not found"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+#[post("/proposals")]
+async fn create_proposal_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<CreateProposalSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let has_text = |value: &Option<String>| value.as_deref().is_some_and(|v| !v.trim().is_empty());
+    if !has_text(&body.source) && !has_text(&body.comment) {
+        return HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "A source or a comment is required"}),
+        );
+    }
+
+    let (tree_id, base_version) = match proposal_tree(&data, &body).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    // предлагать может любой участник дерева, в том числе зритель, но не токен на чтение
+    match auth_guard.tree_role(&data, tree_id).await {
+        Ok(Some(_)) => {
+            if let Err(response) = auth_guard.require_write(tree_id) {
+                return response;
+            }
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Person not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match create_proposal(&data.pool, tree_id, user_id, base_version, &body).await {
+        Ok(proposal) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "proposal": proposal}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[get("/proposals")]
+async fn get_my_proposals_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    match get_proposals_by_user(&data.pool, user_id).await {
+        Ok(proposals) => {
+            let proposals: Vec<_> = proposals
+                .into_iter()
+                .filter(|proposal| auth_guard.allows_tree(proposal.tree_id))
+                .collect();
+            HttpResponse::Ok()
+                .json(serde_json::json!({"status": "success", "proposals": proposals}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[get("/trees/{tree_id}/proposals")]
+async fn get_tree_proposals_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    query: web::Query<ProposalQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let tree_id = path.into_inner();
+
+    match auth_guard.tree_role(&data, tree_id).await {
+        Ok(Some(role)) if role >= TreeRole::Editor => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Forbidden()
+                .json(serde_json::json!({"status": "fail", "message": "Editor role is required"}));
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Tree not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match get_proposals_for_tree(&data.pool, tree_id, query.status).await {
+        Ok(proposals) => HttpResponse::Ok()
+            .json(serde_json::json!({"status": "success", "proposals": proposals})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[post("/proposals/{id}/accept")]
+async fn accept_proposal_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    body: web::Json<ReviewProposalSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let proposal = match find_reviewable_proposal(&data, path.into_inner(), &auth_guard).await {
+        Ok(proposal) => proposal,
+        Err(response) => return response,
+    };
+
+    // сначала занимаем предложение, чтобы два редактора не применили его дважды
+    match review_proposal(
+        &data.pool,
+        proposal.id,
+        ProposalStatus::Accepted,
+        user_id,
+        body.comment.as_deref(),
+    )
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Conflict().json(
+                serde_json::json!({"status": "fail", "message": "Proposal is already reviewed"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match apply_proposal(&data, &auth_guard, user_id, &proposal).await {
+        Ok(result) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "result": result}))
+        }
+        Err(response) => {
+            if let Err(e) = reopen_proposal(&data.pool, proposal.id).await {
+                log::error!("Failed to reopen proposal {}: {}", proposal.id, e);
+            }
+            response
+        }
+    }
+}
+
+#[post("/proposals/{id}/reject")]
+async fn reject_proposal_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    body: web::Json<ReviewProposalSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let proposal = match find_reviewable_proposal(&data, path.into_inner(), &auth_guard).await {
+        Ok(proposal) => proposal,
+        Err(response) => return response,
+    };
+
+    match review_proposal(
+        &data.pool,
+        proposal.id,
+        ProposalStatus::Rejected,
+        user_id,
+        body.comment.as_deref(),
+    )
+    .await
+    {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Ok(false) => HttpResponse::Conflict()
+            .json(serde_json::json!({"status": "fail", "message": "Proposal is already reviewed"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use actix_web::{HttpRequest, HttpResponse, http::header};
+
+use crate::{model::AppState, rate_limit::Policy};
+
+/// Адрес клиента — для лимитов, сессий, устройств и журнала админов. Заголовкам прокси
+/// верим только по настройке, иначе X-Forwarded-For позволил бы обходить лимит по IP
+/// и подделывать адрес в истории входов.
+pub(super) fn client_ip(data: &AppState, req: &HttpRequest) -> Option<String> {
+    if data.env.rate_limit.trust_forwarded {
+        req.connection_info()
+            .realip_remote_addr()
+            .map(str::to_string)
+    } else {
+        req.peer_addr().map(|addr| addr.ip().to_string())
+    }
+}
+
+pub(super) fn ip_key(data: &AppState, req: &HttpRequest, action: &str) -> String {
+    let ip = client_ip(data, req);
+
+    format!("{}:ip:{}", action, ip.as_deref().unwrap_or("unknown"))
+}
+
+pub(super) fn account_key(action: &str, email: &str) -> String {
+    format!("{}:account:{}", action, email.trim().to_lowercase())
+}
+
+fn too_many_attempts(retry_after: Duration) -> HttpResponse {
+    let seconds = (retry_after.as_secs_f64().ceil() as u64).max(1);
+
+    HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, seconds.to_string()))
+        .json(serde_json::json!({
+            "status": "fail",
+            "message": "Too many attempts, please try again later",
+            "retry_after": seconds
+        }))
+}
+
+// Сбой хранилища счётчиков не должен закрывать вход всем: пропускаем и пишем в лог
+fn log_failure(key: &str, e: &dyn std::error::Error) {
+    log::error!("Rate limiter failed for {}: {}", key, e);
+}
+
+/// Засчитывает попытку; Err — лимит исчерпан, ответ с Retry-After.
+pub(super) async fn limit(data: &AppState, key: &str, policy: &Policy) -> Result<(), HttpResponse> {
+    match data.limiter.hit(key, policy).await {
+        Ok(None) => Ok(()),
+        Ok(Some(retry_after)) => Err(too_many_attempts(retry_after)),
+        Err(e) => {
+            log_failure(key, e.as_ref());
+            Ok(())
+        }
+    }
+}
+
+/// Err, пока ключ заблокирован. Попытку не засчитывает.
+pub(super) async fn ensure_not_locked(data: &AppState, key: &str) -> Result<(), HttpResponse> {
+    match data.limiter.locked_for(key).await {
+        Ok(None) => Ok(()),
+        Ok(Some(retry_after)) => Err(too_many_attempts(retry_after)),
+        Err(e) => {
+            log_failure(key, e.as_ref());
+            Ok(())
+        }
+    }
+}
+
+pub(super) async fn reset(data: &AppState, key: &str) {
+    if let Err(e) = data.limiter.reset(key).await {
+        log_failure(key, e.as_ref());
+    }
+}
@@ -0,0 +1,236 @@
+use std::time::{Duration, Instant};
+
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use actix_ws::{Message, MessageStream, Session};
+use uuid::Uuid;
+
+use super::auth::AuthenticationGuard;
+use crate::{
+    model::{AppState, NewChange, RealtimeCommand},
+    realtime::{Outbox, Viewer},
+    repo::{get_tree_role, get_user_by_id, touch_session},
+};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(45);
+// Столько событий может ждать отправки; больше — клиент не успевает, отключаем его
+const OUTBOX_CAPACITY: usize = 256;
+
+/// Сообщает подписчикам дерева о правке. Снимков до/после не шлём: они не проходят
+/// через скрытие живых, клиент перечитает персону обычным запросом.
+pub(super) fn publish_change(data: &AppState, change: &NewChange, change_id: Option<i64>) {
+    let Some(tree_id) = change.tree_id else {
+        return;
+    };
+
+    data.hub.publish(
+        tree_id,
+        &serde_json::json!({
+            "type": "change",
+            "tree_id": tree_id,
+            "change_id": change_id,
+            "user_id": change.user_id,
+            "entity_type": change.entity.as_str(),
+            "entity_id": change.entity_id,
+            "operation": change.operation.as_str(),
+            "person_ids": change.person_ids
+        }),
+    );
+}
+
+fn publish_presence(data: &AppState, tree_id: Uuid) {
+    data.hub.publish(
+        tree_id,
+        &serde_json::json!({
+            "type": "presence",
+            "tree_id": tree_id,
+            "viewers": data.hub.viewers(tree_id)
+        }),
+    );
+}
+
+async fn handle_command(
+    data: &AppState,
+    connection_id: Uuid,
+    viewer: &Viewer,
+    outbox: &Outbox,
+    text: &str,
+) -> serde_json::Value {
+    let command: RealtimeCommand = match serde_json::from_str(text) {
+        Ok(command) => command,
+        Err(e) => return serde_json::json!({"type": "error", "message": e.to_string()}),
+    };
+
+    match command {
+        RealtimeCommand::Subscribe { tree_id } => {
+            match get_tree_role(&data.pool, tree_id, viewer.user_id).await {
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    return serde_json::json!({
+                        "type": "error",
+                        "tree_id": tree_id,
+                        "message": "Tree not found"
+                    });
+                }
+                Err(e) => return serde_json::json!({"type": "error", "message": e.to_string()}),
+            }
+
+            data.hub
+                .subscribe(tree_id, connection_id, viewer.clone(), outbox.clone());
+            publish_presence(data, tree_id);
+
+            serde_json::json!({"type": "subscribed", "tree_id": tree_id})
+        }
+        RealtimeCommand::Unsubscribe { tree_id } => {
+            if data.hub.unsubscribe(tree_id, connection_id) {
+                publish_presence(data, tree_id);
+            }
+
+            serde_json::json!({"type": "unsubscribed", "tree_id": tree_id})
+        }
+    }
+}
+
+/// Права проверяются не только при подписке: сессию могут отозвать, а пользователя —
+/// убрать из дерева, пока соединение открыто. false — сессия больше не действует.
+/// Из деревьев, где роли больше нет, соединение отписывается, клиенту уходит unsubscribed.
+async fn recheck_access(
+    data: &AppState,
+    connection_id: Uuid,
+    session_id: Uuid,
+    viewer: &Viewer,
+    session: &mut Session,
+) -> bool {
+    match touch_session(&data.pool, session_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return false,
+        Err(e) => {
+            log::error!("Failed to check realtime session {}: {}", session_id, e);
+            return false;
+        }
+    }
+
+    for tree_id in data.hub.subscriptions(connection_id) {
+        let allowed = match get_tree_role(&data.pool, tree_id, viewer.user_id).await {
+            Ok(role) => role.is_some(),
+            Err(e) => {
+                log::error!("Failed to check realtime access to {}: {}", tree_id, e);
+                false
+            }
+        };
+        if !allowed && data.hub.unsubscribe(tree_id, connection_id) {
+            publish_presence(data, tree_id);
+            let event = serde_json::json!({"type": "unsubscribed", "tree_id": tree_id});
+            if session.text(event.to_string()).await.is_err() {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+async fn run_connection(
+    data: web::Data<AppState>,
+    session_id: Uuid,
+    viewer: Viewer,
+    mut session: Session,
+    mut messages: MessageStream,
+) {
+    let connection_id = Uuid::new_v4();
+    let (outbox, mut events, overflow) = Outbox::new(OUTBOX_CAPACITY);
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    let mut last_seen = Instant::now();
+
+    loop {
+        tokio::select! {
+            message = messages.recv() => {
+                let Some(Ok(message)) = message else {
+                    break;
+                };
+                last_seen = Instant::now();
+
+                match message {
+                    Message::Text(text) => {
+                        let reply =
+                            handle_command(&data, connection_id, &viewer, &outbox, &text).await;
+                        if session.text(reply.to_string()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Message::Ping(bytes) if session.pong(&bytes).await.is_err() => break,
+                    Message::Close(_) => break,
+                    _ => {}
+                }
+            }
+            Some(event) = events.recv() => {
+                if session.text(event).await.is_err() {
+                    break;
+                }
+            }
+            _ = overflow.notified() => {
+                log::warn!("Realtime client {} is too slow, disconnecting", viewer.user_id);
+                break;
+            }
+            _ = heartbeat.tick() => {
+                // клиент пропал без Close (сон ноутбука, обрыв сети)
+                if last_seen.elapsed() > CLIENT_TIMEOUT || session.ping(b"").await.is_err() {
+                    break;
+                }
+                if !recheck_access(&data, connection_id, session_id, &viewer, &mut session).await {
+                    break;
+                }
+            }
+        }
+    }
+
+    for tree_id in data.hub.disconnect(connection_id) {
+        publish_presence(&data, tree_id);
+    }
+    let _ = session.close(None).await;
+}
+
+/// WebSocket: клиент шлёт {"type": "subscribe" | "unsubscribe", "tree_id": ...},
+/// сервер — события change и presence по деревьям, на которые он подписан.
+#[get("/ws")]
+async fn realtime_handler(
+    auth_guard: AuthenticationGuard,
+    req: HttpRequest,
+    stream: web::Payload,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return Ok(HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"})));
+    };
+    // подписка проверяет роль напрямую, без scope токена
+    let session_id = match auth_guard.require_session() {
+        Ok(session_id) => session_id,
+        Err(response) => return Ok(response),
+    };
+
+    let name = match get_user_by_id(&data.pool, &auth_guard.user_id).await {
+        Ok(Some(user)) => user.name,
+        Ok(None) => {
+            return Ok(HttpResponse::Unauthorized()
+                .json(serde_json::json!({"status": "fail", "message": "User not found"})));
+        }
+        Err(e) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            })));
+        }
+    };
+
+    let (response, session, messages) = actix_ws::handle(&req, stream)?;
+    actix_web::rt::spawn(run_connection(
+        data,
+        session_id,
+        Viewer { user_id, name },
+        session,
+        messages,
+    ));
+
+    Ok(response)
+}
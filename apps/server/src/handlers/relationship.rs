@@ -0,0 +1,527 @@
+use std::cmp::Ordering;
+
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, put, web};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use super::{
+    auth::AuthenticationGuard,
+    etag::{check_if_match, etag, precondition_failed},
+    history::{log_change, snapshot},
+    person::{find_editable_person, role_for_person},
+};
+use crate::{
+    graph::{Partnership, Person, needs_redaction, year_of},
+    model::{
+        AppState, ChangeEntity, ChangeOperation, LinkParentSchema, LinkSiblingsSchema, NewChange,
+        PartnershipSchema, TreeRole,
+    },
+};
+
+/// Обе персоны связи доступны пользователю на правку и лежат в одном дереве.
+pub(super) async fn find_editable_pair(
+    data: &AppState,
+    person1_id: &str,
+    person2_id: &str,
+    auth_guard: &AuthenticationGuard,
+) -> Result<(Person, Person), HttpResponse> {
+    if person1_id == person2_id {
+        return Err(HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "Person cannot be linked to itself"}),
+        ));
+    }
+
+    let person1 = find_editable_person(data, person1_id, auth_guard).await?;
+    let person2 = find_editable_person(data, person2_id, auth_guard).await?;
+
+    if person1.tree_id != person2.tree_id {
+        return Err(HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "Persons belong to different trees"}),
+        ));
+    }
+
+    Ok((person1, person2))
+}
+
+/// Полные даты сравниваются целиком, неполные ("1890", "около 1890") — по году.
+fn compare_dates(a: &str, b: &str) -> Option<Ordering> {
+    match (
+        NaiveDate::parse_from_str(a, "%Y-%m-%d"),
+        NaiveDate::parse_from_str(b, "%Y-%m-%d"),
+    ) {
+        (Ok(a), Ok(b)) => Some(a.cmp(&b)),
+        _ => Some(year_of(a)?.cmp(&year_of(b)?)),
+    }
+}
+
+/// Союз не может закончиться раньше, чем начался, а причина окончания
+/// имеет смысл только вместе с датой окончания.
+pub(super) fn validate_partnership(body: &PartnershipSchema) -> Result<(), HttpResponse> {
+    if body.end_reason.is_some() && body.end_date.is_none() {
+        return Err(HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "End reason requires an end date"}),
+        ));
+    }
+
+    if let (Some(start), Some(end)) = (&body.start_date, &body.end_date)
+        && compare_dates(start, end) == Some(Ordering::Greater)
+    {
+        return Err(HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "End date is before start date"}),
+        ));
+    }
+
+    Ok(())
+}
+
+pub(super) async fn find_partnership(
+    data: &AppState,
+    id: &str,
+) -> Result<Partnership, HttpResponse> {
+    match Partnership::find(&data.graph, id).await {
+        Ok(Some(partnership)) => Ok(partnership),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Partnership not found"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+pub(super) async fn write_parent_link(
+    data: &AppState,
+    user_id: Uuid,
+    parent: &Person,
+    child: &Person,
+) -> Result<(), HttpResponse> {
+    if let Err(e) = Person::link_parent(&data.graph, &parent.id, &child.id).await {
+        return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })));
+    }
+
+    log_change(
+        data,
+        NewChange {
+            tree_id: child.tree_id,
+            user_id,
+            entity: ChangeEntity::ParentLink,
+            entity_id: format!("{}:{}", parent.id, child.id),
+            person_ids: vec![parent.id.clone(), child.id.clone()],
+            operation: ChangeOperation::Create,
+            before: None,
+            after: Some(serde_json::json!({"parent_id": parent.id, "child_id": child.id})),
+            revert_of: None,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn write_siblings_link(
+    data: &AppState,
+    user_id: Uuid,
+    person1: &Person,
+    person2: &Person,
+) -> Result<(), HttpResponse> {
+    if let Err(e) = Person::link_siblings(&data.graph, &person1.id, &person2.id).await {
+        return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })));
+    }
+
+    log_change(
+        data,
+        NewChange {
+            tree_id: person1.tree_id,
+            user_id,
+            entity: ChangeEntity::SiblingLink,
+            entity_id: format!("{}:{}", person1.id, person2.id),
+            person_ids: vec![person1.id.clone(), person2.id.clone()],
+            operation: ChangeOperation::Create,
+            before: None,
+            after: Some(serde_json::json!({"person1_id": person1.id, "person2_id": person2.id})),
+            revert_of: None,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn write_partnership_create(
+    data: &AppState,
+    user_id: Uuid,
+    person1: &Person,
+    person2: &Person,
+    body: &PartnershipSchema,
+) -> Result<Partnership, HttpResponse> {
+    validate_partnership(body)?;
+
+    let partnership = Partnership {
+        id: Uuid::new_v4().to_string(),
+        person1_id: person1.id.clone(),
+        person2_id: person2.id.clone(),
+        kind: body.kind,
+        start_date: body.start_date.clone(),
+        end_date: body.end_date.clone(),
+        end_reason: body.end_reason,
+        person1_order: body.person1_order,
+        person2_order: body.person2_order,
+        version: 1,
+    };
+
+    if let Err(e) = Partnership::create(&data.graph, &partnership).await {
+        return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })));
+    }
+
+    // порядковые номера проставляет база, поэтому в журнал пишем перечитанный союз
+    let partnership = find_partnership(data, &partnership.id).await?;
+
+    log_change(
+        data,
+        NewChange {
+            tree_id: person1.tree_id,
+            user_id,
+            entity: ChangeEntity::Partnership,
+            entity_id: partnership.id.clone(),
+            person_ids: vec![person1.id.clone(), person2.id.clone()],
+            operation: ChangeOperation::Create,
+            before: None,
+            after: snapshot(&partnership),
+            revert_of: None,
+        },
+    )
+    .await?;
+
+    Ok(partnership)
+}
+
+pub(super) async fn write_partnership_update(
+    data: &AppState,
+    user_id: Uuid,
+    before: &Partnership,
+    expected_version: i64,
+    person1: &Person,
+    person2: &Person,
+    body: &PartnershipSchema,
+) -> Result<Partnership, HttpResponse> {
+    validate_partnership(body)?;
+
+    // пару партнёров союза не меняем: для этого союз удаляют и создают заново
+    let same_pair = (body.person1_id == before.person1_id && body.person2_id == before.person2_id)
+        || (body.person1_id == before.person2_id && body.person2_id == before.person1_id);
+    if !same_pair {
+        return Err(HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "Partners of a partnership cannot be changed"}),
+        ));
+    }
+
+    let updated = Partnership {
+        id: before.id.clone(),
+        person1_id: body.person1_id.clone(),
+        person2_id: body.person2_id.clone(),
+        kind: body.kind,
+        start_date: body.start_date.clone(),
+        end_date: body.end_date.clone(),
+        end_reason: body.end_reason,
+        person1_order: body.person1_order,
+        person2_order: body.person2_order,
+        version: before.version,
+    };
+
+    // пишем поверх версии, которую видел автор правки: параллельная правка даёт 412,
+    // а не затирается
+    match Partnership::update(&data.graph, &updated, Some(expected_version)).await {
+        Ok(true) => {}
+        Ok(false) => {
+            let current = find_partnership(data, &before.id).await?;
+            return Err(precondition_failed(
+                "partnership",
+                &current,
+                current.version,
+            ));
+        }
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            })));
+        }
+    }
+
+    let after = find_partnership(data, &before.id).await?;
+
+    log_change(
+        data,
+        NewChange {
+            tree_id: person1.tree_id,
+            user_id,
+            entity: ChangeEntity::Partnership,
+            entity_id: before.id.clone(),
+            person_ids: vec![person1.id.clone(), person2.id.clone()],
+            operation: ChangeOperation::Update,
+            before: snapshot(before),
+            after: snapshot(&after),
+            revert_of: None,
+        },
+    )
+    .await?;
+
+    Ok(after)
+}
+
+#[post("/relationships/parents")]
+async fn link_parent_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<LinkParentSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let (parent, child) =
+        match find_editable_pair(&data, &body.parent_id, &body.child_id, &auth_guard).await {
+            Ok(pair) => pair,
+            Err(response) => return response,
+        };
+
+    match write_parent_link(&data, user_id, &parent, &child).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(response) => response,
+    }
+}
+
+#[post("/relationships/siblings")]
+async fn link_siblings_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<LinkSiblingsSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let (person1, person2) =
+        match find_editable_pair(&data, &body.person1_id, &body.person2_id, &auth_guard).await {
+            Ok(pair) => pair,
+            Err(response) => return response,
+        };
+
+    match write_siblings_link(&data, user_id, &person1, &person2).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(response) => response,
+    }
+}
+
+/// Союзы персоны по порядку. Ниже редактора даты союза скрываются,
+/// если скрыта любая из двух персон: по ним легко восстановить возраст.
+#[get("/persons/{id}/partnerships")]
+async fn get_person_partnerships_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let person = match Person::find(&data.graph, &path).await {
+        Ok(Some(person)) => person,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Person not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    let role = match role_for_person(&data, &person, &auth_guard).await {
+        Ok(Some(role)) => role,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Person not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    let mut partnerships = match Partnership::list_for_person(&data.graph, &person.id).await {
+        Ok(partnerships) => partnerships,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    if role < TreeRole::Editor {
+        for partnership in partnerships.iter_mut() {
+            let hidden = needs_redaction(&person)
+                || match Person::find(&data.graph, &partnership.person2_id).await {
+                    Ok(partner) => partner.is_none_or(|partner| needs_redaction(&partner)),
+                    Err(e) => {
+                        return HttpResponse::InternalServerError().json(serde_json::json!({
+                            "status": "error",
+                            "info": e.to_string()
+                        }));
+                    }
+                };
+            if hidden {
+                partnership.start_date = None;
+                partnership.end_date = None;
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "partnerships": partnerships}))
+}
+
+#[post("/partnerships")]
+async fn create_partnership_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<PartnershipSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let (person1, person2) =
+        match find_editable_pair(&data, &body.person1_id, &body.person2_id, &auth_guard).await {
+            Ok(pair) => pair,
+            Err(response) => return response,
+        };
+
+    match write_partnership_create(&data, user_id, &person1, &person2, &body).await {
+        Ok(partnership) => HttpResponse::Ok()
+            .insert_header(etag(partnership.version))
+            .json(serde_json::json!({"status": "success", "partnership": partnership})),
+        Err(response) => response,
+    }
+}
+
+#[put("/partnerships/{id}")]
+async fn update_partnership_handler(
+    auth_guard: AuthenticationGuard,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<PartnershipSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let before = match find_partnership(&data, &path).await {
+        Ok(partnership) => partnership,
+        Err(response) => return response,
+    };
+
+    let (person1, person2) = match find_editable_pair(
+        &data,
+        &before.person1_id,
+        &before.person2_id,
+        &auth_guard,
+    )
+    .await
+    {
+        Ok(pair) => pair,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = check_if_match(&req, "partnership", &before, before.version) {
+        return response;
+    }
+
+    match write_partnership_update(
+        &data,
+        user_id,
+        &before,
+        before.version,
+        &person1,
+        &person2,
+        &body,
+    )
+    .await
+    {
+        Ok(after) => HttpResponse::Ok()
+            .insert_header(etag(after.version))
+            .json(serde_json::json!({"status": "success", "partnership": after})),
+        Err(response) => response,
+    }
+}
+
+#[delete("/partnerships/{id}")]
+async fn delete_partnership_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let before = match find_partnership(&data, &path).await {
+        Ok(partnership) => partnership,
+        Err(response) => return response,
+    };
+
+    let (person1, person2) = match find_editable_pair(
+        &data,
+        &before.person1_id,
+        &before.person2_id,
+        &auth_guard,
+    )
+    .await
+    {
+        Ok(pair) => pair,
+        Err(response) => return response,
+    };
+
+    if let Err(e) = Partnership::delete(&data.graph, &before.id).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+
+    if let Err(response) = log_change(
+        &data,
+        NewChange {
+            tree_id: person1.tree_id,
+            user_id,
+            entity: ChangeEntity::Partnership,
+            entity_id: before.id.clone(),
+            person_ids: vec![person1.id, person2.id],
+            operation: ChangeOperation::Delete,
+            before: snapshot(&before),
+            after: None,
+            revert_of: None,
+        },
+    )
+    .await
+    {
+        return response;
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
+}
@@ -0,0 +1,440 @@
+use std::fmt;
+
+use actix_web::{HttpResponse, Responder, post, web};
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use super::{
+    auth::AuthenticationGuard,
+    history::{announce_change, snapshot},
+};
+use crate::{
+    graph::{Partnership, Person, PersonName},
+    model::{
+        AppState, Change, ChangeEntity, ChangeOperation, LinkParentSchema, LinkSiblingsSchema,
+        NewChange, RollbackTreeSchema, TreeRole,
+    },
+    repo::{
+        begin_revert, get_change, get_later_changes, get_tree_changes_since, is_change_reverted,
+    },
+};
+
+// Поля персоны, которые правит update/set_privacy; имена журналируются отдельно
+const PERSON_FIELDS: [&str; 4] = ["birth_date", "death_date", "gender", "privacy"];
+
+// Поля союза без version: в снимках до появления версий её нет
+const PARTNERSHIP_FIELDS: [&str; 8] = [
+    "person1_id",
+    "person2_id",
+    "kind",
+    "start_date",
+    "end_date",
+    "end_reason",
+    "person1_order",
+    "person2_order",
+];
+
+pub(super) enum RevertError {
+    // текущее состояние не совпадает с тем, что оставило изменение
+    Conflict(&'static str),
+    // запись журнала не разбирается — отменять нечего
+    Invalid(&'static str),
+    Graph(neo4rs::Error),
+    Db(sqlx::Error),
+}
+
+impl From<neo4rs::Error> for RevertError {
+    fn from(e: neo4rs::Error) -> Self {
+        RevertError::Graph(e)
+    }
+}
+
+impl From<sqlx::Error> for RevertError {
+    fn from(e: sqlx::Error) -> Self {
+        RevertError::Db(e)
+    }
+}
+
+impl fmt::Display for RevertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertError::Conflict(message) | RevertError::Invalid(message) => f.write_str(message),
+            RevertError::Graph(e) => write!(f, "{}", e),
+            RevertError::Db(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn parse_snapshot<T: DeserializeOwned>(
+    value: &Option<serde_json::Value>,
+) -> Result<T, RevertError> {
+    value
+        .clone()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .ok_or(RevertError::Invalid("Change snapshot is malformed"))
+}
+
+fn same_fields(current: &serde_json::Value, expected: &serde_json::Value, fields: &[&str]) -> bool {
+    fields.iter().all(|f| current.get(f) == expected.get(f))
+}
+
+/// Сущность должна выглядеть ровно так, как её оставило изменение.
+/// Иначе её с тех пор правили, и откат затёр бы чужую работу.
+async fn check_current_state(
+    data: &AppState,
+    change: &Change,
+    entity: ChangeEntity,
+) -> Result<(), RevertError> {
+    let matches = match entity {
+        ChangeEntity::Person => {
+            let current = Person::find(&data.graph, &change.entity_id)
+                .await?
+                .and_then(|p| snapshot(&p));
+            match (&current, &change.after) {
+                (None, None) => true,
+                (Some(current), Some(expected)) => same_fields(current, expected, &PERSON_FIELDS),
+                _ => false,
+            }
+        }
+        ChangeEntity::Names => {
+            let current = Person::find(&data.graph, &change.entity_id)
+                .await?
+                .and_then(|p| snapshot(&p.names));
+            current.is_some() && current == change.after
+        }
+        ChangeEntity::Partnership => {
+            let current = Partnership::find(&data.graph, &change.entity_id)
+                .await?
+                .and_then(|p| snapshot(&p));
+            match (&current, &change.after) {
+                (None, None) => true,
+                (Some(current), Some(expected)) => {
+                    same_fields(current, expected, &PARTNERSHIP_FIELDS)
+                }
+                _ => false,
+            }
+        }
+        // у связей нет собственных полей, менять там нечего
+        ChangeEntity::ParentLink | ChangeEntity::SiblingLink => true,
+    };
+
+    if matches {
+        Ok(())
+    } else {
+        Err(RevertError::Conflict(
+            "Entity was modified after this change",
+        ))
+    }
+}
+
+/// Возвращает граф к состоянию до изменения `entity_id` со снимками `before`/`after`.
+async fn apply_inverse(
+    data: &AppState,
+    entity: ChangeEntity,
+    operation: ChangeOperation,
+    entity_id: &str,
+    before: &Option<serde_json::Value>,
+    after: &Option<serde_json::Value>,
+    user_id: Uuid,
+) -> Result<(), RevertError> {
+    let graph = &data.graph;
+
+    match (entity, operation) {
+        // отменённое создание, как и обычное удаление, уходит в корзину
+        (ChangeEntity::Person, ChangeOperation::Create) => {
+            Person::trash(graph, entity_id, &user_id).await?;
+        }
+        // из корзины персона возвращается со всеми связями; если корзину уже
+        // очистили — создаём заново по снимку, но уже без связей
+        (ChangeEntity::Person, ChangeOperation::Delete) => {
+            if Person::find_trashed(graph, entity_id).await?.is_some() {
+                Person::restore(graph, entity_id).await?;
+            } else {
+                let person: Person = parse_snapshot(before)?;
+                Person::create(graph, &person).await?;
+            }
+        }
+        (ChangeEntity::Person, ChangeOperation::Update) => {
+            let person: Person = parse_snapshot(before)?;
+            Person::update(
+                graph,
+                &person.id,
+                &person.birth_date,
+                person.death_date.as_deref(),
+                &person.gender,
+                None,
+            )
+            .await?;
+            Person::set_privacy(graph, &person.id, person.privacy, None).await?;
+        }
+        (ChangeEntity::Names, _) => {
+            let names: Vec<PersonName> = parse_snapshot(before)?;
+            Person::set_names(graph, entity_id, &names, None).await?;
+        }
+        (ChangeEntity::ParentLink, ChangeOperation::Create) => {
+            let link: LinkParentSchema = parse_snapshot(after)?;
+            Person::unlink_parent(graph, &link.parent_id, &link.child_id).await?;
+        }
+        (ChangeEntity::ParentLink, ChangeOperation::Delete) => {
+            let link: LinkParentSchema = parse_snapshot(before)?;
+            Person::link_parent(graph, &link.parent_id, &link.child_id).await?;
+        }
+        (ChangeEntity::SiblingLink, ChangeOperation::Create) => {
+            let link: LinkSiblingsSchema = parse_snapshot(after)?;
+            Person::unlink_siblings(graph, &link.person1_id, &link.person2_id).await?;
+        }
+        (ChangeEntity::SiblingLink, ChangeOperation::Delete) => {
+            let link: LinkSiblingsSchema = parse_snapshot(before)?;
+            Person::link_siblings(graph, &link.person1_id, &link.person2_id).await?;
+        }
+        (ChangeEntity::ParentLink | ChangeEntity::SiblingLink, ChangeOperation::Update) => {
+            return Err(RevertError::Invalid("Links cannot be updated"));
+        }
+        (ChangeEntity::Partnership, ChangeOperation::Create) => {
+            Partnership::delete(graph, entity_id).await?;
+        }
+        (ChangeEntity::Partnership, ChangeOperation::Delete) => {
+            let partnership: Partnership = parse_snapshot(before)?;
+            Partnership::create(graph, &partnership).await?;
+        }
+        (ChangeEntity::Partnership, ChangeOperation::Update) => {
+            let partnership: Partnership = parse_snapshot(before)?;
+            Partnership::update(graph, &partnership, None).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Отменяет одно изменение и пишет отмену в журнал обратной операцией,
+/// так что и сам откат можно отменить.
+async fn revert_change(data: &AppState, change: &Change, user_id: Uuid) -> Result<(), RevertError> {
+    let entity = ChangeEntity::parse(&change.entity_type)
+        .ok_or(RevertError::Invalid("Unknown entity type"))?;
+    let operation = ChangeOperation::parse(&change.operation)
+        .ok_or(RevertError::Invalid("Unknown operation"))?;
+
+    let revert = NewChange {
+        tree_id: change.tree_id,
+        user_id,
+        entity,
+        entity_id: change.entity_id.clone(),
+        person_ids: change.person_ids.clone(),
+        operation: operation.inverse(),
+        before: change.after.clone(),
+        after: change.before.clone(),
+        revert_of: Some(change.id),
+    };
+
+    // сначала занимаем отмену в журнале, и только потом трогаем граф: второй
+    // параллельный откат того же изменения получит конфликт, а не применит его дважды
+    let Some((tx, revert_id)) = begin_revert(&data.pool, &revert).await? else {
+        return Err(RevertError::Conflict("Change is already reverted"));
+    };
+
+    check_current_state(data, change, entity).await?;
+    apply_inverse(
+        data,
+        entity,
+        operation,
+        &change.entity_id,
+        &change.before,
+        &change.after,
+        user_id,
+    )
+    .await?;
+
+    if let Err(e) = tx.commit().await {
+        if let Err(undo_error) = undo_change(data, &revert).await {
+            log::error!(
+                "Failed to undo unrecorded revert of {}: {}",
+                change.id,
+                undo_error
+            );
+        }
+        return Err(e.into());
+    }
+
+    announce_change(data, &revert, revert_id).await;
+
+    Ok(())
+}
+
+/// Отменяет правку, которую не удалось записать в журнал (см. journal_change)
+/// или откат которой не удалось закоммитить.
+pub(super) async fn undo_change(data: &AppState, change: &NewChange) -> Result<(), RevertError> {
+    apply_inverse(
+        data,
+        change.entity,
+        change.operation,
+        &change.entity_id,
+        &change.before,
+        &change.after,
+        change.user_id,
+    )
+    .await
+}
+
+#[post("/changes/{id}/revert")]
+async fn revert_change_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let change = match get_change(&data.pool, path.into_inner()).await {
+        Ok(Some(change)) => change,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Change not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    // правки персон без дерева может отменить только их автор
+    let allowed = match change.tree_id {
+        Some(tree_id) => match auth_guard.tree_role(&data, tree_id).await {
+            Ok(role) => role.is_some_and(|r| r >= TreeRole::Editor),
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": "error",
+                    "info": e.to_string()
+                }));
+            }
+        },
+        None => auth_guard.session_id.is_some() && change.user_id == user_id,
+    };
+    if !allowed {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({"status": "fail", "message": "Editor role is required"}));
+    }
+
+    match is_change_reverted(&data.pool, change.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return HttpResponse::Conflict().json(
+                serde_json::json!({"status": "fail", "message": "Change is already reverted"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    let whole_person = change.entity_type == ChangeEntity::Person.as_str()
+        && change.operation == ChangeOperation::Create.as_str();
+    match get_later_changes(&data.pool, &change, whole_person).await {
+        Ok(later) if later.is_empty() => {}
+        Ok(later) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "status": "fail",
+                "message": "Change has later edits, revert them first",
+                "conflicts": later
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match revert_change(&data, &change, user_id).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(RevertError::Conflict(message)) => {
+            HttpResponse::Conflict().json(serde_json::json!({"status": "fail", "message": message}))
+        }
+        Err(RevertError::Invalid(message)) => HttpResponse::UnprocessableEntity()
+            .json(serde_json::json!({"status": "fail", "message": message})),
+        Err(e @ (RevertError::Graph(_) | RevertError::Db(_))) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Откатывает дерево к состоянию на момент `until`: отменяет все более поздние изменения,
+/// начиная с последнего. Изменения, поверх которых состояние успело разойтись с журналом,
+/// не трогаются и возвращаются в conflicts.
+#[post("/trees/{tree_id}/rollback")]
+async fn rollback_tree_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    body: web::Json<RollbackTreeSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    let tree_id = path.into_inner();
+
+    match auth_guard.tree_role(&data, tree_id).await {
+        Ok(Some(TreeRole::Owner)) => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Forbidden().json(
+                serde_json::json!({"status": "fail", "message": "Only the tree owner can roll back the tree"}),
+            );
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Tree not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    let changes = match get_tree_changes_since(&data.pool, tree_id, body.until).await {
+        Ok(changes) => changes,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    let mut reverted = Vec::new();
+    let mut conflicts = Vec::new();
+    for change in changes.iter() {
+        match revert_change(&data, change, user_id).await {
+            Ok(()) => reverted.push(change.id),
+            Err(RevertError::Conflict(message) | RevertError::Invalid(message)) => {
+                conflicts.push(serde_json::json!({"change": change, "message": message}));
+            }
+            Err(e @ (RevertError::Graph(_) | RevertError::Db(_))) => {
+                // часть изменений уже отменена и записана в журнал — сообщаем, какая
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": "error",
+                    "info": e.to_string(),
+                    "reverted": reverted
+                }));
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "reverted": reverted,
+        "conflicts": conflicts
+    }))
+}
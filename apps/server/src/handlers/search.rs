@@ -0,0 +1,65 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use uuid::Uuid;
+
+use super::auth::AuthenticationGuard;
+use crate::{
+    graph::search_all,
+    model::{AppState, FullTextSearchQuery},
+    repo::get_accessible_tree_ids,
+};
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+
+#[get("/search")]
+async fn search_handler(
+    auth_guard: AuthenticationGuard,
+    query: web::Query<FullTextSearchQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let tree_ids = match get_accessible_tree_ids(&data.pool, user_id).await {
+        Ok(ids) => ids
+            .into_iter()
+            .filter(|id| auth_guard.allows_tree(*id))
+            .collect::<Vec<Uuid>>(),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    match search_all(
+        &data.graph,
+        &tree_ids,
+        &query.q,
+        (page - 1) * per_page,
+        per_page,
+    )
+    .await
+    {
+        Ok(result) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "total": result.total,
+            "page": page,
+            "per_page": per_page,
+            "hits": result.hits
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
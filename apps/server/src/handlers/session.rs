@@ -0,0 +1,284 @@
+use actix_web::{
+    HttpRequest, HttpResponse, HttpResponseBuilder, Responder,
+    cookie::{Cookie, time::Duration as ActixWebDuration},
+    delete, get,
+    http::header,
+    post, web,
+};
+use chrono::{Duration, prelude::*};
+use jsonwebtoken::{EncodingKey, Header, encode};
+use uuid::Uuid;
+
+use super::{auth::AuthenticationGuard, rate_limit::client_ip};
+use crate::{
+    model::{AppState, RefreshOutcome, TokenClaims},
+    repo::{
+        create_session, get_sessions, revoke_all_sessions, revoke_session, rotate_refresh_token,
+    },
+};
+
+pub(super) const ACCESS_COOKIE: &str = "token";
+pub(super) const REFRESH_COOKIE: &str = "refresh_token";
+// refresh-токен не должен уходить с каждым запросом к API, только на /api/auth/*
+const REFRESH_COOKIE_PATH: &str = "/api/auth";
+const IMPERSONATION_MAX_AGE_DAYS: i64 = 1;
+
+/// Устройство и адрес клиента: User-Agent и IP (см. client_ip).
+pub(super) fn client_info(data: &AppState, req: &HttpRequest) -> (String, Option<String>) {
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("unknown")
+        .to_string();
+    let ip = client_ip(data, req);
+
+    (user_agent, ip)
+}
+
+fn new_refresh_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn session_cookies(
+    data: &AppState,
+    user_id: Uuid,
+    session_id: Uuid,
+    refresh_token: String,
+) -> (Cookie<'static>, Cookie<'static>) {
+    let now = Utc::now();
+    let iat = now.timestamp() as usize;
+    let exp = (now + Duration::minutes(data.env.jwt_max_age)).timestamp() as usize;
+    let claims = TokenClaims {
+        sub: user_id.to_string(),
+        sid: session_id.to_string(),
+        exp,
+        iat,
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(data.env.jwt_secret.as_ref()),
+    )
+    .unwrap();
+
+    let access = Cookie::build(ACCESS_COOKIE, token)
+        .path("/")
+        .max_age(ActixWebDuration::new(60 * data.env.jwt_max_age, 0))
+        .http_only(true)
+        .finish();
+    let refresh = Cookie::build(REFRESH_COOKIE, refresh_token)
+        .path(REFRESH_COOKIE_PATH)
+        .max_age(ActixWebDuration::days(data.env.refresh_token_max_age))
+        .http_only(true)
+        .finish();
+
+    (access, refresh)
+}
+
+pub(super) fn clear_session_cookies(response: &mut HttpResponseBuilder) {
+    for (name, path) in [(ACCESS_COOKIE, "/"), (REFRESH_COOKIE, REFRESH_COOKIE_PATH)] {
+        response.cookie(
+            Cookie::build(name, "")
+                .path(path)
+                .max_age(ActixWebDuration::new(-1, 0))
+                .http_only(true)
+                .finish(),
+        );
+    }
+}
+
+/// Заводит сессию при входе (паролем или через OAuth) и выдаёт её cookie.
+pub(super) async fn start_session(
+    data: &AppState,
+    req: &HttpRequest,
+    user_id: Uuid,
+) -> Result<(Cookie<'static>, Cookie<'static>), HttpResponse> {
+    open_session(data, req, user_id, data.env.refresh_token_max_age, None).await
+}
+
+/// Сессия админа от имени пользователя: видна тому в списке сессий
+/// и не продлевается дольше IMPERSONATION_MAX_AGE_DAYS.
+pub(super) async fn start_impersonation(
+    data: &AppState,
+    req: &HttpRequest,
+    user_id: Uuid,
+    admin_id: Uuid,
+) -> Result<(Cookie<'static>, Cookie<'static>), HttpResponse> {
+    open_session(
+        data,
+        req,
+        user_id,
+        IMPERSONATION_MAX_AGE_DAYS,
+        Some(admin_id),
+    )
+    .await
+}
+
+async fn open_session(
+    data: &AppState,
+    req: &HttpRequest,
+    user_id: Uuid,
+    max_age_days: i64,
+    impersonated_by: Option<Uuid>,
+) -> Result<(Cookie<'static>, Cookie<'static>), HttpResponse> {
+    let (user_agent, ip) = client_info(data, req);
+    let refresh_token = new_refresh_token();
+
+    match create_session(
+        &data.pool,
+        user_id,
+        &user_agent,
+        ip.as_deref(),
+        &refresh_token,
+        max_age_days,
+        impersonated_by,
+    )
+    .await
+    {
+        Ok(Some(session_id)) => Ok(session_cookies(data, user_id, session_id, refresh_token)),
+        Ok(None) => Err(HttpResponse::Forbidden()
+            .json(serde_json::json!({"status": "fail", "message": "Account is suspended"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+#[post("/auth/refresh")]
+async fn refresh_handler(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    let Some(refresh_token) = req.cookie(REFRESH_COOKIE).map(|c| c.value().to_string()) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Refresh token is missing"}));
+    };
+
+    let new_token = new_refresh_token();
+    let outcome = rotate_refresh_token(
+        &data.pool,
+        &refresh_token,
+        &new_token,
+        data.env.refresh_token_max_age,
+    )
+    .await;
+
+    match outcome {
+        Ok(RefreshOutcome::Rotated {
+            session_id,
+            user_id,
+        }) => {
+            let (access, refresh) = session_cookies(&data, user_id, session_id, new_token);
+            HttpResponse::Ok()
+                .cookie(access)
+                .cookie(refresh)
+                .json(serde_json::json!({"status": "success"}))
+        }
+        Ok(RefreshOutcome::Reused) => {
+            log::warn!("Refresh token reuse detected, session revoked");
+            let mut response = HttpResponse::Unauthorized();
+            clear_session_cookies(&mut response);
+            response.json(serde_json::json!({
+                "status": "fail",
+                "message": "Refresh token reuse detected, session revoked"
+            }))
+        }
+        Ok(RefreshOutcome::Invalid) => {
+            let mut response = HttpResponse::Unauthorized();
+            clear_session_cookies(&mut response);
+            response.json(
+                serde_json::json!({"status": "fail", "message": "Invalid or expired refresh token"}),
+            )
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[get("/sessions")]
+async fn get_sessions_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let current_session = match auth_guard.require_session() {
+        Ok(session_id) => session_id,
+        Err(response) => return response,
+    };
+
+    match get_sessions(&data.pool, user_id, current_session).await {
+        Ok(sessions) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "sessions": sessions}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[delete("/sessions/{id}")]
+async fn revoke_session_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    let current_session = match auth_guard.require_session() {
+        Ok(session_id) => session_id,
+        Err(response) => return response,
+    };
+    let session_id = path.into_inner();
+
+    match revoke_session(&data.pool, session_id, user_id).await {
+        Ok(true) => {
+            let mut response = HttpResponse::Ok();
+            if session_id == current_session {
+                clear_session_cookies(&mut response);
+            }
+            response.json(serde_json::json!({"status": "success"}))
+        }
+        Ok(false) => HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Session not found"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Выход на всех устройствах, включая текущее.
+#[post("/sessions/revoke_all")]
+async fn revoke_all_sessions_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    match revoke_all_sessions(&data.pool, user_id, None).await {
+        Ok(revoked) => {
+            let mut response = HttpResponse::Ok();
+            clear_session_cookies(&mut response);
+            response.json(serde_json::json!({"status": "success", "revoked": revoked}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
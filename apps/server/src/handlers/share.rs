@@ -0,0 +1,234 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, web};
+use uuid::Uuid;
+
+use super::{
+    auth::AuthenticationGuard,
+    rate_limit::{ensure_not_locked, ip_key, limit, reset},
+    verification::require_verified,
+};
+use crate::{
+    graph::{Person, needs_redaction, redact},
+    model::{AppState, CreateShareLinkSchema, TreeRole},
+    repo::{
+        create_share_link, get_active_share_link, get_share_link_by_id, get_share_links_for_tree,
+        revoke_share_link,
+    },
+};
+
+// Пароль ссылки передаётся заголовком, чтобы не оседать в логах вместе с URL
+pub const SHARE_PASSWORD_HEADER: &str = "x-share-password";
+
+/// Ссылками на дерево управляют редакторы и владелец.
+async fn require_editor(
+    data: &AppState,
+    tree_id: Uuid,
+    auth_guard: &AuthenticationGuard,
+) -> Result<(), HttpResponse> {
+    match auth_guard.tree_role(data, tree_id).await {
+        Ok(Some(role)) if role >= TreeRole::Editor => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(serde_json::json!({"status": "fail", "message": "Editor role is required"}))),
+        Ok(None) => Err(HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Tree not found"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+#[post("/trees/{tree_id}/shares")]
+async fn create_share_link_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    body: web::Json<CreateShareLinkSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    let tree_id = path.into_inner();
+
+    if let Err(response) = require_editor(&data, tree_id, &auth_guard).await {
+        return response;
+    }
+    if let Err(response) = require_verified(&data, user_id).await {
+        return response;
+    }
+
+    // корень поддерева должен принадлежать этому же дереву
+    if let Some(root_id) = &body.root_person_id {
+        match Person::find(&data.graph, root_id).await {
+            Ok(Some(person)) if person.tree_id == Some(tree_id) => {}
+            Ok(_) => {
+                return HttpResponse::BadRequest().json(
+                    serde_json::json!({"status": "fail", "message": "Root person is not in this tree"}),
+                );
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": "error",
+                    "info": e.to_string()
+                }));
+            }
+        }
+    }
+
+    let password = body.password.as_deref().filter(|p| !p.is_empty());
+
+    match create_share_link(
+        &data.pool,
+        tree_id,
+        user_id,
+        body.root_person_id.as_deref(),
+        body.expires_at,
+        password,
+    )
+    .await
+    {
+        Ok(link) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "share": link}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[get("/trees/{tree_id}/shares")]
+async fn get_share_links_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let tree_id = path.into_inner();
+
+    if let Err(response) = require_editor(&data, tree_id, &auth_guard).await {
+        return response;
+    }
+
+    match get_share_links_for_tree(&data.pool, tree_id).await {
+        Ok(links) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "shares": links}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[delete("/shares/{id}")]
+async fn revoke_share_link_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let link = match get_share_link_by_id(&data.pool, path.into_inner()).await {
+        Ok(Some(link)) => link,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Share link not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    if let Err(response) = require_editor(&data, link.tree_id, &auth_guard).await {
+        return response;
+    }
+
+    match revoke_share_link(&data.pool, link.id).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Публичный просмотр по ссылке, без авторизации. Зритель по ссылке — не участник
+/// дерева, поэтому живые персоны скрываются всегда.
+#[get("/public/shares/{token}")]
+async fn get_shared_tree_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // отозванная, истёкшая и несуществующая ссылки неотличимы снаружи
+    let link = match get_active_share_link(&data.pool, &path).await {
+        Ok(Some(link)) => link,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Share link not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    if let Some(hash) = &link.password {
+        let password = req
+            .headers()
+            .get(SHARE_PASSWORD_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+
+        // перебор пароля ограничен и по ссылке, и по адресу
+        let link_limit = format!("share:link:{}", link.id);
+        let ip_limit = ip_key(&data, &req, "share");
+        for key in [&link_limit, &ip_limit] {
+            if let Err(response) = ensure_not_locked(&data, key).await {
+                return response;
+            }
+        }
+
+        if !bcrypt::verify(password, hash).unwrap_or(false) {
+            let by_link = limit(&data, &link_limit, &data.env.rate_limit.share_password_link).await;
+            let by_ip = limit(&data, &ip_limit, &data.env.rate_limit.share_password_ip).await;
+            if let Err(response) = by_link.and(by_ip) {
+                return response;
+            }
+            return HttpResponse::Unauthorized().json(
+                serde_json::json!({"status": "fail", "message": "Share link password is required"}),
+            );
+        }
+        reset(&data, &link_limit).await;
+    }
+
+    let persons = match &link.root_person_id {
+        Some(root_id) => Person::list_subtree(&data.graph, root_id).await,
+        None => Person::list_for_tree(&data.graph, &link.tree_id).await,
+    };
+
+    match persons {
+        Ok(mut persons) => {
+            // супруги из чужих деревьев по ссылке не раскрываются
+            persons.retain(|p| p.tree_id == Some(link.tree_id));
+            for person in persons.iter_mut() {
+                if needs_redaction(person) {
+                    redact(person);
+                }
+            }
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": "success",
+                "tree_id": link.tree_id,
+                "root_person_id": link.root_person_id,
+                "persons": persons
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
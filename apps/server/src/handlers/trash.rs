@@ -0,0 +1,281 @@
+use actix_web::{HttpResponse, Responder, delete, get, post, web};
+use uuid::Uuid;
+
+use super::{
+    auth::AuthenticationGuard,
+    history::{log_change, snapshot},
+    person::{find_editable_person, reload, role_for_person},
+};
+use crate::{
+    graph::Person,
+    jobs::TRASH_RETENTION_DAYS,
+    model::{AppState, ChangeEntity, ChangeOperation, NewChange, TreeRole},
+    repo::{get_trashed_tree, get_trashed_trees_for_owner, restore_tree, trash_tree},
+};
+
+#[delete("/persons/{id}")]
+async fn delete_person_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let before = match find_editable_person(&data, &path, &auth_guard).await {
+        Ok(person) => person,
+        Err(response) => return response,
+    };
+
+    if let Err(e) = Person::trash(&data.graph, &before.id, &user_id).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+
+    if let Err(response) = log_change(
+        &data,
+        NewChange {
+            tree_id: before.tree_id,
+            user_id,
+            entity: ChangeEntity::Person,
+            entity_id: before.id.clone(),
+            person_ids: vec![before.id.clone()],
+            operation: ChangeOperation::Delete,
+            before: snapshot(&before),
+            after: None,
+            revert_of: None,
+        },
+    )
+    .await
+    {
+        return response;
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
+}
+
+#[post("/persons/{id}/restore")]
+async fn restore_person_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    let person = match Person::find_trashed(&data.graph, &path).await {
+        Ok(Some(person)) => person,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(
+                serde_json::json!({"status": "fail", "message": "Person not found in trash"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    match role_for_person(&data, &person, &auth_guard).await {
+        Ok(Some(role)) if role >= TreeRole::Editor => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Forbidden()
+                .json(serde_json::json!({"status": "fail", "message": "Editor role is required"}));
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound().json(
+                serde_json::json!({"status": "fail", "message": "Person not found in trash"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    if let Err(e) = Person::restore(&data.graph, &person.id).await {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }));
+    }
+
+    let after = match reload(&data, &person.id).await {
+        Ok(person) => person,
+        Err(response) => return response,
+    };
+
+    if let Err(response) = log_change(
+        &data,
+        NewChange {
+            tree_id: after.tree_id,
+            user_id,
+            entity: ChangeEntity::Person,
+            entity_id: after.id.clone(),
+            person_ids: vec![after.id.clone()],
+            operation: ChangeOperation::Create,
+            before: None,
+            after: snapshot(&after),
+            revert_of: None,
+        },
+    )
+    .await
+    {
+        return response;
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "person": after}))
+}
+
+#[get("/trees/{tree_id}/trash")]
+async fn get_tree_trash_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let tree_id = path.into_inner();
+
+    match auth_guard.tree_role(&data, tree_id).await {
+        Ok(Some(role)) if role >= TreeRole::Editor => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Forbidden()
+                .json(serde_json::json!({"status": "fail", "message": "Editor role is required"}));
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Tree not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match Person::list_trash(&data.graph, &tree_id).await {
+        Ok(persons) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "retention_days": TRASH_RETENTION_DAYS,
+            "persons": persons
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[delete("/trees/{tree_id}")]
+async fn delete_tree_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    let tree_id = path.into_inner();
+
+    match auth_guard.tree_role(&data, tree_id).await {
+        Ok(Some(TreeRole::Owner)) => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Forbidden().json(
+                serde_json::json!({"status": "fail", "message": "Only the tree owner can delete the tree"}),
+            );
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "Tree not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match trash_tree(&data.pool, tree_id, user_id).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[get("/trash/trees")]
+async fn get_trashed_trees_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    match get_trashed_trees_for_owner(&data.pool, user_id).await {
+        Ok(trees) => {
+            let trees: Vec<_> = trees
+                .into_iter()
+                .filter(|tree| auth_guard.allows_tree(tree.id))
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": "success",
+                "retention_days": TRASH_RETENTION_DAYS,
+                "trees": trees
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[post("/trees/{tree_id}/restore")]
+async fn restore_tree_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    // удалить дерево токен не может (его роль не выше редактора), восстановить — тоже
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    // дерево в корзине видно только владельцу
+    match get_trashed_tree(&data.pool, path.into_inner()).await {
+        Ok(Some(tree)) if tree.owner_id == user_id => {
+            match restore_tree(&data.pool, tree.id).await {
+                Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+                Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": "error",
+                    "info": e.to_string()
+                })),
+            }
+        }
+        Ok(_) => HttpResponse::NotFound()
+            .json(serde_json::json!({"status": "fail", "message": "Tree not found in trash"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
@@ -0,0 +1,124 @@
+use actix_web::{HttpResponse, Responder, get, post, web};
+use uuid::Uuid;
+
+use super::{auth::AuthenticationGuard, verification::require_verified};
+use crate::{
+    model::{AddTreeMemberSchema, AppState, CreateTreeSchema, TreeRole},
+    repo::{add_tree_member, create_tree, get_trees_for_user, get_user_by_email},
+};
+
+#[post("/trees")]
+async fn create_tree_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<CreateTreeSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_session() {
+        return response;
+    }
+
+    if body.name.trim().is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"status": "fail", "message": "Tree name is required"}));
+    }
+
+    match create_tree(&data.pool, user_id, body.name.trim()).await {
+        Ok(tree) => HttpResponse::Ok().json(serde_json::json!({"status": "success", "tree": tree})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[get("/trees")]
+async fn get_trees_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+
+    match get_trees_for_user(&data.pool, user_id).await {
+        Ok(trees) => {
+            let trees: Vec<_> = trees
+                .into_iter()
+                .filter(|tree| auth_guard.allows_tree(tree.id))
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "trees": trees}))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[post("/trees/{tree_id}/members")]
+async fn add_tree_member_handler(
+    auth_guard: AuthenticationGuard,
+    path: web::Path<Uuid>,
+    body: web::Json<AddTreeMemberSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    let tree_id = path.into_inner();
+
+    match auth_guard.tree_role(&data, tree_id).await {
+        Ok(Some(TreeRole::Owner)) => {}
+        Ok(_) => {
+            return HttpResponse::Forbidden().json(
+                serde_json::json!({"status": "fail", "message": "Only the tree owner can add members"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    if body.role == TreeRole::Owner {
+        return HttpResponse::BadRequest().json(
+            serde_json::json!({"status": "fail", "message": "Tree can have only one owner"}),
+        );
+    }
+    if let Err(response) = require_verified(&data, user_id).await {
+        return response;
+    }
+
+    let member = match get_user_by_email(&data.pool, &body.email.to_lowercase()).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "fail", "message": "User not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    match add_tree_member(&data.pool, tree_id, member.id.unwrap(), body.role).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Ok(false) => HttpResponse::Conflict().json(
+            serde_json::json!({"status": "fail", "message": "Tree owner cannot be added as a member"}),
+        ),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
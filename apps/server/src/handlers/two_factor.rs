@@ -0,0 +1,439 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, post, web};
+use chrono::{Duration, prelude::*};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use super::{
+    action_token::{consume_token, decode_token, issue_action_token},
+    auth::{AuthenticationGuard, user_to_response},
+    notification::notify_login,
+    session::start_session,
+};
+use crate::{
+    model::{ActionPurpose, AppState, SecondFactorSchema, TotpCodeSchema, TwoFactorLoginSchema},
+    repo::{
+        disable_totp, enable_totp, get_totp, get_user_by_id, is_action_token_active,
+        is_totp_enabled, mark_totp_step_used, record_failed_action_attempt, replace_recovery_codes,
+        start_totp_setup, use_recovery_code,
+    },
+};
+
+const ISSUER: &str = "Family Tree";
+const STEP_SECONDS: i64 = 30;
+const RECOVERY_CODE_COUNT: usize = 10;
+const PRE_AUTH_TTL_MINUTES: i64 = 5;
+// после стольких неверных кодов pre-auth токен гаснет и нужно снова вводить пароль
+const PRE_AUTH_MAX_ATTEMPTS: i32 = 5;
+
+fn build_totp(secret: &str, account: &str) -> Option<TOTP> {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().ok()?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        STEP_SECONDS as u64,
+        secret,
+        Some(ISSUER.to_string()),
+        account.to_string(),
+    )
+    .ok()
+}
+
+/// Шаг, которому соответствует код, с допуском в один шаг на расхождение часов.
+fn matching_step(totp: &TOTP, code: &str) -> Option<i64> {
+    let code = code.trim();
+    let step = Utc::now().timestamp() / STEP_SECONDS;
+
+    (step - 1..=step + 1).find(|s| totp.generate((s * STEP_SECONDS) as u64) == code)
+}
+
+fn new_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw = Uuid::new_v4().simple().to_string();
+            format!("{}-{}-{}", &raw[..4], &raw[4..8], &raw[8..12])
+        })
+        .collect()
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().to_lowercase()
+}
+
+/// Проверяет код из приложения. Принятый код запоминается и второй раз не проходит.
+async fn check_totp_code(data: &AppState, user_id: Uuid, code: &str) -> Result<bool, sqlx::Error> {
+    let Some(stored) = get_totp(&data.pool, user_id).await? else {
+        return Ok(false);
+    };
+    let Some(totp) = build_totp(&stored.secret, "") else {
+        return Ok(false);
+    };
+
+    match matching_step(&totp, code) {
+        Some(step) => mark_totp_step_used(&data.pool, user_id, step).await,
+        None => Ok(false),
+    }
+}
+
+/// Второй фактор: код из приложения либо одноразовый код восстановления.
+async fn check_second_factor(
+    data: &AppState,
+    user_id: Uuid,
+    code: Option<&str>,
+    recovery_code: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    match (code, recovery_code) {
+        (Some(code), _) => check_totp_code(data, user_id, code).await,
+        (None, Some(recovery_code)) => {
+            use_recovery_code(&data.pool, user_id, &normalize_recovery_code(recovery_code)).await
+        }
+        (None, None) => Ok(false),
+    }
+}
+
+/// Если у пользователя включена 2FA, вместо сессии выдаёт pre-auth токен:
+/// с ним можно только пройти второй шаг входа.
+pub(super) async fn begin_two_factor_login(
+    data: &AppState,
+    user_id: Uuid,
+) -> Result<Option<String>, HttpResponse> {
+    let token = match is_totp_enabled(&data.pool, user_id).await {
+        Ok(true) => {
+            issue_action_token(
+                data,
+                user_id,
+                ActionPurpose::TwoFactorLogin,
+                Duration::minutes(PRE_AUTH_TTL_MINUTES),
+            )
+            .await
+        }
+        Ok(false) => return Ok(None),
+        Err(e) => Err(e),
+    };
+
+    match token {
+        Ok(token) => Ok(Some(token)),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+#[post("/auth/2fa/login")]
+async fn two_factor_login_handler(
+    req: HttpRequest,
+    body: web::Json<TwoFactorLoginSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let invalid = || {
+        HttpResponse::Unauthorized().json(serde_json::json!({
+            "status": "fail",
+            "message": "Invalid or expired pre-auth token, please log in again"
+        }))
+    };
+
+    let Some((token_id, user_id)) =
+        decode_token(&data, &body.pre_auth_token, ActionPurpose::TwoFactorLogin)
+    else {
+        return invalid();
+    };
+
+    // погашенным токеном коды не проверяем, иначе перебор продолжался бы после лимита
+    match is_action_token_active(&data.pool, token_id, ActionPurpose::TwoFactorLogin).await {
+        Ok(true) => {}
+        Ok(false) => return invalid(),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    let passed = check_second_factor(
+        &data,
+        user_id,
+        body.code.as_deref(),
+        body.recovery_code.as_deref(),
+    )
+    .await;
+
+    match passed {
+        Ok(true) => {}
+        Ok(false) => {
+            if let Err(e) =
+                record_failed_action_attempt(&data.pool, token_id, PRE_AUTH_MAX_ATTEMPTS).await
+            {
+                log::error!("Failed to record 2FA attempt of {}: {}", user_id, e);
+            }
+            return HttpResponse::Unauthorized().json(
+                serde_json::json!({"status": "fail", "message": "Invalid authentication code"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    // гасим токен только после верного кода: опечатка не заставляет вводить пароль заново
+    match consume_token(&data, &body.pre_auth_token, ActionPurpose::TwoFactorLogin).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return invalid(),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    let user = match get_user_by_id(&data.pool, &user_id.to_string()).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return invalid(),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    notify_login(&data, &req, user_id, &user.provider.to_lowercase()).await;
+
+    let (access, refresh) = match start_session(&data, &req, user_id).await {
+        Ok(cookies) => cookies,
+        Err(response) => return response,
+    };
+
+    HttpResponse::Ok()
+        .cookie(access)
+        .cookie(refresh)
+        .json(serde_json::json!({"status": "success", "user": user_to_response(&user)}))
+}
+
+/// Первый шаг подключения: новый секрет и otpauth:// URI для QR-кода.
+/// 2FA включится, только когда пользователь подтвердит код из приложения.
+#[post("/auth/2fa/setup")]
+async fn setup_two_factor_handler(
+    auth_guard: AuthenticationGuard,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_own_session() {
+        return response;
+    }
+
+    let email = match get_user_by_id(&data.pool, &auth_guard.user_id).await {
+        Ok(Some(user)) => user.email,
+        Ok(None) => {
+            return HttpResponse::Unauthorized()
+                .json(serde_json::json!({"status": "fail", "message": "User not found"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    let Secret::Encoded(secret) = Secret::generate_secret().to_encoded() else {
+        unreachable!("to_encoded always returns an encoded secret");
+    };
+    let Some(totp) = build_totp(&secret, &email) else {
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({"status": "error", "info": "Failed to create TOTP"}));
+    };
+
+    match start_totp_setup(&data.pool, user_id, &secret).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "secret": secret,
+            "otpauth_url": totp.get_url()
+        })),
+        Ok(false) => HttpResponse::Conflict().json(
+            serde_json::json!({"status": "fail", "message": "Two-factor authentication is already enabled"}),
+        ),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Подтверждает подключение кодом из приложения и выдаёт коды восстановления.
+/// Коды показываются один раз, в базе лежат только их хеши.
+#[post("/auth/2fa/enable")]
+async fn enable_two_factor_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<TotpCodeSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_own_session() {
+        return response;
+    }
+
+    match get_totp(&data.pool, user_id).await {
+        Ok(Some(totp)) if totp.enabled_at.is_none() => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "status": "fail",
+                "message": "Two-factor authentication is already enabled"
+            }));
+        }
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": "fail",
+                "message": "Start two-factor setup first"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match check_totp_code(&data, user_id, &body.code).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(
+                serde_json::json!({"status": "fail", "message": "Invalid authentication code"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    let recovery_codes = new_recovery_codes();
+    match enable_totp(&data.pool, user_id, &recovery_codes).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "recovery_codes": recovery_codes
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+#[post("/auth/2fa/disable")]
+async fn disable_two_factor_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<SecondFactorSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_own_session() {
+        return response;
+    }
+
+    match check_second_factor(
+        &data,
+        user_id,
+        body.code.as_deref(),
+        body.recovery_code.as_deref(),
+    )
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(
+                serde_json::json!({"status": "fail", "message": "Invalid authentication code"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match disable_totp(&data.pool, user_id).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+/// Новый набор кодов восстановления взамен старого — по коду из приложения.
+#[post("/auth/2fa/recovery_codes")]
+async fn regenerate_recovery_codes_handler(
+    auth_guard: AuthenticationGuard,
+    body: web::Json<TotpCodeSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Ok(user_id) = Uuid::parse_str(&auth_guard.user_id) else {
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({"status": "fail", "message": "Invalid token"}));
+    };
+    if let Err(response) = auth_guard.require_own_session() {
+        return response;
+    }
+
+    match is_totp_enabled(&data.pool, user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": "fail",
+                "message": "Two-factor authentication is not enabled"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    match check_totp_code(&data, user_id, &body.code).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(
+                serde_json::json!({"status": "fail", "message": "Invalid authentication code"}),
+            );
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    let recovery_codes = new_recovery_codes();
+    match replace_recovery_codes(&data.pool, user_id, &recovery_codes).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "recovery_codes": recovery_codes
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
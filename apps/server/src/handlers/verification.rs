@@ -0,0 +1,152 @@
+use std::error::Error;
+
+use actix_web::{HttpResponse, Responder, post, web};
+use chrono::{Duration, prelude::*};
+use uuid::Uuid;
+
+use super::action_token::{consume_token, issue_action_token};
+use crate::{
+    model::{ActionPurpose, AppState, ResendVerificationSchema, User, VerifyEmailSchema},
+    repo::{get_recent_action_tokens, get_user_by_email, get_user_by_id, set_user_verified},
+};
+
+const VERIFY_TOKEN_TTL_HOURS: i64 = 24;
+const RESEND_COOLDOWN_SECONDS: i64 = 60;
+const RESEND_DAILY_LIMIT: i64 = 5;
+
+pub(super) async fn send_verification_email(
+    data: &AppState,
+    user: &User,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some(user_id) = user.id else {
+        return Err("User has no id".into());
+    };
+
+    let token = issue_action_token(
+        data,
+        user_id,
+        ActionPurpose::VerifyEmail,
+        Duration::hours(VERIFY_TOKEN_TTL_HOURS),
+    )
+    .await?;
+    let link = format!("{}/verify-email?token={}", data.env.client_origin, token);
+
+    data.mailer
+        .send(
+            &user.email,
+            "Confirm your email",
+            format!(
+                "Hi {},\n\nPlease confirm your email address by opening this link:\n{}\n\n\
+                 The link is valid for {} hours.",
+                user.name, link, VERIFY_TOKEN_TTL_HOURS
+            ),
+        )
+        .await
+}
+
+/// С REQUIRE_VERIFIED_EMAIL неподтверждённым пользователям нельзя открывать доступ к деревьям.
+pub(super) async fn require_verified(data: &AppState, user_id: Uuid) -> Result<(), HttpResponse> {
+    if !data.env.require_verified_email {
+        return Ok(());
+    }
+
+    match get_user_by_id(&data.pool, &user_id.to_string()).await {
+        Ok(Some(user)) if user.verified => Ok(()),
+        Ok(_) => Err(HttpResponse::Forbidden()
+            .json(serde_json::json!({"status": "fail", "message": "Email is not verified"}))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        }))),
+    }
+}
+
+#[post("/auth/verify_email")]
+async fn verify_email_handler(
+    body: web::Json<VerifyEmailSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = match consume_token(&data, &body.token, ActionPurpose::VerifyEmail).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": "fail",
+                "message": "Invalid or expired verification token"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+
+    match set_user_verified(&data.pool, user_id).await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({"status": "success"})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": "error",
+            "info": e.to_string()
+        })),
+    }
+}
+
+// Ответ одинаковый для неизвестной, уже подтверждённой и неподтверждённой почты,
+// чтобы по нему нельзя было перебирать адреса
+#[post("/auth/verify_email/resend")]
+async fn resend_verification_handler(
+    body: web::Json<ResendVerificationSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let sent = HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "If the account exists and is not verified, a new email has been sent"
+    }));
+
+    let user = match get_user_by_email(&data.pool, &body.email.to_lowercase()).await {
+        Ok(Some(user)) if !user.verified && user.provider == "local" => user,
+        Ok(_) => return sent,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    };
+    let Some(user_id) = user.id else {
+        return sent;
+    };
+
+    let now = Utc::now().naive_utc();
+    let recent = get_recent_action_tokens(
+        &data.pool,
+        user_id,
+        ActionPurpose::VerifyEmail,
+        now - Duration::days(1),
+    )
+    .await;
+
+    // лимиты соблюдаем молча: 429 выдал бы, что адрес зарегистрирован
+    match recent {
+        Ok((_, _, Some(last))) if now - last < Duration::seconds(RESEND_COOLDOWN_SECONDS) => {
+            return sent;
+        }
+        Ok((count, _, _)) if count >= RESEND_DAILY_LIMIT => return sent,
+        Ok(_) => {}
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": "error",
+                "info": e.to_string()
+            }));
+        }
+    }
+
+    if let Err(e) = send_verification_email(&data, &user).await {
+        log::error!("Failed to send verification email to {}: {}", user_id, e);
+        return HttpResponse::InternalServerError().json(
+            serde_json::json!({"status": "error", "message": "Failed to send verification email"}),
+        );
+    }
+
+    sent
+}
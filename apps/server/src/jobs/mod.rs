@@ -0,0 +1,5 @@
+mod purge;
+mod rate_limit;
+
+pub use purge::{TRASH_RETENTION_DAYS, spawn_trash_purge};
+pub use rate_limit::spawn_rate_limit_purge;
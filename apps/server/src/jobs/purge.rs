@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use neo4rs::Graph;
+use sqlx::PgPool;
+
+use crate::{
+    graph::Person,
+    repo::{delete_tree, get_expired_trashed_tree_ids},
+};
+
+// Сколько удалённые персоны и деревья лежат в корзине до окончательного удаления
+pub const TRASH_RETENTION_DAYS: i32 = 30;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+async fn purge_expired(
+    pool: &PgPool,
+    graph: &Graph,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let persons = Person::purge_trashed(graph, TRASH_RETENTION_DAYS as i64).await?;
+    if persons > 0 {
+        log::info!("Purged {} persons from trash", persons);
+    }
+
+    // сначала граф, потом Postgres: если упадём посередине, дерево останется
+    // в корзине и следующий проход дочистит его
+    for tree_id in get_expired_trashed_tree_ids(pool, TRASH_RETENTION_DAYS).await? {
+        Person::purge_tree(graph, &tree_id).await?;
+        delete_tree(pool, tree_id).await?;
+        log::info!("Purged tree {} from trash", tree_id);
+    }
+
+    Ok(())
+}
+
+/// Раз в час окончательно удаляет всё, что пролежало в корзине дольше TRASH_RETENTION_DAYS.
+pub fn spawn_trash_purge(pool: PgPool, graph: Graph) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = purge_expired(&pool, &graph).await {
+                log::error!("Trash purge failed: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,18 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::model::AppState;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Раз в десять минут выбрасывает отжившие счётчики попыток входа.
+pub fn spawn_rate_limit_purge(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = state.limiter.purge_expired().await {
+                log::error!("Rate limit purge failed: {}", e);
+            }
+        }
+    });
+}
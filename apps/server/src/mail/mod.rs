@@ -0,0 +1,57 @@
+use std::error::Error;
+
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, message::Mailbox,
+    transport::smtp::authentication::Credentials,
+};
+
+use crate::config::Config;
+
+pub struct Mailer {
+    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn init(config: &Config) -> Mailer {
+        let transport = config.smtp_host.as_ref().map(|host| {
+            let mut builder = AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+                .expect("SMTP_HOST must be a valid host");
+            if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password)
+            {
+                builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+            }
+            builder.build()
+        });
+
+        Mailer {
+            transport,
+            from: config
+                .mail_from
+                .parse()
+                .expect("MAIL_FROM must be a valid mailbox"),
+        }
+    }
+
+    /// Без SMTP письмо только пишется в лог — для локальной разработки.
+    pub async fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        body: String,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(transport) = &self.transport else {
+            log::info!("Mail to {} ({}):\n{}", to, subject, body);
+            return Ok(());
+        };
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject)
+            .body(body)?;
+        transport.send(message).await?;
+
+        Ok(())
+    }
+}
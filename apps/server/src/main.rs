@@ -1,7 +1,14 @@
 mod config;
+mod graph;
 mod handlers;
+mod jobs;
+mod mail;
 mod model;
+mod oidc;
+mod rate_limit;
+mod realtime;
 mod repo;
+mod text;
 
 use actix_cors::Cors;
 use actix_web::middleware::Logger;
@@ -26,8 +33,15 @@ async fn main() -> std::io::Result<()> {
         .await
         .unwrap();
 
+    graph::init_schema(&graph)
+        .await
+        .expect("Failed to init graph schema");
+
+    jobs::spawn_trash_purge(pool.clone(), graph.clone());
+
     let db = AppState::init(pool, graph);
     let app_data = web::Data::new(db);
+    jobs::spawn_rate_limit_purge(app_data.clone().into_inner());
     let public_dir = std::env::current_dir().unwrap().join("public");
 
     println!("🚀 Server started successfully");
@@ -41,7 +55,10 @@ async fn main() -> std::io::Result<()> {
                 header::CONTENT_TYPE,
                 header::AUTHORIZATION,
                 header::ACCEPT,
+                header::IF_MATCH,
+                header::HeaderName::from_static(handlers::SHARE_PASSWORD_HEADER),
             ])
+            .expose_headers(vec![header::ETAG, header::RETRY_AFTER])
             .supports_credentials();
         App::new()
             .app_data(app_data.clone())
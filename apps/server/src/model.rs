@@ -4,7 +4,14 @@ use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Postgres};
 use uuid::Uuid;
 
-use crate::config;
+use crate::{
+    config,
+    graph::{PartnershipEndReason, PartnershipKind, PersonName, Privacy},
+    mail::Mailer,
+    oidc::Registry,
+    rate_limit::{self, RateLimiter},
+    realtime::Hub,
+};
 
 #[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
 pub struct User {
@@ -18,20 +25,493 @@ pub struct User {
     pub provider: String,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
+    pub suspended_at: Option<NaiveDateTime>,
+    pub password_reset_required: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct Tree {
+    pub id: Uuid,
+    pub name: String,
+    pub owner_id: Uuid,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub deleted_by: Option<Uuid>,
+}
+
+// Порядок вариантов важен: роли сравниваются как viewer < editor < owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreeRole {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl TreeRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TreeRole::Viewer => "viewer",
+            TreeRole::Editor => "editor",
+            TreeRole::Owner => "owner",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<TreeRole> {
+        match value {
+            "viewer" => Some(TreeRole::Viewer),
+            "editor" => Some(TreeRole::Editor),
+            "owner" => Some(TreeRole::Owner),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct ShareLink {
+    pub id: Uuid,
+    pub token: String,
+    pub tree_id: Uuid,
+    pub root_person_id: Option<String>,
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_by: Uuid,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeEntity {
+    Person,
+    Names,
+    ParentLink,
+    SiblingLink,
+    Partnership,
+}
+
+impl ChangeEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeEntity::Person => "person",
+            ChangeEntity::Names => "names",
+            ChangeEntity::ParentLink => "parent_link",
+            ChangeEntity::SiblingLink => "sibling_link",
+            ChangeEntity::Partnership => "partnership",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ChangeEntity> {
+        match value {
+            "person" => Some(ChangeEntity::Person),
+            "names" => Some(ChangeEntity::Names),
+            "parent_link" => Some(ChangeEntity::ParentLink),
+            "sibling_link" => Some(ChangeEntity::SiblingLink),
+            "partnership" => Some(ChangeEntity::Partnership),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOperation {
+    Create,
+    Update,
+    Delete,
+}
+
+impl ChangeOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeOperation::Create => "create",
+            ChangeOperation::Update => "update",
+            ChangeOperation::Delete => "delete",
+        }
+    }
+
+    /// Операция, которая отменяет эту.
+    pub fn inverse(&self) -> ChangeOperation {
+        match self {
+            ChangeOperation::Create => ChangeOperation::Delete,
+            ChangeOperation::Update => ChangeOperation::Update,
+            ChangeOperation::Delete => ChangeOperation::Create,
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ChangeOperation> {
+        match value {
+            "create" => Some(ChangeOperation::Create),
+            "update" => Some(ChangeOperation::Update),
+            "delete" => Some(ChangeOperation::Delete),
+            _ => None,
+        }
+    }
+}
+
+/// Запись журнала изменений. before/after — снимки сущности в JSON,
+/// у create нет before, у delete нет after.
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct Change {
+    pub id: i64,
+    pub tree_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub user_name: Option<String>,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub person_ids: Vec<String>,
+    pub operation: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub revert_of: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct NewChange {
+    pub tree_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub entity: ChangeEntity,
+    pub entity_id: String,
+    pub person_ids: Vec<String>,
+    pub operation: ChangeOperation,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub revert_of: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalKind {
+    PersonUpdate,
+    PersonNames,
+    ParentLink,
+    SiblingLink,
+    PartnershipCreate,
+    PartnershipUpdate,
+}
+
+impl ProposalKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProposalKind::PersonUpdate => "person_update",
+            ProposalKind::PersonNames => "person_names",
+            ProposalKind::ParentLink => "parent_link",
+            ProposalKind::SiblingLink => "sibling_link",
+            ProposalKind::PartnershipCreate => "partnership_create",
+            ProposalKind::PartnershipUpdate => "partnership_update",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ProposalKind> {
+        match value {
+            "person_update" => Some(ProposalKind::PersonUpdate),
+            "person_names" => Some(ProposalKind::PersonNames),
+            "parent_link" => Some(ProposalKind::ParentLink),
+            "sibling_link" => Some(ProposalKind::SiblingLink),
+            "partnership_create" => Some(ProposalKind::PartnershipCreate),
+            "partnership_update" => Some(ProposalKind::PartnershipUpdate),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+impl ProposalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProposalStatus::Pending => "pending",
+            ProposalStatus::Accepted => "accepted",
+            ProposalStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// Предложенная правка. payload — тело той же схемы, что принимает прямая правка
+/// (UpdatePersonSchema, LinkParentSchema, PartnershipSchema...).
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct EditProposal {
+    pub id: Uuid,
+    pub tree_id: Uuid,
+    pub kind: String,
+    pub target_id: String,
+    pub payload: serde_json::Value,
+    pub source: Option<String>,
+    pub comment: Option<String>,
+    pub status: String,
+    pub proposed_by: Uuid,
+    pub reviewed_by: Option<Uuid>,
+    pub review_comment: Option<String>,
+    pub reviewed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub base_version: Option<i64>, // версия цели на момент предложения; у связей её нет
+}
+
+/// Узел, к которому привязана ветка. Событий и источников в графе пока нет,
+/// они добавятся сюда вместе с узлами; колонка subject_type к этому готова.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentSubject {
+    Person,
+}
+
+impl CommentSubject {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentSubject::Person => "person",
+        }
+    }
+
+    /// Метка узла в Neo4j.
+    pub fn label(&self) -> &'static str {
+        match self {
+            CommentSubject::Person => "Person",
+        }
+    }
+}
+
+/// Комментарий в ветке. У удалённого body = None, но он остаётся в выдаче,
+/// чтобы ответы на него не потеряли родителя.
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct Comment {
+    pub id: Uuid,
+    pub tree_id: Uuid,
+    pub subject_type: String,
+    pub subject_id: String,
+    pub parent_id: Option<Uuid>,
+    pub author_id: Uuid,
+    pub author_name: Option<String>,
+    pub body: Option<String>,
+    pub mentions: Vec<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub edited_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    PersonChanged,
+    RelationshipChanged,
+    Mention,
+    NewDeviceLogin,
+}
+
+impl NotificationEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationEvent::PersonChanged => "person_changed",
+            NotificationEvent::RelationshipChanged => "relationship_changed",
+            NotificationEvent::Mention => "mention",
+            NotificationEvent::NewDeviceLogin => "new_device_login",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub event_type: String,
+    pub actor_id: Option<Uuid>,
+    pub actor_name: Option<String>,
+    pub tree_id: Option<Uuid>,
+    pub data: serde_json::Value,
+    pub read_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct NewNotification {
+    pub event: NotificationEvent,
+    pub actor_id: Option<Uuid>,
+    pub tree_id: Option<Uuid>,
+    pub data: serde_json::Value,
+}
+
+/// Сессия входа: одна на устройство, продлевается ротацией refresh-токена.
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_agent: String,
+    pub ip: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub current: bool,
+    pub impersonated_by: Option<Uuid>,
+}
+
+/// Что токен доступа может делать в дереве.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenAccess {
+    Read,
+    Write,
+}
+
+impl TokenAccess {
+    /// Наибольшая роль, которую даёт доступ. Права владельца токен не даёт никогда.
+    pub fn max_role(&self) -> TreeRole {
+        match self {
+            TokenAccess::Read => TreeRole::Viewer,
+            TokenAccess::Write => TreeRole::Editor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenScope {
+    pub tree_id: Uuid,
+    pub access: TokenAccess,
+}
+
+/// Персональный токен доступа, без самого значения.
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct AccessToken {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: sqlx::types::Json<Vec<TokenScope>>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAccessTokenSchema {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub expires_in_days: Option<i64>,
+}
+
+/// Внешний аккаунт, через который можно войти.
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct Identity {
+    pub id: Uuid,
+    pub provider: String,
+    pub email: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub enum RefreshOutcome {
+    Rotated { session_id: Uuid, user_id: Uuid },
+    // токен уже меняли: его украли, сессия отозвана
+    Reused,
+    // неизвестный токен, истёкшая или отозванная сессия
+    Invalid,
+}
+
+/// Действующая сессия, см. touch_session.
+#[derive(Debug)]
+pub struct ActiveSession {
+    pub impersonated_by: Option<Uuid>, // админ, вошедший от имени пользователя
+}
+
+/// Пользователь в списке для админа.
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct AdminUser {
+    pub id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub role: String,
+    pub provider: String,
+    pub verified: bool,
+    pub suspended_at: Option<NaiveDateTime>,
+    pub password_reset_required: bool,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// Что пользователь занимает в Postgres. Персоны считаются отдельно, в графе.
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct StorageUsage {
+    pub trees: i64,
+    pub changes: i64,
+    pub history_bytes: i64,
+    pub comments: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    ListUsers,
+    ViewStorage,
+    ViewAudit,
+    Suspend,
+    Unsuspend,
+    Delete,
+    Impersonate,
+    ForcePasswordReset,
+}
+
+impl AdminAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminAction::ListUsers => "list_users",
+            AdminAction::ViewStorage => "view_storage",
+            AdminAction::ViewAudit => "view_audit",
+            AdminAction::Suspend => "suspend",
+            AdminAction::Unsuspend => "unsuspend",
+            AdminAction::Delete => "delete",
+            AdminAction::Impersonate => "impersonate",
+            AdminAction::ForcePasswordReset => "force_password_reset",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub admin_id: Uuid,
+    pub admin_name: Option<String>,
+    pub action: String,
+    pub target_user_id: Option<Uuid>,
+    pub details: serde_json::Value,
+    pub ip: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct UserTotp {
+    pub secret: String, // base32
+    pub enabled_at: Option<NaiveDateTime>,
 }
 
 pub struct AppState {
     pub env: config::Config,
     pub pool: Pool<Postgres>,
     pub graph: Graph,
+    pub hub: Hub,
+    pub mailer: Mailer,
+    pub oidc: Registry,
+    pub limiter: Box<dyn RateLimiter>,
 }
 
 impl AppState {
     pub fn init(p: Pool<Postgres>, g: Graph) -> AppState {
+        let env = config::Config::init();
+        let mailer = Mailer::init(&env);
+        let oidc = Registry::init(&env);
+        let limiter = rate_limit::init(&env.rate_limit, p.clone());
+
         AppState {
-            env: config::Config::init(),
+            env,
             pool: p,
             graph: g,
+            hub: Hub::default(),
+            mailer,
+            oidc,
+            limiter,
         }
     }
 }
@@ -39,6 +519,37 @@ impl AppState {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenClaims {
     pub sub: String,
+    pub sid: String, // sessions.id: отозванная сессия гасит и свои access-токены
+    pub iat: usize,
+    pub exp: usize,
+}
+
+/// Назначение одноразового токена из письма.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionPurpose {
+    VerifyEmail,
+    ResetPassword,
+    // pre-auth токен между паролем и кодом 2FA
+    TwoFactorLogin,
+}
+
+impl ActionPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionPurpose::VerifyEmail => "verify_email",
+            ActionPurpose::ResetPassword => "reset_password",
+            ActionPurpose::TwoFactorLogin => "two_factor_login",
+        }
+    }
+}
+
+/// Подписанный токен из письма; jti — action_tokens.id, по нему токен гасится.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActionTokenClaims {
+    pub sub: String,
+    pub jti: String,
+    pub purpose: ActionPurpose,
     pub iat: usize,
     pub exp: usize,
 }
@@ -49,6 +560,26 @@ pub struct QueryCode {
     pub state: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct OAuthAuthorizeQuery {
+    pub redirect: Option<String>,
+}
+
+/// Подписанное содержимое cookie на время входа через провайдера: с чем сверять
+/// ответный редирект и куда вернуть пользователя.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthFlowClaims {
+    pub provider: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub redirect: String,
+    // вход привязывает аккаунт к уже вошедшему пользователю, а не открывает сессию
+    #[serde(default)]
+    pub link_user: Option<Uuid>,
+    pub exp: usize,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RegisterUserSchema {
     pub name: String,
@@ -61,3 +592,236 @@ pub struct LoginUserSchema {
     pub email: String,
     pub password: String,
 }
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PersonSearchMode {
+    #[default]
+    Name,
+    Phonetic,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchPersonQuery {
+    pub q: String,
+    #[serde(default)]
+    pub mode: PersonSearchMode,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTreeSchema {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FullTextSearchQuery {
+    pub q: String,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddTreeMemberSchema {
+    pub email: String,
+    pub role: TreeRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePrivacySchema {
+    pub privacy: Privacy,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateShareLinkSchema {
+    pub root_person_id: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePersonSchema {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub names: Vec<PersonName>,
+    #[serde(default)]
+    pub birth_date: String,
+    pub death_date: Option<String>,
+    pub gender: String,
+    pub tree_id: Option<Uuid>,
+    #[serde(default)]
+    pub privacy: Privacy,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePersonSchema {
+    #[serde(default)]
+    pub birth_date: String,
+    pub death_date: Option<String>,
+    pub gender: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkParentSchema {
+    pub parent_id: String,
+    pub child_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkSiblingsSchema {
+    pub person1_id: String,
+    pub person2_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartnershipSchema {
+    pub person1_id: String,
+    pub person2_id: String,
+    pub kind: PartnershipKind,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub end_reason: Option<PartnershipEndReason>,
+    pub person1_order: Option<i64>,
+    pub person2_order: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RollbackTreeSchema {
+    pub until: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateProposalSchema {
+    pub kind: ProposalKind,
+    pub target_id: String,
+    pub payload: serde_json::Value,
+    pub source: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewProposalSchema {
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProposalQuery {
+    pub status: Option<ProposalStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommentsQuery {
+    pub subject_type: CommentSubject,
+    pub subject_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCommentSchema {
+    pub subject_type: CommentSubject,
+    pub subject_id: String,
+    pub parent_id: Option<Uuid>,
+    pub body: String,
+    #[serde(default)]
+    pub mentions: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCommentSchema {
+    pub body: String,
+    #[serde(default)]
+    pub mentions: Vec<Uuid>,
+}
+
+/// Команда клиента по WebSocket, см. handlers::realtime.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RealtimeCommand {
+    Subscribe { tree_id: Uuid },
+    Unsubscribe { tree_id: Uuid },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationsQuery {
+    #[serde(default)]
+    pub unread_only: bool,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailSchema {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationSchema {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordSchema {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordSchema {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordSchema {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpCodeSchema {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetPasswordSchema {
+    pub password: String,
+}
+
+/// Подтверждение вторым фактором, например для отключения 2FA.
+#[derive(Debug, Deserialize)]
+pub struct SecondFactorSchema {
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+/// Второй шаг входа: код из приложения или один из кодов восстановления.
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorLoginSchema {
+    pub pre_auth_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminUsersQuery {
+    pub q: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuspendUserSchema {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub user_id: Option<Uuid>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
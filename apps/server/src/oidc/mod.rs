@@ -0,0 +1,750 @@
+use std::{collections::HashMap, error::Error};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header, jwk::JwkSet};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::{OnceCell, RwLock};
+use uuid::Uuid;
+
+use crate::config::Config;
+
+pub type OidcError = Box<dyn Error + Send + Sync>;
+
+/// Где в ответе userinfo лежат нужные поля. Вложенные поля — через точку.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClaimMapping {
+    pub subject: String,
+    pub email: String,
+    pub email_verified: Option<String>,
+    pub name: String,
+    pub picture: Option<String>,
+}
+
+impl Default for ClaimMapping {
+    fn default() -> Self {
+        ClaimMapping {
+            subject: "sub".to_string(),
+            email: "email".to_string(),
+            email_verified: Some("email_verified".to_string()),
+            name: "name".to_string(),
+            picture: Some("picture".to_string()),
+        }
+    }
+}
+
+/// Провайдер входа из конфигурации. Эндпоинты, не заданные явно, берутся
+/// из discovery-документа issuer'а.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderConfig {
+    pub name: String, // ключ в URL: /sessions/oauth/{name}
+    pub display_name: Option<String>,
+    pub issuer: Option<String>,
+    pub authorization_endpoint: Option<String>,
+    pub token_endpoint: Option<String>,
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: Option<String>,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub claims: ClaimMapping,
+    // провайдер отдаёт только подтверждённые адреса, но не сообщает об этом в claim'ах
+    #[serde(default)]
+    pub trust_email: bool,
+}
+
+fn default_scopes() -> Vec<String> {
+    ["openid", "email", "profile"]
+        .iter()
+        .map(|scope| scope.to_string())
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct Endpoints {
+    issuer: Option<String>,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: Option<String>,
+    jwks_uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    id_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+}
+
+fn random_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Одноразовые значения одного входа: state против CSRF, nonce для id_token
+/// и code_verifier для PKCE. Между редиректами хранятся в cookie браузера.
+#[derive(Debug, Clone)]
+pub struct AuthRequest {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+impl AuthRequest {
+    pub fn generate() -> AuthRequest {
+        AuthRequest {
+            state: random_token(),
+            nonce: random_token(),
+            code_verifier: random_token(),
+        }
+    }
+
+    fn code_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.code_verifier.as_bytes()))
+    }
+}
+
+/// Пользователь, как его описал провайдер.
+#[derive(Debug)]
+pub struct OidcUser {
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub name: String,
+    pub picture: Option<String>,
+}
+
+pub struct Provider {
+    pub config: ProviderConfig,
+    client: Client,
+    endpoints: OnceCell<Endpoints>,
+    jwks: RwLock<Option<JwkSet>>,
+}
+
+impl Provider {
+    /// Эндпоинты читаются при первом входе через провайдера и кешируются.
+    async fn endpoints(&self) -> Result<&Endpoints, OidcError> {
+        self.endpoints
+            .get_or_try_init(|| self.resolve_endpoints())
+            .await
+    }
+
+    async fn resolve_endpoints(&self) -> Result<Endpoints, OidcError> {
+        let config = &self.config;
+
+        if let (Some(authorization_endpoint), Some(token_endpoint)) =
+            (&config.authorization_endpoint, &config.token_endpoint)
+        {
+            return Ok(Endpoints {
+                issuer: config.issuer.clone(),
+                authorization_endpoint: authorization_endpoint.clone(),
+                token_endpoint: token_endpoint.clone(),
+                userinfo_endpoint: config.userinfo_endpoint.clone(),
+                jwks_uri: config.jwks_uri.clone(),
+            });
+        }
+
+        let Some(issuer) = &config.issuer else {
+            return Err(
+                format!("Provider {} has neither endpoints nor issuer", config.name).into(),
+            );
+        };
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            issuer.trim_end_matches('/')
+        );
+        let discovered = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Endpoints>()
+            .await?;
+
+        // документ с чужим issuer — признак подмены или ошибки в настройках
+        if discovered
+            .issuer
+            .as_deref()
+            .map(|i| i.trim_end_matches('/'))
+            != Some(issuer.trim_end_matches('/'))
+        {
+            return Err(format!("Discovery document of {} has wrong issuer", config.name).into());
+        }
+
+        // явно заданные эндпоинты важнее найденных
+        Ok(Endpoints {
+            issuer: discovered.issuer,
+            authorization_endpoint: config
+                .authorization_endpoint
+                .clone()
+                .unwrap_or(discovered.authorization_endpoint),
+            token_endpoint: config
+                .token_endpoint
+                .clone()
+                .unwrap_or(discovered.token_endpoint),
+            userinfo_endpoint: config
+                .userinfo_endpoint
+                .clone()
+                .or(discovered.userinfo_endpoint),
+            jwks_uri: config.jwks_uri.clone().or(discovered.jwks_uri),
+        })
+    }
+
+    /// Провайдер OpenID Connect (а не просто OAuth2) выдаёт id_token, и его проверяем.
+    fn is_openid(&self) -> bool {
+        self.config.scopes.iter().any(|scope| scope == "openid")
+    }
+
+    pub async fn authorization_url(&self, request: &AuthRequest) -> Result<String, OidcError> {
+        let endpoints = self.endpoints().await?;
+
+        let mut url = Url::parse(&endpoints.authorization_endpoint)?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", &self.config.redirect_url)
+            .append_pair("scope", &self.config.scopes.join(" "))
+            .append_pair("state", &request.state)
+            .append_pair("code_challenge", &request.code_challenge())
+            .append_pair("code_challenge_method", "S256");
+        if self.is_openid() {
+            url.query_pairs_mut().append_pair("nonce", &request.nonce);
+        }
+
+        Ok(url.to_string())
+    }
+
+    /// Меняет код авторизации на токен и читает профиль пользователя.
+    pub async fn fetch_user(
+        &self,
+        code: &str,
+        request: &AuthRequest,
+    ) -> Result<OidcUser, OidcError> {
+        let endpoints = self.endpoints().await?;
+        let Some(userinfo_endpoint) = &endpoints.userinfo_endpoint else {
+            return Err(format!("Provider {} has no userinfo endpoint", self.config.name).into());
+        };
+
+        let params = [
+            ("grant_type", "authorization_code"),
+            ("redirect_uri", self.config.redirect_url.as_str()),
+            ("client_id", self.config.client_id.as_str()),
+            ("client_secret", self.config.client_secret.as_str()),
+            ("code", code),
+            ("code_verifier", request.code_verifier.as_str()),
+        ];
+        let response = self
+            .client
+            .post(&endpoints.token_endpoint)
+            .form(&params)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err("An error occurred while trying to retrieve access token.".into());
+        }
+        let tokens = response.json::<TokenResponse>().await?;
+
+        let id_subject = if self.is_openid() {
+            let Some(id_token) = &tokens.id_token else {
+                return Err("Provider did not return an id_token".into());
+            };
+            Some(
+                self.verify_id_token(endpoints, id_token, &request.nonce)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
+        let response = self
+            .client
+            .get(userinfo_endpoint)
+            .bearer_auth(&tokens.access_token)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err("An error occurred while trying to retrieve user information.".into());
+        }
+        let info = response.json::<Value>().await?;
+
+        let user = self.map_claims(&info)?;
+        // userinfo должен описывать того же пользователя, что и id_token
+        if id_subject.is_some_and(|subject| subject != user.subject) {
+            return Err("Userinfo subject does not match the id_token".into());
+        }
+
+        Ok(user)
+    }
+
+    /// Проверяет подпись id_token по JWKS провайдера, issuer, audience, срок и nonce.
+    /// Возвращает sub.
+    async fn verify_id_token(
+        &self,
+        endpoints: &Endpoints,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<String, OidcError> {
+        let header = decode_header(id_token)?;
+        // симметричная подпись ключом клиента здесь не нужна и открывает подмену алгоритма
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err("id_token must be signed with an asymmetric key".into());
+        }
+        let Some(kid) = header.kid else {
+            return Err("id_token has no key id".into());
+        };
+        let key = self.decoding_key(endpoints, &kid).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.config.client_id]);
+        if let Some(issuer) = &endpoints.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)?.claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err("id_token nonce does not match".into());
+        }
+
+        Ok(claims.sub)
+    }
+
+    /// Ключ из кеша JWKS; незнакомый kid — повод перечитать набор ключей (ротация).
+    async fn decoding_key(
+        &self,
+        endpoints: &Endpoints,
+        kid: &str,
+    ) -> Result<DecodingKey, OidcError> {
+        if let Some(jwk) = self
+            .jwks
+            .read()
+            .await
+            .as_ref()
+            .and_then(|jwks| jwks.find(kid))
+        {
+            return Ok(DecodingKey::from_jwk(jwk)?);
+        }
+
+        let Some(jwks_uri) = &endpoints.jwks_uri else {
+            return Err(format!("Provider {} has no jwks_uri", self.config.name).into());
+        };
+        let jwks = self
+            .client
+            .get(jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+
+        let key = match jwks.find(kid) {
+            Some(jwk) => DecodingKey::from_jwk(jwk)?,
+            None => return Err("id_token is signed with an unknown key".into()),
+        };
+        *self.jwks.write().await = Some(jwks);
+
+        Ok(key)
+    }
+
+    fn map_claims(&self, info: &Value) -> Result<OidcUser, OidcError> {
+        let claims = &self.config.claims;
+        let required = |path: &str| {
+            claim_string(info, path)
+                .ok_or_else(|| OidcError::from(format!("Userinfo has no {} claim", path)))
+        };
+
+        let email_verified = self.config.trust_email
+            || claims
+                .email_verified
+                .as_deref()
+                .and_then(|path| claim(info, path))
+                .is_some_and(|value| {
+                    value.as_bool() == Some(true) || value.as_str() == Some("true")
+                });
+
+        Ok(OidcUser {
+            subject: required(&claims.subject)?,
+            email: required(&claims.email)?.to_lowercase(),
+            email_verified,
+            name: claim_string(info, &claims.name).unwrap_or_default(),
+            picture: claims
+                .picture
+                .as_deref()
+                .and_then(|path| claim_string(info, path)),
+        })
+    }
+}
+
+fn claim<'a>(info: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(info, |value, key| value.get(key))
+}
+
+/// Строковое значение claim'а; числовые id (как у VK) приводятся к строке.
+fn claim_string(info: &Value, path: &str) -> Option<String> {
+    match claim(info, path)? {
+        Value::String(value) if !value.is_empty() => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Настроенные провайдеры входа по имени.
+pub struct Registry {
+    providers: HashMap<String, Provider>,
+}
+
+impl Registry {
+    pub fn init(config: &Config) -> Registry {
+        let client = Client::new();
+        let providers = config
+            .oidc_providers
+            .iter()
+            .map(|provider| {
+                (
+                    provider.name.clone(),
+                    Provider {
+                        config: provider.clone(),
+                        client: client.clone(),
+                        endpoints: OnceCell::new(),
+                        jwks: RwLock::new(None),
+                    },
+                )
+            })
+            .collect();
+
+        Registry { providers }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Provider> {
+        self.providers.get(name)
+    }
+
+    pub fn providers(&self) -> Vec<&ProviderConfig> {
+        let mut providers: Vec<&ProviderConfig> =
+            self.providers.values().map(|p| &p.config).collect();
+        providers.sort_by(|a, b| a.name.cmp(&b.name));
+        providers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+    use chrono::Utc;
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde_json::json;
+
+    use super::*;
+
+    const CLIENT_ID: &str = "family-tree";
+    const ACCESS_TOKEN: &str = "mock-access-token";
+    const KEY1: (&str, &str, &str) = (
+        "key1",
+        include_str!("testdata/key1.pem"),
+        include_str!("testdata/key1.jwk.json"),
+    );
+    const KEY2: (&str, &str, &str) = (
+        "key2",
+        include_str!("testdata/key2.pem"),
+        include_str!("testdata/key2.jwk.json"),
+    );
+
+    /// Локальный провайдер: что он отдаёт и что к нему пришло.
+    struct Mock {
+        base: String,
+        // issuer в discovery, если он должен отличаться от настоящего
+        discovery_issuer: Option<String>,
+        signing_key: (&'static str, &'static str, &'static str),
+        published_keys: Vec<(&'static str, &'static str, &'static str)>,
+        nonce: String,
+        subject: String,
+        userinfo: Value,
+        token_requests: Vec<HashMap<String, String>>,
+        jwks_requests: usize,
+    }
+
+    type SharedMock = Arc<Mutex<Mock>>;
+
+    async fn discovery(mock: web::Data<SharedMock>) -> HttpResponse {
+        let mock = mock.lock().unwrap();
+        HttpResponse::Ok().json(json!({
+            "issuer": mock.discovery_issuer.clone().unwrap_or(mock.base.clone()),
+            "authorization_endpoint": format!("{}/authorize", mock.base),
+            "token_endpoint": format!("{}/token", mock.base),
+            "userinfo_endpoint": format!("{}/userinfo", mock.base),
+            "jwks_uri": format!("{}/jwks", mock.base)
+        }))
+    }
+
+    async fn token(
+        mock: web::Data<SharedMock>,
+        form: web::Form<HashMap<String, String>>,
+    ) -> HttpResponse {
+        let mut mock = mock.lock().unwrap();
+        mock.token_requests.push(form.into_inner());
+
+        let (kid, pem, _) = mock.signing_key;
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(kid.to_string());
+        let now = Utc::now().timestamp();
+        let claims = json!({
+            "iss": mock.base,
+            "aud": CLIENT_ID,
+            "sub": mock.subject,
+            "nonce": mock.nonce,
+            "iat": now,
+            "exp": now + 300
+        });
+        let id_token = encode(
+            &header,
+            &claims,
+            &EncodingKey::from_rsa_pem(pem.as_bytes()).unwrap(),
+        )
+        .unwrap();
+
+        HttpResponse::Ok().json(json!({
+            "access_token": ACCESS_TOKEN,
+            "token_type": "Bearer",
+            "id_token": id_token
+        }))
+    }
+
+    async fn userinfo(mock: web::Data<SharedMock>, req: HttpRequest) -> HttpResponse {
+        let authorized = req
+            .headers()
+            .get("authorization")
+            .and_then(|h| h.to_str().ok())
+            == Some(&format!("Bearer {}", ACCESS_TOKEN));
+        if !authorized {
+            return HttpResponse::Unauthorized().finish();
+        }
+        HttpResponse::Ok().json(mock.lock().unwrap().userinfo.clone())
+    }
+
+    async fn jwks(mock: web::Data<SharedMock>) -> HttpResponse {
+        let mut mock = mock.lock().unwrap();
+        mock.jwks_requests += 1;
+        let keys: Vec<Value> = mock
+            .published_keys
+            .iter()
+            .map(|(_, _, jwk)| serde_json::from_str(jwk).unwrap())
+            .collect();
+        HttpResponse::Ok().json(json!({"keys": keys}))
+    }
+
+    async fn start_mock() -> SharedMock {
+        let mock = Arc::new(Mutex::new(Mock {
+            base: String::new(),
+            discovery_issuer: None,
+            signing_key: KEY1,
+            published_keys: vec![KEY1],
+            nonce: String::new(),
+            subject: "user-1".to_string(),
+            userinfo: json!({
+                "sub": "user-1",
+                "email": "Ivan@Example.com",
+                "email_verified": true,
+                "name": "Иван Петров"
+            }),
+            token_requests: Vec::new(),
+            jwks_requests: 0,
+        }));
+
+        let data = web::Data::new(mock.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(discovery),
+                )
+                .route("/token", web::post().to(token))
+                .route("/userinfo", web::get().to(userinfo))
+                .route("/jwks", web::get().to(jwks))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        mock.lock().unwrap().base = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+
+        mock
+    }
+
+    fn provider(mock: &SharedMock, scopes: &[&str], claims: ClaimMapping) -> Provider {
+        Provider {
+            config: ProviderConfig {
+                name: "mock".to_string(),
+                display_name: None,
+                issuer: Some(mock.lock().unwrap().base.clone()),
+                authorization_endpoint: None,
+                token_endpoint: None,
+                userinfo_endpoint: None,
+                jwks_uri: None,
+                client_id: CLIENT_ID.to_string(),
+                client_secret: "secret".to_string(),
+                redirect_url: "http://localhost/callback".to_string(),
+                scopes: scopes.iter().map(|s| s.to_string()).collect(),
+                claims,
+                trust_email: false,
+            },
+            client: Client::new(),
+            endpoints: OnceCell::new(),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    fn openid_provider(mock: &SharedMock) -> Provider {
+        provider(mock, &["openid", "email"], ClaimMapping::default())
+    }
+
+    /// Вход целиком: ссылка на провайдера, затем обмен кода. Nonce мок берёт из запроса,
+    /// как настоящий провайдер из параметра авторизации.
+    async fn login(mock: &SharedMock, provider: &Provider) -> Result<OidcUser, OidcError> {
+        let request = AuthRequest::generate();
+        provider.authorization_url(&request).await?;
+        mock.lock().unwrap().nonce = request.nonce.clone();
+        provider.fetch_user("code", &request).await
+    }
+
+    #[actix_web::test]
+    async fn discovery_with_foreign_issuer_is_rejected() {
+        let mock = start_mock().await;
+        mock.lock().unwrap().discovery_issuer = Some("https://evil.example".to_string());
+        let provider = openid_provider(&mock);
+
+        let error = provider
+            .authorization_url(&AuthRequest::generate())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("wrong issuer"), "{}", error);
+    }
+
+    #[actix_web::test]
+    async fn code_exchange_uses_pkce_and_verifies_id_token() {
+        let mock = start_mock().await;
+        let provider = openid_provider(&mock);
+        let request = AuthRequest::generate();
+
+        let url = Url::parse(&provider.authorization_url(&request).await.unwrap()).unwrap();
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert!(
+            url.as_str()
+                .starts_with(&format!("{}/authorize", mock.lock().unwrap().base))
+        );
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["state"], request.state);
+        assert_eq!(params["nonce"], request.nonce);
+
+        mock.lock().unwrap().nonce = request.nonce.clone();
+        let user = provider.fetch_user("code", &request).await.unwrap();
+        assert_eq!(user.subject, "user-1");
+        assert_eq!(user.email, "ivan@example.com");
+        assert!(user.email_verified);
+        assert_eq!(user.name, "Иван Петров");
+
+        // провайдер получил verifier, из которого получается challenge из ссылки
+        let mock = mock.lock().unwrap();
+        let verifier = &mock.token_requests[0]["code_verifier"];
+        assert_eq!(verifier, &request.code_verifier);
+        assert_eq!(
+            URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())),
+            params["code_challenge"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn id_token_with_foreign_nonce_is_rejected() {
+        let mock = start_mock().await;
+        let provider = openid_provider(&mock);
+        let request = AuthRequest::generate();
+        provider.authorization_url(&request).await.unwrap();
+
+        mock.lock().unwrap().nonce = "someone-else".to_string();
+        let error = provider.fetch_user("code", &request).await.unwrap_err();
+        assert!(error.to_string().contains("nonce"), "{}", error);
+    }
+
+    #[actix_web::test]
+    async fn userinfo_of_another_subject_is_rejected() {
+        let mock = start_mock().await;
+        mock.lock().unwrap().subject = "user-2".to_string();
+        let provider = openid_provider(&mock);
+
+        let error = login(&mock, &provider).await.unwrap_err();
+        assert!(error.to_string().contains("subject"), "{}", error);
+    }
+
+    #[actix_web::test]
+    async fn jwks_is_cached_and_refetched_on_key_rotation() {
+        let mock = start_mock().await;
+        let provider = openid_provider(&mock);
+
+        login(&mock, &provider).await.unwrap();
+        login(&mock, &provider).await.unwrap();
+        assert_eq!(mock.lock().unwrap().jwks_requests, 1);
+
+        // провайдер сменил ключ: незнакомый kid заставляет перечитать набор
+        {
+            let mut mock = mock.lock().unwrap();
+            mock.signing_key = KEY2;
+            mock.published_keys = vec![KEY2];
+        }
+        login(&mock, &provider).await.unwrap();
+        assert_eq!(mock.lock().unwrap().jwks_requests, 2);
+
+        // ключ, которого нет в опубликованном наборе, не принимается
+        mock.lock().unwrap().signing_key = KEY1;
+        let error = login(&mock, &provider).await.unwrap_err();
+        assert!(error.to_string().contains("unknown key"), "{}", error);
+    }
+
+    #[actix_web::test]
+    async fn nested_and_numeric_claims_are_mapped() {
+        let mock = start_mock().await;
+        // как у VK: OAuth2 без id_token, профиль вложен, id числовой
+        mock.lock().unwrap().userinfo = json!({
+            "user": {
+                "user_id": 123456789,
+                "email": "Maria@VK.com",
+                "first_name": "Мария",
+                "avatar": "https://vk.example/a.jpg"
+            }
+        });
+        let provider = provider(
+            &mock,
+            &["email"],
+            ClaimMapping {
+                subject: "user.user_id".to_string(),
+                email: "user.email".to_string(),
+                email_verified: None,
+                name: "user.first_name".to_string(),
+                picture: Some("user.avatar".to_string()),
+            },
+        );
+
+        let user = login(&mock, &provider).await.unwrap();
+        assert_eq!(user.subject, "123456789");
+        assert_eq!(user.email, "maria@vk.com");
+        assert!(!user.email_verified);
+        assert_eq!(user.name, "Мария");
+        assert_eq!(user.picture.as_deref(), Some("https://vk.example/a.jpg"));
+        // без openid id_token не проверяется и JWKS не нужен
+        assert_eq!(mock.lock().unwrap().jwks_requests, 0);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use super::{Policy, RateLimitError, RateLimiter};
+
+struct Entry {
+    window_start: Instant,
+    attempts: u32,
+    locked_until: Option<Instant>,
+    // когда запись можно выбросить: окно кончилось и блокировка снята
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+pub struct MemoryRateLimiter {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+#[async_trait]
+impl RateLimiter for MemoryRateLimiter {
+    async fn hit(&self, key: &str, policy: &Policy) -> Result<Option<Duration>, RateLimitError> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.entry(key.to_string()).or_insert(Entry {
+            window_start: now,
+            attempts: 0,
+            locked_until: None,
+            expires_at: now,
+        });
+
+        if let Some(locked_until) = entry.locked_until.filter(|until| *until > now) {
+            return Ok(Some(locked_until - now));
+        }
+
+        // после блокировки или по концу окна счёт начинается заново
+        if entry.locked_until.is_some() || entry.window_start + policy.window <= now {
+            entry.window_start = now;
+            entry.attempts = 0;
+            entry.locked_until = None;
+        }
+        entry.attempts += 1;
+        entry.expires_at = entry.window_start + policy.window;
+
+        if entry.attempts > policy.max_attempts {
+            let locked_until = now + policy.lockout;
+            entry.locked_until = Some(locked_until);
+            entry.expires_at = entry.expires_at.max(locked_until);
+            return Ok(Some(policy.lockout));
+        }
+
+        Ok(None)
+    }
+
+    async fn locked_for(&self, key: &str) -> Result<Option<Duration>, RateLimitError> {
+        let now = Instant::now();
+        let entries = self.entries.lock().unwrap();
+
+        Ok(entries
+            .get(key)
+            .and_then(|entry| entry.locked_until)
+            .filter(|until| *until > now)
+            .map(|until| until - now))
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), RateLimitError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<(), RateLimitError> {
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, entry| entry.expires_at > now);
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+mod memory;
+mod postgres;
+
+use std::{error::Error, time::Duration};
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+pub use memory::MemoryRateLimiter;
+pub use postgres::PostgresRateLimiter;
+
+pub type RateLimitError = Box<dyn Error + Send + Sync>;
+
+/// Сколько попыток прощается за окно и на сколько блокировать ключ сверх них.
+#[derive(Debug, Clone, Copy)]
+pub struct Policy {
+    pub max_attempts: u32,
+    pub window: Duration,
+    pub lockout: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    // счётчики в памяти процесса: годится, пока сервер один
+    Memory,
+    // общие счётчики для нескольких экземпляров
+    Postgres,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub backend: Backend,
+    // X-Forwarded-For можно подделать: доверяем ему только за своим прокси
+    pub trust_forwarded: bool,
+    pub login_ip: Policy,
+    pub login_account: Policy, // считаются только неудачные входы
+    pub register_ip: Policy,
+    // неверные пароли публичных ссылок: по ссылке и по адресу
+    pub share_password_link: Policy,
+    pub share_password_ip: Policy,
+}
+
+/// Счётчики попыток по ключу ("login:ip:1.2.3.4", "login:account:a@b.c").
+#[async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// Засчитывает попытку. Some — попытка сверх лимита или ключ уже заблокирован:
+    /// через сколько можно повторить.
+    async fn hit(&self, key: &str, policy: &Policy) -> Result<Option<Duration>, RateLimitError>;
+
+    /// Оставшаяся блокировка ключа, без учёта новой попытки.
+    async fn locked_for(&self, key: &str) -> Result<Option<Duration>, RateLimitError>;
+
+    /// Сбрасывает счётчик, например после успешного входа.
+    async fn reset(&self, key: &str) -> Result<(), RateLimitError>;
+
+    /// Удаляет счётчики, у которых истекли и окно, и блокировка.
+    async fn purge_expired(&self) -> Result<(), RateLimitError>;
+}
+
+pub fn init(config: &RateLimitConfig, pool: PgPool) -> Box<dyn RateLimiter> {
+    match config.backend {
+        Backend::Memory => Box::new(MemoryRateLimiter::default()),
+        Backend::Postgres => Box::new(PostgresRateLimiter::new(pool)),
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use super::{Policy, RateLimitError, RateLimiter};
+use crate::repo::{
+    delete_expired_rate_limits, get_rate_limit_lock, hit_rate_limit, lock_rate_limit,
+    reset_rate_limit,
+};
+
+/// Счётчики в таблице rate_limits, общие для всех экземпляров сервера.
+pub struct PostgresRateLimiter {
+    pool: PgPool,
+}
+
+impl PostgresRateLimiter {
+    pub fn new(pool: PgPool) -> PostgresRateLimiter {
+        PostgresRateLimiter { pool }
+    }
+}
+
+#[async_trait]
+impl RateLimiter for PostgresRateLimiter {
+    async fn hit(&self, key: &str, policy: &Policy) -> Result<Option<Duration>, RateLimitError> {
+        let (attempts, locked_for) =
+            hit_rate_limit(&self.pool, key, policy.window.as_secs_f64()).await?;
+
+        if let Some(locked_for) = locked_for {
+            return Ok(Some(Duration::from_secs_f64(locked_for)));
+        }
+        if attempts > policy.max_attempts as i32 {
+            lock_rate_limit(&self.pool, key, policy.lockout.as_secs_f64()).await?;
+            return Ok(Some(policy.lockout));
+        }
+
+        Ok(None)
+    }
+
+    async fn locked_for(&self, key: &str) -> Result<Option<Duration>, RateLimitError> {
+        let locked_for = get_rate_limit_lock(&self.pool, key).await?;
+        Ok(locked_for.map(Duration::from_secs_f64))
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), RateLimitError> {
+        reset_rate_limit(&self.pool, key).await?;
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<(), RateLimitError> {
+        let purged = delete_expired_rate_limits(&self.pool).await?;
+        if purged > 0 {
+            log::debug!("Purged {} expired rate limit counters", purged);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::{
+    Notify,
+    mpsc::{self, Receiver, Sender, error::TrySendError},
+};
+use uuid::Uuid;
+
+/// Кто сейчас смотрит дерево.
+#[derive(Debug, Clone, Serialize)]
+pub struct Viewer {
+    pub user_id: Uuid,
+    pub name: String,
+}
+
+/// Очередь событий одного соединения. Она ограничена: клиента, который не успевает
+/// читать, отключаем, а не копим для него события в памяти.
+#[derive(Clone)]
+pub struct Outbox {
+    sender: Sender<String>,
+    overflow: Arc<Notify>,
+}
+
+impl Outbox {
+    /// Очередь, её приёмник и сигнал переполнения, по которому соединение закрывается.
+    pub fn new(capacity: usize) -> (Outbox, Receiver<String>, Arc<Notify>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        let overflow = Arc::new(Notify::new());
+        let outbox = Outbox {
+            sender,
+            overflow: overflow.clone(),
+        };
+
+        (outbox, receiver, overflow)
+    }
+
+    fn push(&self, message: String) {
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(message) {
+            self.overflow.notify_one();
+        }
+    }
+}
+
+struct Subscriber {
+    viewer: Viewer,
+    outbox: Outbox,
+}
+
+/// Подписки WebSocket-соединений на деревья. Соединение получает события только тех
+/// деревьев, на которые подписалось; состояние живёт в памяти одного процесса.
+#[derive(Default)]
+pub struct Hub {
+    // tree_id -> connection_id -> подписчик
+    trees: Mutex<HashMap<Uuid, HashMap<Uuid, Subscriber>>>,
+}
+
+impl Hub {
+    pub fn subscribe(&self, tree_id: Uuid, connection_id: Uuid, viewer: Viewer, outbox: Outbox) {
+        let mut trees = self.trees.lock().unwrap();
+        trees
+            .entry(tree_id)
+            .or_default()
+            .insert(connection_id, Subscriber { viewer, outbox });
+    }
+
+    /// true — соединение действительно было подписано на дерево.
+    pub fn unsubscribe(&self, tree_id: Uuid, connection_id: Uuid) -> bool {
+        let mut trees = self.trees.lock().unwrap();
+        let Some(subscribers) = trees.get_mut(&tree_id) else {
+            return false;
+        };
+
+        let removed = subscribers.remove(&connection_id).is_some();
+        if subscribers.is_empty() {
+            trees.remove(&tree_id);
+        }
+
+        removed
+    }
+
+    /// Отписывает соединение от всех деревьев; возвращает деревья, где оно было подписано.
+    pub fn disconnect(&self, connection_id: Uuid) -> Vec<Uuid> {
+        let mut trees = self.trees.lock().unwrap();
+        let mut left = Vec::new();
+
+        trees.retain(|tree_id, subscribers| {
+            if subscribers.remove(&connection_id).is_some() {
+                left.push(*tree_id);
+            }
+            !subscribers.is_empty()
+        });
+
+        left
+    }
+
+    /// Деревья, на которые подписано соединение.
+    pub fn subscriptions(&self, connection_id: Uuid) -> Vec<Uuid> {
+        let trees = self.trees.lock().unwrap();
+        trees
+            .iter()
+            .filter(|(_, subscribers)| subscribers.contains_key(&connection_id))
+            .map(|(tree_id, _)| *tree_id)
+            .collect()
+    }
+
+    /// Зрители дерева без повторов: у одного пользователя может быть несколько вкладок.
+    pub fn viewers(&self, tree_id: Uuid) -> Vec<Viewer> {
+        let trees = self.trees.lock().unwrap();
+        let mut viewers: Vec<Viewer> = Vec::new();
+
+        for subscriber in trees.get(&tree_id).into_iter().flat_map(|s| s.values()) {
+            if !viewers
+                .iter()
+                .any(|v| v.user_id == subscriber.viewer.user_id)
+            {
+                viewers.push(subscriber.viewer.clone());
+            }
+        }
+
+        viewers
+    }
+
+    /// Рассылает событие всем подписчикам дерева. Закрытые и переполненные соединения
+    /// уберёт их собственный обработчик, здесь ошибки отправки не важны.
+    pub fn publish<T: Serialize>(&self, tree_id: Uuid, event: &T) {
+        let Ok(message) = serde_json::to_string(event) else {
+            return;
+        };
+
+        let trees = self.trees.lock().unwrap();
+        for subscriber in trees.get(&tree_id).into_iter().flat_map(|s| s.values()) {
+            subscriber.outbox.push(message.clone());
+        }
+    }
+}
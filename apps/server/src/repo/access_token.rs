@@ -0,0 +1,113 @@
+use chrono::NaiveDateTime;
+use sqlx::{Error, PgPool, types::Json};
+use uuid::Uuid;
+
+use crate::model::{AccessToken, TokenScope};
+
+pub async fn create_access_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    token: &str,
+    scopes: &[TokenScope],
+    expires_at: NaiveDateTime,
+) -> Result<AccessToken, Error> {
+    let token = sqlx::query_as!(
+        AccessToken,
+        r#"
+        INSERT INTO personal_access_tokens (user_id, name, token_hash, scopes, expires_at)
+        VALUES ($1, $2, encode(sha256(convert_to($3, 'UTF8')), 'hex'), $4, $5)
+        RETURNING id, name, scopes AS "scopes: Json<Vec<TokenScope>>",
+                  created_at, expires_at, last_used_at
+        "#,
+        user_id,
+        name,
+        token,
+        Json(scopes) as _,
+        expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(token)
+}
+
+/// Владелец и scope действующего токена. last_used_at обновляется не чаще раза в минуту.
+pub async fn authenticate_access_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<(Uuid, Vec<TokenScope>)>, Error> {
+    let row = sqlx::query!(
+        r#"
+        WITH active AS (
+            SELECT t.id, t.user_id, t.scopes, t.last_used_at
+            FROM personal_access_tokens t
+            JOIN users u ON u.id = t.user_id AND u.suspended_at IS NULL
+            WHERE t.token_hash = encode(sha256(convert_to($1, 'UTF8')), 'hex')
+              AND t.revoked_at IS NULL AND t.expires_at > NOW()
+        ), touched AS (
+            UPDATE personal_access_tokens SET last_used_at = NOW()
+            WHERE id IN (
+                SELECT id FROM active
+                WHERE last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute'
+            )
+        )
+        SELECT user_id, scopes AS "scopes: Json<Vec<TokenScope>>" FROM active
+        "#,
+        token
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| (row.user_id, row.scopes.0)))
+}
+
+pub async fn get_access_tokens(pool: &PgPool, user_id: Uuid) -> Result<Vec<AccessToken>, Error> {
+    let tokens = sqlx::query_as!(
+        AccessToken,
+        r#"
+        SELECT id, name, scopes AS "scopes: Json<Vec<TokenScope>>",
+               created_at, expires_at, last_used_at
+        FROM personal_access_tokens
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tokens)
+}
+
+pub async fn revoke_access_token(pool: &PgPool, id: Uuid, user_id: Uuid) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE personal_access_tokens
+        SET revoked_at = NOW()
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Отзывает все токены пользователя: после смены или сброса пароля старые токены не действуют.
+pub async fn revoke_all_access_tokens(pool: &PgPool, user_id: Uuid) -> Result<u64, Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE personal_access_tokens
+        SET revoked_at = NOW()
+        WHERE user_id = $1 AND revoked_at IS NULL
+        "#,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
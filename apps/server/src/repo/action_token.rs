@@ -0,0 +1,119 @@
+use chrono::NaiveDateTime;
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::model::ActionPurpose;
+
+pub async fn create_action_token(
+    pool: &PgPool,
+    id: Uuid,
+    user_id: Uuid,
+    purpose: ActionPurpose,
+    expires_at: NaiveDateTime,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO action_tokens (id, user_id, purpose, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        id,
+        user_id,
+        purpose.as_str(),
+        expires_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Гасит токен. Возвращает его владельца, если токен был действителен и ещё не использован.
+pub async fn consume_action_token(
+    pool: &PgPool,
+    id: Uuid,
+    purpose: ActionPurpose,
+) -> Result<Option<Uuid>, Error> {
+    let user_id = sqlx::query_scalar!(
+        r#"
+        UPDATE action_tokens
+        SET used_at = NOW()
+        WHERE id = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING user_id
+        "#,
+        id,
+        purpose.as_str()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user_id)
+}
+
+/// Токен ещё можно использовать: не погашен и не просрочен.
+pub async fn is_action_token_active(
+    pool: &PgPool,
+    id: Uuid,
+    purpose: ActionPurpose,
+) -> Result<bool, Error> {
+    let active = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM action_tokens
+            WHERE id = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+        ) AS "active!"
+        "#,
+        id,
+        purpose.as_str()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(active)
+}
+
+/// Неудачная попытка по токену (например, неверный код 2FA). После `max_attempts`
+/// неудач токен гасится, и перебирать коды дальше с ним нельзя.
+pub async fn record_failed_action_attempt(
+    pool: &PgPool,
+    id: Uuid,
+    max_attempts: i32,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        UPDATE action_tokens
+        SET attempts = attempts + 1,
+            used_at = CASE WHEN attempts + 1 >= $2 THEN NOW() ELSE used_at END
+        WHERE id = $1
+        "#,
+        id,
+        max_attempts
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Сколько токенов выдано пользователю после `since` и когда — первый и последний.
+/// Нужно для ограничения повторных писем.
+pub async fn get_recent_action_tokens(
+    pool: &PgPool,
+    user_id: Uuid,
+    purpose: ActionPurpose,
+    since: NaiveDateTime,
+) -> Result<(i64, Option<NaiveDateTime>, Option<NaiveDateTime>), Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", MIN(created_at) AS first, MAX(created_at) AS last
+        FROM action_tokens
+        WHERE user_id = $1 AND purpose = $2 AND created_at > $3
+        "#,
+        user_id,
+        purpose.as_str(),
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((row.count, row.first, row.last))
+}
@@ -0,0 +1,189 @@
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::model::{AdminAction, AdminUser, AuditEntry, StorageUsage};
+
+/// Пользователи по имени или адресу; без запроса — все, новые первыми.
+pub async fn search_users(
+    pool: &PgPool,
+    q: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<AdminUser>, Error> {
+    let users = sqlx::query_as!(
+        AdminUser,
+        r#"
+        SELECT id, name, email, role, provider, verified, suspended_at,
+               password_reset_required, created_at
+        FROM users
+        WHERE $1::TEXT IS NULL
+           OR name ILIKE '%' || $1 || '%'
+           OR email ILIKE '%' || $1 || '%'
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+        q,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(users)
+}
+
+/// Блокирует пользователя и отзывает все его сессии.
+pub async fn suspend_user(pool: &PgPool, user_id: Uuid) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE users SET suspended_at = NOW(), updated_at = NOW() WHERE id = $1",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn unsuspend_user(pool: &PgPool, user_id: Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE users SET suspended_at = NULL, updated_at = NOW() WHERE id = $1",
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Закрывает вход по паролю до сброса и отзывает все сессии и токены доступа.
+pub async fn require_password_reset(pool: &PgPool, user_id: Uuid) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE users SET password_reset_required = TRUE, updated_at = NOW() WHERE id = $1",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE personal_access_tokens SET revoked_at = NOW()
+        WHERE user_id = $1 AND revoked_at IS NULL
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Удаляет пользователя; его деревья и всё привязанное к нему уходит каскадом.
+pub async fn delete_user(pool: &PgPool, user_id: Uuid) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Журнал правок считается по деревьям владельца и его персонам без дерева.
+pub async fn get_storage_usage(pool: &PgPool, user_id: Uuid) -> Result<StorageUsage, Error> {
+    let usage = sqlx::query_as!(
+        StorageUsage,
+        r#"
+        WITH owned_changes AS (
+            SELECT c.before, c.after
+            FROM changes c
+            LEFT JOIN trees t ON t.id = c.tree_id
+            WHERE t.owner_id = $1 OR (c.tree_id IS NULL AND c.user_id = $1)
+        )
+        SELECT
+            (SELECT COUNT(*) FROM trees WHERE owner_id = $1) AS "trees!",
+            (SELECT COUNT(*) FROM owned_changes) AS "changes!",
+            (SELECT COALESCE(SUM(
+                COALESCE(pg_column_size(before), 0) + COALESCE(pg_column_size(after), 0)
+            ), 0)::BIGINT FROM owned_changes) AS "history_bytes!",
+            (SELECT COUNT(*) FROM comments WHERE author_id = $1) AS "comments!"
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(usage)
+}
+
+pub async fn record_admin_action(
+    pool: &PgPool,
+    admin_id: Uuid,
+    action: AdminAction,
+    target_user_id: Option<Uuid>,
+    details: serde_json::Value,
+    ip: Option<&str>,
+) -> Result<i64, Error> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO admin_audit_log (admin_id, action, target_user_id, details, ip)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+        admin_id,
+        action.as_str(),
+        target_user_id,
+        details,
+        ip
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn get_audit_log(
+    pool: &PgPool,
+    target_user_id: Option<Uuid>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<AuditEntry>, Error> {
+    let entries = sqlx::query_as!(
+        AuditEntry,
+        r#"
+        SELECT a.id, a.admin_id, u.name AS "admin_name?", a.action, a.target_user_id,
+               a.details, a.ip, a.created_at
+        FROM admin_audit_log a
+        LEFT JOIN users u ON u.id = a.admin_id
+        WHERE $1::UUID IS NULL OR a.target_user_id = $1
+        ORDER BY a.id DESC
+        LIMIT $2 OFFSET $3
+        "#,
+        target_user_id,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
@@ -0,0 +1,115 @@
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::model::{Comment, CreateCommentSchema};
+
+pub async fn create_comment(
+    pool: &PgPool,
+    tree_id: Uuid,
+    author_id: Uuid,
+    comment: &CreateCommentSchema,
+) -> Result<Uuid, Error> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO comments (tree_id, subject_type, subject_id, parent_id, author_id, body, mentions)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+        tree_id,
+        comment.subject_type.as_str(),
+        comment.subject_id,
+        comment.parent_id,
+        author_id,
+        comment.body,
+        &comment.mentions
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn get_comment(pool: &PgPool, id: Uuid) -> Result<Option<Comment>, Error> {
+    let comment = sqlx::query_as!(
+        Comment,
+        r#"
+        SELECT c.id, c.tree_id, c.subject_type, c.subject_id, c.parent_id, c.author_id,
+               u.name AS "author_name?",
+               CASE WHEN c.deleted_at IS NULL THEN c.body END AS "body?",
+               c.mentions, c.created_at, c.edited_at, c.deleted_at
+        FROM comments c
+        LEFT JOIN users u ON u.id = c.author_id
+        WHERE c.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(comment)
+}
+
+/// Все комментарии к узлу плоским списком по времени; ветки собираются по parent_id.
+pub async fn get_comments_for_subject(
+    pool: &PgPool,
+    subject_type: &str,
+    subject_id: &str,
+) -> Result<Vec<Comment>, Error> {
+    let comments = sqlx::query_as!(
+        Comment,
+        r#"
+        SELECT c.id, c.tree_id, c.subject_type, c.subject_id, c.parent_id, c.author_id,
+               u.name AS "author_name?",
+               CASE WHEN c.deleted_at IS NULL THEN c.body END AS "body?",
+               c.mentions, c.created_at, c.edited_at, c.deleted_at
+        FROM comments c
+        LEFT JOIN users u ON u.id = c.author_id
+        WHERE c.subject_type = $1 AND c.subject_id = $2
+        ORDER BY c.created_at
+        "#,
+        subject_type,
+        subject_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(comments)
+}
+
+pub async fn update_comment(
+    pool: &PgPool,
+    id: Uuid,
+    body: &str,
+    mentions: &[Uuid],
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        UPDATE comments
+        SET body = $2, mentions = $3, edited_at = NOW()
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        id,
+        body,
+        mentions
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_comment(pool: &PgPool, id: Uuid, deleted_by: Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        UPDATE comments
+        SET deleted_at = NOW(), deleted_by = $2
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        id,
+        deleted_by
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use sqlx::{Error, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::model::{Change, NewChange};
+
+pub async fn record_change(pool: &PgPool, change: &NewChange) -> Result<i64, Error> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO changes
+            (tree_id, user_id, entity_type, entity_id, person_ids, operation, before, after,
+             revert_of)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id
+        "#,
+        change.tree_id,
+        change.user_id,
+        change.entity.as_str(),
+        change.entity_id,
+        &change.person_ids,
+        change.operation.as_str(),
+        change.before,
+        change.after,
+        change.revert_of
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id)
+}
+
+/// Открывает транзакцию отката и сразу пишет в неё строку отмены. Пока транзакция
+/// открыта, строка держит уникальный индекс по revert_of: параллельный откат того же
+/// изменения дождётся её и получит None. Граф меняется до commit, при ошибке
+/// транзакция просто откатывается.
+pub async fn begin_revert(
+    pool: &PgPool,
+    change: &NewChange,
+) -> Result<Option<(Transaction<'static, Postgres>, i64)>, Error> {
+    let mut tx = pool.begin().await?;
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO changes
+            (tree_id, user_id, entity_type, entity_id, person_ids, operation, before, after,
+             revert_of)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (revert_of) DO NOTHING
+        RETURNING id
+        "#,
+        change.tree_id,
+        change.user_id,
+        change.entity.as_str(),
+        change.entity_id,
+        &change.person_ids,
+        change.operation.as_str(),
+        change.before,
+        change.after,
+        change.revert_of
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    Ok(id.map(|id| (tx, id)))
+}
+
+/// История персоны: её собственные правки и правки связей, где она участвует.
+/// Новые записи первыми.
+pub async fn get_person_history(
+    pool: &PgPool,
+    person_id: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Change>, Error> {
+    let changes = sqlx::query_as!(
+        Change,
+        r#"
+        SELECT c.id, c.tree_id, c.user_id, u.name AS "user_name?", c.entity_type, c.entity_id,
+               c.person_ids, c.operation, c.before, c.after, c.revert_of, c.created_at
+        FROM changes c
+        LEFT JOIN users u ON u.id = c.user_id
+        WHERE c.person_ids @> ARRAY[$1]
+        ORDER BY c.id DESC
+        LIMIT $2 OFFSET $3
+        "#,
+        person_id,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(changes)
+}
+
+pub async fn get_tree_history(
+    pool: &PgPool,
+    tree_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Change>, Error> {
+    let changes = sqlx::query_as!(
+        Change,
+        r#"
+        SELECT c.id, c.tree_id, c.user_id, u.name AS "user_name?", c.entity_type, c.entity_id,
+               c.person_ids, c.operation, c.before, c.after, c.revert_of, c.created_at
+        FROM changes c
+        LEFT JOIN users u ON u.id = c.user_id
+        WHERE c.tree_id = $1
+        ORDER BY c.id DESC
+        LIMIT $2 OFFSET $3
+        "#,
+        tree_id,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(changes)
+}
+
+pub async fn get_change(pool: &PgPool, id: i64) -> Result<Option<Change>, Error> {
+    let change = sqlx::query_as!(
+        Change,
+        r#"
+        SELECT c.id, c.tree_id, c.user_id, u.name AS "user_name?", c.entity_type, c.entity_id,
+               c.person_ids, c.operation, c.before, c.after, c.revert_of, c.created_at
+        FROM changes c
+        LEFT JOIN users u ON u.id = c.user_id
+        WHERE c.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(change)
+}
+
+pub async fn is_change_reverted(pool: &PgPool, id: i64) -> Result<bool, Error> {
+    let reverted = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM changes WHERE revert_of = $1) AS "exists!""#,
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(reverted)
+}
+
+/// Действующие изменения той же сущности, сделанные после `change`.
+/// Отменённые правки и их откаты друг друга гасят и не считаются.
+/// Для созданной персоны учитываются все последующие правки, где она участвует:
+/// без неё они теряют смысл.
+pub async fn get_later_changes(
+    pool: &PgPool,
+    change: &Change,
+    whole_person: bool,
+) -> Result<Vec<Change>, Error> {
+    let changes = sqlx::query_as!(
+        Change,
+        r#"
+        SELECT c.id, c.tree_id, c.user_id, u.name AS "user_name?", c.entity_type, c.entity_id,
+               c.person_ids, c.operation, c.before, c.after, c.revert_of, c.created_at
+        FROM changes c
+        LEFT JOIN users u ON u.id = c.user_id
+        WHERE c.id > $1
+          AND CASE WHEN $4
+                   THEN c.person_ids @> ARRAY[$3]
+                   ELSE c.entity_type = $2 AND c.entity_id = $3 END
+          AND NOT EXISTS (SELECT 1 FROM changes r WHERE r.revert_of = c.id)
+          AND (c.revert_of IS NULL OR c.revert_of <= $1)
+        ORDER BY c.id
+        "#,
+        change.id,
+        change.entity_type,
+        change.entity_id,
+        whole_person
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(changes)
+}
+
+/// Изменения дерева после момента `since`, новые первыми — в порядке отката.
+/// Пары "правка + её откат", целиком попавшие в окно, пропускаются.
+pub async fn get_tree_changes_since(
+    pool: &PgPool,
+    tree_id: Uuid,
+    since: chrono::NaiveDateTime,
+) -> Result<Vec<Change>, Error> {
+    let changes = sqlx::query_as!(
+        Change,
+        r#"
+        SELECT c.id, c.tree_id, c.user_id, u.name AS "user_name?", c.entity_type, c.entity_id,
+               c.person_ids, c.operation, c.before, c.after, c.revert_of, c.created_at
+        FROM changes c
+        LEFT JOIN users u ON u.id = c.user_id
+        WHERE c.tree_id = $1
+          AND c.created_at > $2
+          AND NOT EXISTS (SELECT 1 FROM changes r WHERE r.revert_of = c.id)
+          AND NOT EXISTS (
+              SELECT 1 FROM changes t WHERE t.id = c.revert_of AND t.created_at > $2
+          )
+        ORDER BY c.id DESC
+        "#,
+        tree_id,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(changes)
+}
@@ -0,0 +1,166 @@
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::model::Identity;
+
+/// Владелец внешнего аккаунта; заодно отмечает вход через него.
+pub async fn use_identity(
+    pool: &PgPool,
+    provider: &str,
+    subject: &str,
+) -> Result<Option<Uuid>, Error> {
+    let user_id = sqlx::query_scalar!(
+        r#"
+        UPDATE user_identities
+        SET last_used_at = NOW()
+        WHERE provider = $1 AND subject = $2
+        RETURNING user_id
+        "#,
+        provider,
+        subject
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user_id)
+}
+
+/// Привязывает внешний аккаунт. Возвращает владельца: если аккаунт уже
+/// привязан к другому пользователю, это будет не `user_id`.
+pub async fn link_identity(
+    pool: &PgPool,
+    user_id: Uuid,
+    provider: &str,
+    subject: &str,
+    email: &str,
+) -> Result<Uuid, Error> {
+    let owner = sqlx::query_scalar!(
+        r#"
+        INSERT INTO user_identities (user_id, provider, subject, email)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (provider, subject)
+        DO UPDATE SET last_used_at = user_identities.last_used_at
+        RETURNING user_id
+        "#,
+        user_id,
+        provider,
+        subject,
+        email
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(owner)
+}
+
+pub async fn get_identities(pool: &PgPool, user_id: Uuid) -> Result<Vec<Identity>, Error> {
+    let identities = sqlx::query_as!(
+        Identity,
+        r#"
+        SELECT id, provider, email, created_at, last_used_at
+        FROM user_identities
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(identities)
+}
+
+/// Сколько способов входа у пользователя: внешние аккаунты плюс пароль, если он задан.
+pub async fn count_login_methods(pool: &PgPool, user_id: Uuid) -> Result<i64, Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM user_identities WHERE user_id = $1)
+            + (SELECT COUNT(*) FROM users WHERE id = $1 AND password <> '')
+            AS "count!"
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Отвязывает внешний аккаунт, если у пользователя останется другой способ входа.
+/// Строка пользователя блокируется до конца транзакции: параллельные отвязка
+/// и удаление пароля выполняются по очереди и не оставят аккаунт без входа.
+pub async fn unlink_identity(pool: &PgPool, id: Uuid, user_id: Uuid) -> Result<bool, Error> {
+    let mut tx = pool.begin().await?;
+
+    let Some(has_password) = sqlx::query_scalar!(
+        r#"SELECT password <> '' AS "has_password!" FROM users WHERE id = $1 FOR UPDATE"#,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    let identities = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM user_identities WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if !has_password && identities <= 1 {
+        return Ok(false);
+    }
+
+    let result = sqlx::query!(
+        "DELETE FROM user_identities WHERE id = $1 AND user_id = $2",
+        id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Убирает вход по паролю, если привязан хотя бы один внешний аккаунт.
+/// Блокирует строку пользователя так же, как unlink_identity.
+pub async fn remove_user_password(pool: &PgPool, user_id: Uuid) -> Result<bool, Error> {
+    let mut tx = pool.begin().await?;
+
+    let Some(has_password) = sqlx::query_scalar!(
+        r#"SELECT password <> '' AS "has_password!" FROM users WHERE id = $1 FOR UPDATE"#,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    let identities = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM user_identities WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if !has_password || identities == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "UPDATE users SET password = '', updated_at = NOW() WHERE id = $1",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
@@ -1,6 +1,71 @@
+mod access_token;
+mod action_token;
+mod admin;
+mod comment;
+mod history;
+mod identity;
+mod notification;
+mod proposal;
+mod rate_limit;
+mod session;
+mod share;
+mod tree;
+mod two_factor;
 mod user;
 
+pub use access_token::{
+    authenticate_access_token, create_access_token, get_access_tokens, revoke_access_token,
+    revoke_all_access_tokens,
+};
+pub use action_token::{
+    consume_action_token, create_action_token, get_recent_action_tokens, is_action_token_active,
+    record_failed_action_attempt,
+};
+pub use admin::{
+    delete_user, get_audit_log, get_storage_usage, record_admin_action, require_password_reset,
+    search_users, suspend_user, unsuspend_user,
+};
+pub use comment::{
+    create_comment, delete_comment, get_comment, get_comments_for_subject, update_comment,
+};
+pub use history::{
+    begin_revert, get_change, get_later_changes, get_person_history, get_tree_changes_since,
+    get_tree_history, is_change_reverted, record_change,
+};
+pub use identity::{
+    count_login_methods, get_identities, link_identity, remove_user_password, unlink_identity,
+    use_identity,
+};
+pub use notification::{
+    create_notifications, get_notifications, get_unread_notification_count,
+    mark_all_notifications_read, mark_notification_read, register_device,
+};
+pub use proposal::{
+    create_proposal, get_proposal, get_proposals_by_user, get_proposals_for_tree, reopen_proposal,
+    review_proposal,
+};
+pub use rate_limit::{
+    delete_expired_rate_limits, get_rate_limit_lock, hit_rate_limit, lock_rate_limit,
+    reset_rate_limit,
+};
+pub use session::{
+    create_session, get_sessions, revoke_all_sessions, revoke_session, rotate_refresh_token,
+    touch_session,
+};
+pub use share::{
+    create_share_link, get_active_share_link, get_share_link_by_id, get_share_links_for_tree,
+    revoke_share_link,
+};
+pub use tree::{
+    add_tree_member, create_tree, delete_tree, filter_tree_members, get_accessible_tree_ids,
+    get_expired_trashed_tree_ids, get_owned_tree_ids, get_trashed_tree,
+    get_trashed_trees_for_owner, get_tree_role, get_trees_for_user, restore_tree, trash_tree,
+};
+pub use two_factor::{
+    disable_totp, enable_totp, get_totp, is_totp_enabled, mark_totp_step_used,
+    replace_recovery_codes, start_totp_setup, use_recovery_code,
+};
 pub use user::{
-    create_user, get_user_by_email, get_user_by_email_and_password, get_user_by_id,
-    insert_google_user, update_google_user, user_exists,
+    check_user_password, create_user, get_user_by_email, get_user_by_email_and_password,
+    get_user_by_id, insert_oauth_user, set_user_verified, update_user_password, user_exists,
 };
@@ -0,0 +1,138 @@
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::model::{NewNotification, Notification};
+
+/// Одно и то же событие для нескольких получателей.
+pub async fn create_notifications(
+    pool: &PgPool,
+    recipients: &[Uuid],
+    notification: &NewNotification,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, event_type, actor_id, tree_id, data)
+        SELECT recipient, $2, $3, $4, $5
+        FROM UNNEST($1::UUID[]) AS recipient
+        "#,
+        recipients,
+        notification.event.as_str(),
+        notification.actor_id,
+        notification.tree_id,
+        notification.data
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_notifications(
+    pool: &PgPool,
+    user_id: Uuid,
+    unread_only: bool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Notification>, Error> {
+    let notifications = sqlx::query_as!(
+        Notification,
+        r#"
+        SELECT n.id, n.user_id, n.event_type, n.actor_id, u.name AS "actor_name?", n.tree_id,
+               n.data, n.read_at, n.created_at
+        FROM notifications n
+        LEFT JOIN users u ON u.id = n.actor_id
+        WHERE n.user_id = $1 AND (NOT $2 OR n.read_at IS NULL)
+        ORDER BY n.created_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+        user_id,
+        unread_only,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(notifications)
+}
+
+pub async fn get_unread_notification_count(pool: &PgPool, user_id: Uuid) -> Result<i64, Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM notifications
+        WHERE user_id = $1 AND read_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// false — уведомления нет или оно чужое.
+pub async fn mark_notification_read(pool: &PgPool, id: Uuid, user_id: Uuid) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE notifications
+        SET read_at = COALESCE(read_at, NOW())
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn mark_all_notifications_read(pool: &PgPool, user_id: Uuid) -> Result<u64, Error> {
+    let result = sqlx::query!(
+        "UPDATE notifications SET read_at = NOW() WHERE user_id = $1 AND read_at IS NULL",
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Запоминает устройство входа. true — устройство новое, а до него у пользователя
+/// уже были другие (самый первый вход новым не считается).
+pub async fn register_device(
+    pool: &PgPool,
+    user_id: Uuid,
+    user_agent: &str,
+    ip: Option<&str>,
+) -> Result<bool, Error> {
+    let mut tx = pool.begin().await?;
+
+    let had_devices = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM user_devices WHERE user_id = $1) AS "exists!""#,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // xmax = 0 только у только что вставленной строки
+    let inserted = sqlx::query_scalar!(
+        r#"
+        INSERT INTO user_devices (user_id, user_agent, last_ip)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, user_agent)
+        DO UPDATE SET last_ip = EXCLUDED.last_ip, last_seen_at = NOW()
+        RETURNING (xmax = 0) AS "inserted!"
+        "#,
+        user_id,
+        user_agent,
+        ip
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(inserted && had_devices)
+}
@@ -0,0 +1,132 @@
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::model::{CreateProposalSchema, EditProposal, ProposalStatus};
+
+pub async fn create_proposal(
+    pool: &PgPool,
+    tree_id: Uuid,
+    proposed_by: Uuid,
+    base_version: Option<i64>,
+    proposal: &CreateProposalSchema,
+) -> Result<EditProposal, Error> {
+    let proposal = sqlx::query_as!(
+        EditProposal,
+        r#"
+        INSERT INTO edit_proposals
+            (tree_id, proposed_by, kind, target_id, payload, source, comment, base_version)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+        "#,
+        tree_id,
+        proposed_by,
+        proposal.kind.as_str(),
+        proposal.target_id,
+        proposal.payload,
+        proposal.source,
+        proposal.comment,
+        base_version
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(proposal)
+}
+
+pub async fn get_proposal(pool: &PgPool, id: Uuid) -> Result<Option<EditProposal>, Error> {
+    let proposal = sqlx::query_as!(
+        EditProposal,
+        "SELECT * FROM edit_proposals WHERE id = $1",
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(proposal)
+}
+
+/// Очередь дерева; без статуса — все предложения. Старые первыми, чтобы очередь
+/// разбиралась по порядку поступления.
+pub async fn get_proposals_for_tree(
+    pool: &PgPool,
+    tree_id: Uuid,
+    status: Option<ProposalStatus>,
+) -> Result<Vec<EditProposal>, Error> {
+    let proposals = sqlx::query_as!(
+        EditProposal,
+        r#"
+        SELECT *
+        FROM edit_proposals
+        WHERE tree_id = $1 AND ($2::TEXT IS NULL OR status = $2)
+        ORDER BY created_at
+        "#,
+        tree_id,
+        status.map(|s| s.as_str())
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(proposals)
+}
+
+pub async fn get_proposals_by_user(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<EditProposal>, Error> {
+    let proposals = sqlx::query_as!(
+        EditProposal,
+        r#"
+        SELECT *
+        FROM edit_proposals
+        WHERE proposed_by = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(proposals)
+}
+
+/// Переводит ожидающее предложение в итоговый статус. false — его уже разобрал
+/// кто-то другой.
+pub async fn review_proposal(
+    pool: &PgPool,
+    id: Uuid,
+    status: ProposalStatus,
+    reviewed_by: Uuid,
+    review_comment: Option<&str>,
+) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE edit_proposals
+        SET status = $2, reviewed_by = $3, review_comment = $4, reviewed_at = NOW()
+        WHERE id = $1 AND status = 'pending'
+        "#,
+        id,
+        status.as_str(),
+        reviewed_by,
+        review_comment
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Возвращает предложение в очередь, если принять его не получилось.
+pub async fn reopen_proposal(pool: &PgPool, id: Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        UPDATE edit_proposals
+        SET status = 'pending', reviewed_by = NULL, review_comment = NULL, reviewed_at = NULL
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use sqlx::{Error, PgPool};
+
+/// Засчитывает попытку в окне `window_secs`. Возвращает число попыток в окне
+/// и оставшуюся блокировку в секундах, если ключ заблокирован.
+pub async fn hit_rate_limit(
+    pool: &PgPool,
+    key: &str,
+    window_secs: f64,
+) -> Result<(i32, Option<f64>), Error> {
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO rate_limits AS r (key, window_start, attempts, expires_at)
+        VALUES ($1, NOW(), 1, NOW() + make_interval(secs => $2))
+        ON CONFLICT (key) DO UPDATE SET
+            attempts = CASE
+                WHEN r.locked_until > NOW() THEN r.attempts
+                WHEN r.locked_until IS NOT NULL
+                  OR r.window_start <= NOW() - make_interval(secs => $2) THEN 1
+                ELSE r.attempts + 1
+            END,
+            window_start = CASE
+                WHEN r.locked_until > NOW() THEN r.window_start
+                WHEN r.locked_until IS NOT NULL
+                  OR r.window_start <= NOW() - make_interval(secs => $2) THEN NOW()
+                ELSE r.window_start
+            END,
+            locked_until = CASE WHEN r.locked_until > NOW() THEN r.locked_until END,
+            expires_at = GREATEST(r.expires_at, NOW() + make_interval(secs => $2))
+        RETURNING attempts,
+                  CASE WHEN locked_until > NOW()
+                       THEN EXTRACT(EPOCH FROM locked_until - NOW())::FLOAT8
+                  END AS locked_for
+        "#,
+        key,
+        window_secs
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((row.attempts, row.locked_for))
+}
+
+pub async fn lock_rate_limit(pool: &PgPool, key: &str, lockout_secs: f64) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        UPDATE rate_limits
+        SET locked_until = NOW() + make_interval(secs => $2),
+            expires_at = GREATEST(expires_at, NOW() + make_interval(secs => $2))
+        WHERE key = $1
+        "#,
+        key,
+        lockout_secs
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Оставшаяся блокировка ключа в секундах.
+pub async fn get_rate_limit_lock(pool: &PgPool, key: &str) -> Result<Option<f64>, Error> {
+    let locked_for = sqlx::query_scalar!(
+        r#"
+        SELECT EXTRACT(EPOCH FROM locked_until - NOW())::FLOAT8 AS "locked_for!"
+        FROM rate_limits
+        WHERE key = $1 AND locked_until > NOW()
+        "#,
+        key
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(locked_for)
+}
+
+pub async fn reset_rate_limit(pool: &PgPool, key: &str) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM rate_limits WHERE key = $1", key)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_expired_rate_limits(pool: &PgPool) -> Result<u64, Error> {
+    let result = sqlx::query!("DELETE FROM rate_limits WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
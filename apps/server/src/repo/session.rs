@@ -0,0 +1,227 @@
+use chrono::{Duration, Utc};
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::model::{ActiveSession, RefreshOutcome, Session};
+
+/// Новая сессия вместе с первым refresh-токеном. Токен хешируется в базе,
+/// открытым текстом он есть только у клиента. None — пользователь заблокирован.
+pub async fn create_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    user_agent: &str,
+    ip: Option<&str>,
+    refresh_token: &str,
+    max_age_days: i64,
+    impersonated_by: Option<Uuid>,
+) -> Result<Option<Uuid>, Error> {
+    let expires_at = (Utc::now() + Duration::days(max_age_days)).naive_utc();
+    let mut tx = pool.begin().await?;
+
+    let session_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO sessions (user_id, user_agent, ip, expires_at, impersonated_by)
+        SELECT id, $2, $3, $4, $5 FROM users
+        WHERE id = $1 AND suspended_at IS NULL
+        RETURNING id
+        "#,
+        user_id,
+        user_agent,
+        ip,
+        expires_at,
+        impersonated_by
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(session_id) = session_id else {
+        return Ok(None);
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (token_hash, session_id)
+        VALUES (encode(sha256(convert_to($1, 'UTF8')), 'hex'), $2)
+        "#,
+        refresh_token,
+        session_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(session_id))
+}
+
+/// Меняет refresh-токен на новый. Каждый токен годится один раз: повторное
+/// предъявление отзывает всю сессию.
+pub async fn rotate_refresh_token(
+    pool: &PgPool,
+    refresh_token: &str,
+    new_refresh_token: &str,
+    max_age_days: i64,
+) -> Result<RefreshOutcome, Error> {
+    let mut tx = pool.begin().await?;
+
+    let row = sqlx::query!(
+        r#"
+        SELECT t.session_id, t.used_at, s.user_id, s.revoked_at, s.expires_at
+        FROM refresh_tokens t
+        JOIN sessions s ON s.id = t.session_id
+        WHERE t.token_hash = encode(sha256(convert_to($1, 'UTF8')), 'hex')
+        FOR UPDATE
+        "#,
+        refresh_token
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(RefreshOutcome::Invalid);
+    };
+
+    if row.revoked_at.is_some() || row.expires_at < Utc::now().naive_utc() {
+        return Ok(RefreshOutcome::Invalid);
+    }
+
+    if row.used_at.is_some() {
+        sqlx::query!(
+            "UPDATE sessions SET revoked_at = NOW() WHERE id = $1",
+            row.session_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        return Ok(RefreshOutcome::Reused);
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE refresh_tokens
+        SET used_at = NOW()
+        WHERE token_hash = encode(sha256(convert_to($1, 'UTF8')), 'hex')
+        "#,
+        refresh_token
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (token_hash, session_id)
+        VALUES (encode(sha256(convert_to($1, 'UTF8')), 'hex'), $2)
+        "#,
+        new_refresh_token,
+        row.session_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // сессию от имени пользователя не продлеваем: она живёт, сколько выдали
+    let expires_at = (Utc::now() + Duration::days(max_age_days)).naive_utc();
+    sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET expires_at = CASE WHEN impersonated_by IS NULL THEN $2 ELSE expires_at END,
+            last_seen_at = NOW()
+        WHERE id = $1
+        "#,
+        row.session_id,
+        expires_at
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(RefreshOutcome::Rotated {
+        session_id: row.session_id,
+        user_id: row.user_id,
+    })
+}
+
+/// Some — сессия жива. Заодно обновляет last_seen_at, но не чаще раза в минуту,
+/// чтобы не писать в базу на каждый запрос.
+pub async fn touch_session(
+    pool: &PgPool,
+    session_id: Uuid,
+) -> Result<Option<ActiveSession>, Error> {
+    let session = sqlx::query_as!(
+        ActiveSession,
+        r#"
+        WITH active AS (
+            SELECT id, impersonated_by FROM sessions
+            WHERE id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        ), touched AS (
+            UPDATE sessions SET last_seen_at = NOW()
+            WHERE id IN (SELECT id FROM active) AND last_seen_at < NOW() - INTERVAL '1 minute'
+        )
+        SELECT impersonated_by AS "impersonated_by?" FROM active
+        "#,
+        session_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(session)
+}
+
+pub async fn get_sessions(
+    pool: &PgPool,
+    user_id: Uuid,
+    current_session_id: Uuid,
+) -> Result<Vec<Session>, Error> {
+    let sessions = sqlx::query_as!(
+        Session,
+        r#"
+        SELECT id, user_agent, ip, created_at, last_seen_at, expires_at,
+               (id = $2) AS "current!", impersonated_by
+        FROM sessions
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        ORDER BY last_seen_at DESC
+        "#,
+        user_id,
+        current_session_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions)
+}
+
+/// false — сессии нет, она чужая или уже отозвана.
+pub async fn revoke_session(pool: &PgPool, session_id: Uuid, user_id: Uuid) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE sessions SET revoked_at = NOW()
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        session_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Отзывает все сессии пользователя, кроме `except` (например, текущей при смене пароля).
+pub async fn revoke_all_sessions(
+    pool: &PgPool,
+    user_id: Uuid,
+    except: Option<Uuid>,
+) -> Result<u64, Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE sessions SET revoked_at = NOW()
+        WHERE user_id = $1 AND revoked_at IS NULL AND id IS DISTINCT FROM $2
+        "#,
+        user_id,
+        except
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
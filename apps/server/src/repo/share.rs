@@ -0,0 +1,100 @@
+use bcrypt::{DEFAULT_COST, hash};
+use chrono::Local;
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::model::ShareLink;
+
+pub async fn create_share_link(
+    pool: &PgPool,
+    tree_id: Uuid,
+    created_by: Uuid,
+    root_person_id: Option<&str>,
+    expires_at: Option<chrono::NaiveDateTime>,
+    password: Option<&str>,
+) -> Result<ShareLink, Error> {
+    // два v4 UUID дают 244 случайных бита — токен не подобрать перебором
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let password = password.map(|p| hash(p, DEFAULT_COST).unwrap());
+
+    let link = sqlx::query_as!(
+        ShareLink,
+        r#"
+        INSERT INTO share_links (token, tree_id, root_person_id, password, expires_at, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+        token,
+        tree_id,
+        root_person_id,
+        password,
+        expires_at,
+        created_by
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(link)
+}
+
+pub async fn get_share_links_for_tree(
+    pool: &PgPool,
+    tree_id: Uuid,
+) -> Result<Vec<ShareLink>, Error> {
+    let links = sqlx::query_as!(
+        ShareLink,
+        r#"
+        SELECT *
+        FROM share_links
+        WHERE tree_id = $1
+        ORDER BY created_at DESC
+        "#,
+        tree_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(links)
+}
+
+pub async fn get_share_link_by_id(pool: &PgPool, id: Uuid) -> Result<Option<ShareLink>, Error> {
+    let link = sqlx::query_as!(ShareLink, "SELECT * FROM share_links WHERE id = $1", id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(link)
+}
+
+/// Действующая ссылка по токену: не отозвана, не истекла и дерево не в корзине.
+pub async fn get_active_share_link(pool: &PgPool, token: &str) -> Result<Option<ShareLink>, Error> {
+    let link = sqlx::query_as!(
+        ShareLink,
+        r#"
+        SELECT s.*
+        FROM share_links s
+        JOIN trees t ON t.id = s.tree_id
+        WHERE s.token = $1
+          AND s.revoked_at IS NULL
+          AND (s.expires_at IS NULL OR s.expires_at > $2)
+          AND t.deleted_at IS NULL
+        "#,
+        token,
+        Local::now().naive_local()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(link)
+}
+
+pub async fn revoke_share_link(pool: &PgPool, id: Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE share_links SET revoked_at = $1 WHERE id = $2 AND revoked_at IS NULL",
+        Local::now().naive_local(),
+        id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,227 @@
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::model::{Tree, TreeRole};
+
+pub async fn create_tree(pool: &PgPool, owner_id: Uuid, name: &str) -> Result<Tree, Error> {
+    let mut tx = pool.begin().await?;
+
+    let tree = sqlx::query_as!(
+        Tree,
+        r#"
+        INSERT INTO trees (name, owner_id)
+        VALUES ($1, $2)
+        RETURNING *
+        "#,
+        name,
+        owner_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO tree_members (tree_id, user_id, role)
+        VALUES ($1, $2, $3)
+        "#,
+        tree.id,
+        owner_id,
+        TreeRole::Owner.as_str()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(tree)
+}
+
+pub async fn get_trees_for_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<Tree>, Error> {
+    let trees = sqlx::query_as!(
+        Tree,
+        r#"
+        SELECT t.*
+        FROM trees t
+        JOIN tree_members m ON m.tree_id = t.id
+        WHERE m.user_id = $1 AND t.deleted_at IS NULL
+        ORDER BY t.created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(trees)
+}
+
+pub async fn get_accessible_tree_ids(pool: &PgPool, user_id: Uuid) -> Result<Vec<Uuid>, Error> {
+    let ids = sqlx::query_scalar!(
+        r#"
+        SELECT m.tree_id
+        FROM tree_members m
+        JOIN trees t ON t.id = m.tree_id
+        WHERE m.user_id = $1 AND t.deleted_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ids)
+}
+
+pub async fn get_tree_role(
+    pool: &PgPool,
+    tree_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<TreeRole>, Error> {
+    // дерево в корзине недоступно никому, восстановить его можно только через trash
+    let role = sqlx::query_scalar!(
+        r#"
+        SELECT m.role
+        FROM tree_members m
+        JOIN trees t ON t.id = m.tree_id
+        WHERE m.tree_id = $1 AND m.user_id = $2 AND t.deleted_at IS NULL
+        "#,
+        tree_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(role.as_deref().and_then(TreeRole::parse))
+}
+
+/// Добавляет участника или меняет ему роль. Роль владельца так не перезаписать:
+/// иначе дерево осталось бы без владельца. false — пользователь и есть владелец.
+pub async fn add_tree_member(
+    pool: &PgPool,
+    tree_id: Uuid,
+    user_id: Uuid,
+    role: TreeRole,
+) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO tree_members (tree_id, user_id, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (tree_id, user_id) DO UPDATE SET role = EXCLUDED.role
+        WHERE tree_members.role <> 'owner'
+        "#,
+        tree_id,
+        user_id,
+        role.as_str()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn trash_tree(pool: &PgPool, tree_id: Uuid, user_id: Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE trees SET deleted_at = NOW(), deleted_by = $2 WHERE id = $1 AND deleted_at IS NULL",
+        tree_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn restore_tree(pool: &PgPool, tree_id: Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE trees SET deleted_at = NULL, deleted_by = NULL WHERE id = $1",
+        tree_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_trashed_trees_for_owner(
+    pool: &PgPool,
+    owner_id: Uuid,
+) -> Result<Vec<Tree>, Error> {
+    let trees = sqlx::query_as!(
+        Tree,
+        r#"
+        SELECT *
+        FROM trees
+        WHERE owner_id = $1 AND deleted_at IS NOT NULL
+        ORDER BY deleted_at DESC
+        "#,
+        owner_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(trees)
+}
+
+pub async fn get_trashed_tree(pool: &PgPool, tree_id: Uuid) -> Result<Option<Tree>, Error> {
+    let tree = sqlx::query_as!(
+        Tree,
+        "SELECT * FROM trees WHERE id = $1 AND deleted_at IS NOT NULL",
+        tree_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(tree)
+}
+
+/// Деревья, пролежавшие в корзине дольше `retention_days`.
+pub async fn get_expired_trashed_tree_ids(
+    pool: &PgPool,
+    retention_days: i32,
+) -> Result<Vec<Uuid>, Error> {
+    let ids = sqlx::query_scalar!(
+        r#"
+        SELECT id
+        FROM trees
+        WHERE deleted_at < NOW() - make_interval(days => $1)
+        "#,
+        retention_days
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ids)
+}
+
+/// Все деревья владельца, включая лежащие в корзине.
+pub async fn get_owned_tree_ids(pool: &PgPool, owner_id: Uuid) -> Result<Vec<Uuid>, Error> {
+    let ids = sqlx::query_scalar!("SELECT id FROM trees WHERE owner_id = $1", owner_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(ids)
+}
+
+/// Окончательное удаление: участники, ссылки и журнал уходят каскадом.
+pub async fn delete_tree(pool: &PgPool, tree_id: Uuid) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM trees WHERE id = $1", tree_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Те из `user_ids`, кто состоит в дереве.
+pub async fn filter_tree_members(
+    pool: &PgPool,
+    tree_id: Uuid,
+    user_ids: &[Uuid],
+) -> Result<Vec<Uuid>, Error> {
+    let ids = sqlx::query_scalar!(
+        "SELECT user_id FROM tree_members WHERE tree_id = $1 AND user_id = ANY($2)",
+        tree_id,
+        user_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ids)
+}
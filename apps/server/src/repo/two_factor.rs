@@ -0,0 +1,162 @@
+use sqlx::{Error, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::model::UserTotp;
+
+pub async fn get_totp(pool: &PgPool, user_id: Uuid) -> Result<Option<UserTotp>, Error> {
+    let totp = sqlx::query_as!(
+        UserTotp,
+        "SELECT secret, enabled_at FROM user_totp WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(totp)
+}
+
+pub async fn is_totp_enabled(pool: &PgPool, user_id: Uuid) -> Result<bool, Error> {
+    let enabled = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM user_totp WHERE user_id = $1 AND enabled_at IS NOT NULL
+        ) AS "enabled!"
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(enabled)
+}
+
+/// Начинает подключение 2FA с новым секретом. false — 2FA уже включена,
+/// тогда секрет не трогаем.
+pub async fn start_totp_setup(pool: &PgPool, user_id: Uuid, secret: &str) -> Result<bool, Error> {
+    let started = sqlx::query_scalar!(
+        r#"
+        INSERT INTO user_totp (user_id, secret)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+        WHERE user_totp.enabled_at IS NULL
+        RETURNING user_id
+        "#,
+        user_id,
+        secret
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(started.is_some())
+}
+
+/// Запоминает шаг принятого кода. false — код этого или более позднего шага уже
+/// принимали, то есть его пытаются использовать повторно.
+pub async fn mark_totp_step_used(pool: &PgPool, user_id: Uuid, step: i64) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE user_totp
+        SET last_used_step = $2
+        WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+        "#,
+        user_id,
+        step
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+async fn insert_recovery_codes(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    codes: &[String],
+) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO recovery_codes (user_id, code_hash)
+        SELECT $1, encode(sha256(convert_to(code, 'UTF8')), 'hex')
+        FROM UNNEST($2::TEXT[]) AS code
+        "#,
+        user_id,
+        codes
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Включает 2FA и выдаёт первый набор кодов восстановления.
+pub async fn enable_totp(
+    pool: &PgPool,
+    user_id: Uuid,
+    recovery_codes: &[String],
+) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE user_totp SET enabled_at = NOW() WHERE user_id = $1",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    insert_recovery_codes(&mut tx, user_id, recovery_codes).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Заменяет все коды восстановления новыми; старые, в том числе неиспользованные, гаснут.
+pub async fn replace_recovery_codes(
+    pool: &PgPool,
+    user_id: Uuid,
+    recovery_codes: &[String],
+) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+    insert_recovery_codes(&mut tx, user_id, recovery_codes).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Гасит код восстановления. false — такого неиспользованного кода нет.
+pub async fn use_recovery_code(pool: &PgPool, user_id: Uuid, code: &str) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE recovery_codes
+        SET used_at = NOW()
+        WHERE user_id = $1
+          AND code_hash = encode(sha256(convert_to($2, 'UTF8')), 'hex')
+          AND used_at IS NULL
+        "#,
+        user_id,
+        code
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn disable_totp(pool: &PgPool, user_id: Uuid) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
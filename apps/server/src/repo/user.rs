@@ -3,7 +3,7 @@ use chrono::Local;
 use sqlx::{Error, PgPool};
 use uuid::Uuid;
 
-use crate::{handlers::GoogleUserResult, model::User};
+use crate::{model::User, oidc::OidcUser};
 
 pub async fn user_exists(pool: &PgPool, email: &str) -> Result<bool, Error> {
     let exist: Option<bool> =
@@ -32,13 +32,15 @@ pub async fn create_user(
         photo: "default.png".to_string(),
         created_at: None,
         updated_at: None,
+        suspended_at: None,
+        password_reset_required: false,
     };
 
-    sqlx::query_as!(
-        User,
+    let id = sqlx::query_scalar!(
         "
         INSERT INTO users (name, email, password, role, photo, verified, provider)
         VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
     ",
         user.name.clone(),
         user.email.clone(),
@@ -48,10 +50,13 @@ pub async fn create_user(
         user.verified as bool,
         user.provider.clone(),
     )
-    .execute(&pool)
+    .fetch_one(&pool)
     .await?;
 
-    Ok(user)
+    Ok(User {
+        id: Some(id),
+        ..user
+    })
 }
 
 pub async fn get_user_by_email(pool: &PgPool, email: &str) -> Result<Option<User>, sqlx::Error> {
@@ -76,62 +81,33 @@ pub async fn get_user_by_email_and_password(
     Ok(user)
 }
 
-pub async fn update_google_user(
-    pool: &PgPool,
-    id: &str,
-    email: &str,
-    photo: &str,
-) -> Result<User, sqlx::Error> {
-    let user_id = Uuid::parse_str(id).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
-
-    sqlx::query!(
-        r#"
-        UPDATE users
-        SET email = $1, photo = $2, updated_at = $3
-        WHERE id = $4
-        "#,
-        email,
-        photo,
-        Local::now().naive_local(),
-        user_id
-    )
-    .execute(pool)
-    .await?;
-
-    let updated_user = sqlx::query_as!(
-        User,
-        r#"
-        SELECT *
-        FROM users
-        WHERE id = $1
-        "#,
-        user_id
-    )
-    .fetch_one(pool)
-    .await?;
-
-    Ok(updated_user)
-}
-
-pub async fn insert_google_user(
+/// Новый пользователь, вошедший через провайдера, сразу с привязанным аккаунтом.
+pub async fn insert_oauth_user(
     pool: &PgPool,
     id: Uuid,
-    google_user: GoogleUserResult,
+    provider: &str,
+    oidc_user: OidcUser,
 ) -> Result<User, sqlx::Error> {
     let datetime = Local::now().naive_local();
     let user_data = User {
         id: Some(id),
-        name: google_user.name,
-        verified: google_user.verified_email,
-        email: google_user.email,
-        provider: "Google".to_string(),
+        name: oidc_user.name,
+        verified: oidc_user.email_verified,
+        email: oidc_user.email,
+        provider: provider.to_string(),
         role: "user".to_string(),
         password: "".to_string(),
-        photo: google_user.picture,
+        photo: oidc_user
+            .picture
+            .unwrap_or_else(|| "default.png".to_string()),
         created_at: Some(datetime),
         updated_at: Some(datetime),
+        suspended_at: None,
+        password_reset_required: false,
     };
 
+    let mut tx = pool.begin().await?;
+
     let user = sqlx::query_as!(
         User,
         r#"
@@ -152,12 +128,74 @@ pub async fn insert_google_user(
         user_data.created_at,
         user_data.updated_at
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    sqlx::query!(
+        r#"
+        INSERT INTO user_identities (user_id, provider, subject, email)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        id,
+        provider,
+        oidc_user.subject,
+        user.email
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
     Ok(user)
 }
 
+pub async fn set_user_verified(pool: &PgPool, user_id: Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE users SET verified = TRUE, updated_at = NOW() WHERE id = $1",
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Пароль хешируется так же, как при регистрации (bcrypt). Снимает требование
+/// сменить пароль, выставленное админом.
+pub async fn update_user_password(
+    pool: &PgPool,
+    user_id: Uuid,
+    password: &str,
+) -> Result<(), Error> {
+    let password = hash(password, DEFAULT_COST).unwrap();
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password = $2, password_reset_required = FALSE, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id,
+        password
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn check_user_password(
+    pool: &PgPool,
+    user_id: Uuid,
+    password: &str,
+) -> Result<bool, Error> {
+    let hashed = sqlx::query_scalar!("SELECT password FROM users WHERE id = $1", user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(hashed.is_some_and(|hashed| verify(password, &hashed).unwrap_or(false)))
+}
+
 pub async fn get_user_by_id(pool: &PgPool, user_id: &str) -> Result<Option<User>, sqlx::Error> {
     // Преобразуем строку `user_id` в Uuid
     let user_uuid = Uuid::parse_str(user_id).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
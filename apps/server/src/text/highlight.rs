@@ -0,0 +1,114 @@
+const MARK_OPEN: &str = "<mark>";
+const MARK_CLOSE: &str = "</mark>";
+
+fn escape_html(c: char, out: &mut String) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        '\'' => out.push_str("&#39;"),
+        c => out.push(c),
+    }
+}
+
+/// Фрагмент текста вокруг первого совпадения, где слова, начинающиеся с одного
+/// из терминов, обёрнуты в <mark>. Остальной текст экранирован как HTML.
+pub fn highlight(text: &str, terms: &[String], radius: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let terms: Vec<Vec<char>> = terms
+        .iter()
+        .map(|t| t.to_lowercase().chars().collect())
+        .filter(|t: &Vec<char>| !t.is_empty())
+        .collect();
+
+    // совпадения ищем только с начала слова и подсвечиваем слово целиком
+    let mut marks: Vec<(usize, usize)> = Vec::new();
+    let mut i = 0;
+    while i < lower.len() {
+        let word_start = i == 0 || !lower[i - 1].is_alphanumeric();
+        if word_start && terms.iter().any(|t| lower[i..].starts_with(t)) {
+            let mut end = i;
+            while end < lower.len() && lower[end].is_alphanumeric() {
+                end += 1;
+            }
+            marks.push((i, end.max(i + 1)));
+            i = end.max(i + 1);
+        } else {
+            i += 1;
+        }
+    }
+
+    let first = marks.first().map(|(start, _)| *start).unwrap_or(0);
+    let from = first.saturating_sub(radius);
+    let to = (from + radius * 2).min(chars.len());
+
+    let mut out = String::with_capacity(text.len() + marks.len() * 13);
+    if from > 0 {
+        out.push('…');
+    }
+
+    for (pos, c) in chars.iter().enumerate().take(to).skip(from) {
+        if marks.iter().any(|(start, _)| *start == pos) {
+            out.push_str(MARK_OPEN);
+        }
+        escape_html(*c, &mut out);
+        if marks.iter().any(|(_, end)| *end == pos + 1) {
+            out.push_str(MARK_CLOSE);
+        }
+    }
+
+    // подсветка, обрезанная концом фрагмента
+    if marks.iter().any(|(start, end)| *start < to && *end > to) {
+        out.push_str(MARK_CLOSE);
+    }
+
+    if to < chars.len() {
+        out.push('…');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn marks_whole_words_starting_with_a_term() {
+        assert_eq!(
+            highlight("Иван Шевченко, сын Ивана", &terms(&["иван"]), 60),
+            "<mark>Иван</mark> Шевченко, сын <mark>Ивана</mark>"
+        );
+    }
+
+    #[test]
+    fn escapes_html_around_and_inside_marks() {
+        assert_eq!(
+            highlight("<b>Tom & \"Jerry's\"</b>", &terms(&["jerry"]), 60),
+            "&lt;b&gt;Tom &amp; &quot;<mark>Jerry</mark>&#39;s&quot;&lt;/b&gt;"
+        );
+        assert_eq!(
+            highlight("<script>alert(1)</script>", &terms(&["script"]), 60),
+            "&lt;<mark>script</mark>&gt;alert(1)&lt;/<mark>script</mark>&gt;"
+        );
+    }
+
+    #[test]
+    fn cuts_a_snippet_around_the_first_match() {
+        let text = format!("{} Шевченко {}", "а".repeat(20), "б".repeat(20));
+        // слово обрезано концом фрагмента, но <mark> всё равно закрыт
+        assert_eq!(
+            highlight(&text, &terms(&["шевч"]), 5),
+            "…аааа <mark>Шевче</mark>…"
+        );
+    }
+}
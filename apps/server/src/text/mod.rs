@@ -0,0 +1,51 @@
+pub mod highlight;
+pub mod phonetic;
+pub mod translit;
+
+pub use highlight::highlight;
+pub use phonetic::{daitch_mokotoff, double_metaphone};
+pub use translit::search_variants;
+
+/// Ключи поиска для набора слов (частей имени). Используются при записи персоны
+/// и при разборе поискового запроса, поэтому обе стороны сворачиваются одинаково.
+pub fn search_keys<'a>(words: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut keys: Vec<String> = words
+        .into_iter()
+        .flat_map(tokenize)
+        .flat_map(|token| search_variants(&token))
+        .collect();
+
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Фонетические ключи слов с префиксом алгоритма: "dm:474650", "mp:XFXN".
+/// Префикс нужен, чтобы коды разных алгоритмов не совпадали случайно.
+pub fn phonetic_keys<'a>(words: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut keys = Vec::new();
+
+    for token in words.into_iter().flat_map(tokenize) {
+        for code in daitch_mokotoff(&token) {
+            keys.push(format!("dm:{}", code));
+        }
+
+        let (primary, secondary) = double_metaphone(&token);
+        for code in [primary, secondary] {
+            if !code.is_empty() {
+                keys.push(format!("mp:{}", code));
+            }
+        }
+    }
+
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_string())
+        .collect()
+}
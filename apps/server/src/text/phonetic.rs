@@ -0,0 +1,914 @@
+//! Фонетические ключи фамилий: Daitch–Mokotoff Soundex для славянских и еврейских
+//! фамилий и Double Metaphone для остальных. Оба алгоритма работают с латиницей,
+//! кириллица предварительно транслитерируется (BGN/PCGN).
+
+use super::translit::{Scheme, is_cyrillic, transliterate};
+
+fn prepare(word: &str) -> Vec<char> {
+    let latin = if is_cyrillic(word) {
+        transliterate(word, Scheme::BgnPcgn)
+    } else {
+        word.to_string()
+    };
+
+    latin
+        .to_uppercase()
+        .chars()
+        .map(|c| match c {
+            'Á' | 'À' | 'Â' | 'Ä' | 'Ą' => 'A',
+            'Č' | 'Ć' => 'C',
+            'É' | 'È' | 'Ê' | 'Ë' | 'Ę' | 'Ě' => 'E',
+            'Í' | 'Ì' | 'Î' | 'Ï' => 'I',
+            'Ł' => 'L',
+            'Ń' | 'Ň' => 'N',
+            'Ó' | 'Ò' | 'Ô' | 'Ö' => 'O',
+            'Ř' => 'R',
+            'Š' | 'Ś' => 'S',
+            'Ú' | 'Ù' | 'Û' | 'Ü' | 'Ů' => 'U',
+            'Ý' => 'Y',
+            'Ž' | 'Ź' | 'Ż' => 'Z',
+            c => c,
+        })
+        .filter(|c| c.is_ascii_alphabetic() || matches!(c, 'Ç' | 'Ñ'))
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Daitch–Mokotoff Soundex
+// ---------------------------------------------------------------------------
+
+// Код правила: в начале слова, перед гласной, в остальных случаях.
+// Варианты через "|" порождают альтернативные ветви (CH = KH или TCH).
+struct DmRule {
+    patterns: &'static [&'static str],
+    start: &'static str,
+    before_vowel: &'static str,
+    other: &'static str,
+}
+
+const fn dm(
+    patterns: &'static [&'static str],
+    start: &'static str,
+    before_vowel: &'static str,
+    other: &'static str,
+) -> DmRule {
+    DmRule {
+        patterns,
+        start,
+        before_vowel,
+        other,
+    }
+}
+
+// Порядок важен: более длинные сочетания проверяются раньше
+const DM_RULES: &[DmRule] = &[
+    dm(&["SCHTSCH", "SCHTSH", "SCHTCH"], "2", "4", "4"),
+    dm(&["SHTCH", "SHCH", "SHTSH"], "2", "4", "4"),
+    dm(&["STCH", "STSCH", "STRZ", "STRS", "STSH"], "2", "4", "4"),
+    dm(&["SZCZ", "SZCS"], "2", "4", "4"),
+    dm(&["ZDZH", "ZHDZH"], "2", "4", "4"),
+    dm(&["TTSCH"], "4", "4", "4"),
+    dm(&["SCHT", "SCHD"], "2", "43", "43"),
+    dm(&["SHT", "SZT", "SHD", "SZD"], "2", "43", "43"),
+    dm(&["ZSCH"], "4", "4", "4"),
+    dm(&["TSCH", "TTCH", "TTSZ"], "4", "4", "4"),
+    dm(&["CSZ", "CZS"], "4", "4", "4"),
+    dm(&["DRZ", "DRS", "DSH", "DSZ", "DZH", "DZS"], "4", "4", "4"),
+    dm(&["SCH"], "4", "4", "4"),
+    dm(&["CHS"], "5", "54", "54"),
+    dm(
+        &["TCH", "TRZ", "TRS", "TSH", "TTS", "TTZ", "TZS", "TSZ"],
+        "4",
+        "4",
+        "4",
+    ),
+    dm(&["ZDZ"], "2", "4", "4"),
+    dm(&["ZHD"], "2", "43", "43"),
+    dm(&["ZSH"], "4", "4", "4"),
+    dm(&["SC"], "2", "4", "4"),
+    dm(&["ST"], "2", "43", "43"),
+    dm(&["SD"], "2", "43", "43"),
+    dm(&["ZD"], "2", "43", "43"),
+    dm(&["AI", "AJ", "AY"], "0", "1", ""),
+    dm(&["AU"], "0", "7", ""),
+    dm(&["CH"], "5|4", "5|4", "5|4"),
+    dm(&["CK"], "5|45", "5|45", "5|45"),
+    dm(&["CZ", "CS"], "4", "4", "4"),
+    dm(&["DS", "DZ"], "4", "4", "4"),
+    dm(&["DT"], "3", "3", "3"),
+    dm(&["EI", "EJ", "EY"], "0", "1", ""),
+    dm(&["EU"], "1", "1", ""),
+    dm(&["FB"], "7", "7", "7"),
+    dm(&["IA", "IE", "IO", "IU"], "1", "", ""),
+    dm(&["KS"], "5", "54", "54"),
+    dm(&["KH"], "5", "5", "5"),
+    dm(&["MN", "NM"], "", "66", "66"),
+    dm(&["OI", "OJ", "OY"], "0", "1", ""),
+    dm(&["PF", "PH"], "7", "7", "7"),
+    dm(&["RZ", "RS"], "94|4", "94|4", "94|4"),
+    dm(&["SH", "SZ"], "4", "4", "4"),
+    dm(&["TH"], "3", "3", "3"),
+    dm(&["TS", "TC", "TZ"], "4", "4", "4"),
+    dm(&["UI", "UJ", "UY"], "0", "1", ""),
+    dm(&["UE"], "0", "", ""),
+    dm(&["ZH", "ZS"], "4", "4", "4"),
+    dm(&["A", "E", "I", "O", "U"], "0", "", ""),
+    dm(&["B"], "7", "7", "7"),
+    dm(&["C"], "5|4", "5|4", "5|4"),
+    dm(&["D"], "3", "3", "3"),
+    dm(&["F", "P", "V", "W"], "7", "7", "7"),
+    dm(&["G", "K", "Q"], "5", "5", "5"),
+    dm(&["H"], "5", "5", ""),
+    dm(&["J"], "1|4", "1|4", "1|4"),
+    dm(&["L"], "8", "8", "8"),
+    dm(&["M", "N"], "6", "6", "6"),
+    dm(&["R"], "9", "9", "9"),
+    dm(&["S", "Z"], "4", "4", "4"),
+    dm(&["T"], "3", "3", "3"),
+    dm(&["X"], "5", "54", "54"),
+    dm(&["Y"], "1", "", ""),
+];
+
+const DM_LENGTH: usize = 6;
+
+#[derive(Clone)]
+struct DmBranch {
+    code: String,
+    last: String,
+}
+
+/// Коды Daitch–Mokotoff (6 цифр). Из-за неоднозначных сочетаний (CH, CK, C, J, RZ)
+/// у слова может быть несколько кодов.
+pub fn daitch_mokotoff(word: &str) -> Vec<String> {
+    let chars = prepare(word);
+    if chars.is_empty() {
+        return Vec::new();
+    }
+    let text: String = chars
+        .iter()
+        .map(|c| match c {
+            'Ç' => 'C',
+            'Ñ' => 'N',
+            c => *c,
+        })
+        .collect();
+
+    let mut branches = vec![DmBranch {
+        code: String::new(),
+        last: String::new(),
+    }];
+
+    let mut pos = 0;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        let Some((rule, pattern)) = DM_RULES.iter().find_map(|rule| {
+            rule.patterns
+                .iter()
+                .find(|p| rest.starts_with(*p))
+                .map(|p| (rule, *p))
+        }) else {
+            pos += 1;
+            continue;
+        };
+
+        let next = text[pos + pattern.len()..].chars().next();
+        let codes = if pos == 0 {
+            rule.start
+        } else if matches!(next, Some('A' | 'E' | 'I' | 'O' | 'U')) {
+            rule.before_vowel
+        } else {
+            rule.other
+        };
+
+        // MN/NM кодируются всегда, даже если предыдущий код тоже 6
+        let force = pattern == "MN" || pattern == "NM";
+
+        let mut next_branches = Vec::new();
+        for branch in &branches {
+            for code in codes.split('|') {
+                let mut b = branch.clone();
+                if (force || b.last.is_empty() || !b.last.ends_with(code))
+                    && b.code.len() < DM_LENGTH
+                {
+                    b.code.push_str(code);
+                    b.code.truncate(DM_LENGTH);
+                }
+                b.last = code.to_string();
+                next_branches.push(b);
+            }
+        }
+        branches = next_branches;
+
+        pos += pattern.len();
+    }
+
+    let mut codes: Vec<String> = branches
+        .into_iter()
+        .map(|b| format!("{:0<width$}", b.code, width = DM_LENGTH))
+        .collect();
+    codes.sort();
+    codes.dedup();
+    codes
+}
+
+// ---------------------------------------------------------------------------
+// Double Metaphone (Lawrence Philips)
+// ---------------------------------------------------------------------------
+
+const METAPHONE_LENGTH: usize = 4;
+
+struct Metaphone {
+    chars: Vec<char>,
+    primary: String,
+    secondary: String,
+    slavo_germanic: bool,
+}
+
+impl Metaphone {
+    fn at(&self, i: isize) -> char {
+        if i < 0 {
+            return '\0';
+        }
+        self.chars.get(i as usize).copied().unwrap_or('\0')
+    }
+
+    fn string_at(&self, start: isize, len: usize, options: &[&str]) -> bool {
+        if start < 0 || start as usize + len > self.chars.len() {
+            return false;
+        }
+        let slice: String = self.chars[start as usize..start as usize + len]
+            .iter()
+            .collect();
+        options.iter().any(|o| *o == slice)
+    }
+
+    fn is_vowel(&self, i: isize) -> bool {
+        matches!(self.at(i), 'A' | 'E' | 'I' | 'O' | 'U' | 'Y')
+    }
+
+    fn add(&mut self, main: &str) {
+        self.primary.push_str(main);
+        self.secondary.push_str(main);
+    }
+
+    fn add_alt(&mut self, main: &str, alt: &str) {
+        self.primary.push_str(main);
+        self.secondary.push_str(alt);
+    }
+
+    fn done(&self) -> bool {
+        self.primary.len() >= METAPHONE_LENGTH && self.secondary.len() >= METAPHONE_LENGTH
+    }
+}
+
+/// Основной и альтернативный ключи Double Metaphone (до 4 символов).
+pub fn double_metaphone(word: &str) -> (String, String) {
+    let chars = prepare(word);
+    let slavo_germanic = {
+        let s: String = chars.iter().collect();
+        s.contains('W') || s.contains('K') || s.contains("CZ") || s.contains("WITZ")
+    };
+    let mut m = Metaphone {
+        chars,
+        primary: String::new(),
+        secondary: String::new(),
+        slavo_germanic,
+    };
+
+    let length = m.chars.len() as isize;
+    if length == 0 {
+        return (String::new(), String::new());
+    }
+    let last = length - 1;
+    let mut current: isize = 0;
+
+    // не произносятся в начале слова
+    if m.string_at(0, 2, &["GN", "KN", "PN", "WR", "PS"]) {
+        current += 1;
+    }
+
+    // 'X' в начале произносится как 'S' (Xavier)
+    if m.at(0) == 'X' {
+        m.add("S");
+        current += 1;
+    }
+
+    while !m.done() && current < length {
+        match m.at(current) {
+            'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                if current == 0 {
+                    m.add("A");
+                }
+                current += 1;
+            }
+            'B' => {
+                m.add("P");
+                current += if m.at(current + 1) == 'B' { 2 } else { 1 };
+            }
+            'Ç' => {
+                m.add("S");
+                current += 1;
+            }
+            'C' => {
+                current = metaphone_c(&mut m, current);
+            }
+            'D' => {
+                if m.string_at(current, 2, &["DG"]) {
+                    if m.string_at(current + 2, 1, &["I", "E", "Y"]) {
+                        m.add("J");
+                        current += 3;
+                    } else {
+                        m.add("TK");
+                        current += 2;
+                    }
+                } else if m.string_at(current, 2, &["DT", "DD"]) {
+                    m.add("T");
+                    current += 2;
+                } else {
+                    m.add("T");
+                    current += 1;
+                }
+            }
+            'F' => {
+                m.add("F");
+                current += if m.at(current + 1) == 'F' { 2 } else { 1 };
+            }
+            'G' => {
+                current = metaphone_g(&mut m, current);
+            }
+            // H читается только в начале или между гласными
+            'H' if (current == 0 || m.is_vowel(current - 1)) && m.is_vowel(current + 1) => {
+                m.add("H");
+                current += 2;
+            }
+            'H' => {
+                current += 1;
+            }
+            'J' => {
+                current = metaphone_j(&mut m, current, last);
+            }
+            'K' => {
+                m.add("K");
+                current += if m.at(current + 1) == 'K' { 2 } else { 1 };
+            }
+            'L' => {
+                if m.at(current + 1) == 'L' {
+                    // испанское -illo, -illa, -alle
+                    if (current == length - 3
+                        && m.string_at(current - 1, 4, &["ILLO", "ILLA", "ALLE"]))
+                        || ((m.string_at(last - 1, 2, &["AS", "OS"])
+                            || m.string_at(last, 1, &["A", "O"]))
+                            && m.string_at(current - 1, 4, &["ALLE"]))
+                    {
+                        m.add_alt("L", "");
+                        current += 2;
+                        continue;
+                    }
+                    current += 2;
+                } else {
+                    current += 1;
+                }
+                m.add("L");
+            }
+            'M' => {
+                if (m.string_at(current - 1, 3, &["UMB"])
+                    && (current + 1 == last || m.string_at(current + 2, 2, &["ER"])))
+                    || m.at(current + 1) == 'M'
+                {
+                    current += 2;
+                } else {
+                    current += 1;
+                }
+                m.add("M");
+            }
+            'N' => {
+                m.add("N");
+                current += if m.at(current + 1) == 'N' { 2 } else { 1 };
+            }
+            'Ñ' => {
+                m.add("N");
+                current += 1;
+            }
+            'P' => {
+                if m.at(current + 1) == 'H' {
+                    m.add("F");
+                    current += 2;
+                } else {
+                    m.add("P");
+                    current += if m.string_at(current + 1, 1, &["P", "B"]) {
+                        2
+                    } else {
+                        1
+                    };
+                }
+            }
+            'Q' => {
+                m.add("K");
+                current += if m.at(current + 1) == 'Q' { 2 } else { 1 };
+            }
+            'R' => {
+                // французское -ier на конце, но не немецкое -meier
+                if current == last
+                    && !m.slavo_germanic
+                    && m.string_at(current - 2, 2, &["IE"])
+                    && !m.string_at(current - 4, 2, &["ME", "MA"])
+                {
+                    m.add_alt("", "R");
+                } else {
+                    m.add("R");
+                }
+                current += if m.at(current + 1) == 'R' { 2 } else { 1 };
+            }
+            'S' => {
+                current = metaphone_s(&mut m, current, last);
+            }
+            'T' => {
+                if m.string_at(current, 4, &["TION"]) || m.string_at(current, 3, &["TIA", "TCH"]) {
+                    m.add("X");
+                    current += 3;
+                } else if m.string_at(current, 2, &["TH"]) || m.string_at(current, 3, &["TTH"]) {
+                    if m.string_at(current + 2, 2, &["OM", "AM"])
+                        || m.string_at(0, 4, &["VAN ", "VON "])
+                        || m.string_at(0, 3, &["SCH"])
+                    {
+                        m.add("T");
+                    } else {
+                        m.add_alt("0", "T");
+                    }
+                    current += 2;
+                } else {
+                    m.add("T");
+                    current += if m.string_at(current + 1, 1, &["T", "D"]) {
+                        2
+                    } else {
+                        1
+                    };
+                }
+            }
+            'V' => {
+                m.add("F");
+                current += if m.at(current + 1) == 'V' { 2 } else { 1 };
+            }
+            'W' => {
+                if m.string_at(current, 2, &["WR"]) {
+                    m.add("R");
+                    current += 2;
+                    continue;
+                }
+
+                if current == 0 && (m.is_vowel(current + 1) || m.string_at(current, 2, &["WH"])) {
+                    // Wasserman = Vasserman
+                    if m.is_vowel(current + 1) {
+                        m.add_alt("A", "F");
+                    } else {
+                        m.add("A");
+                    }
+                }
+
+                // Arnow = Arnoff, польские -owski
+                if (current == last && m.is_vowel(current - 1))
+                    || m.string_at(current - 1, 5, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"])
+                    || m.string_at(0, 3, &["SCH"])
+                {
+                    m.add_alt("", "F");
+                    current += 1;
+                    continue;
+                }
+
+                // польское -wicz (Filipowicz)
+                if m.string_at(current, 4, &["WICZ", "WITZ"]) {
+                    m.add_alt("TS", "FX");
+                    current += 4;
+                    continue;
+                }
+
+                current += 1;
+            }
+            'X' => {
+                // французское -eaux
+                if !(current == last
+                    && (m.string_at(current - 3, 3, &["IAU", "EAU"])
+                        || m.string_at(current - 2, 2, &["AU", "OU"])))
+                {
+                    m.add("KS");
+                }
+                current += if m.string_at(current + 1, 1, &["C", "X"]) {
+                    2
+                } else {
+                    1
+                };
+            }
+            'Z' => {
+                if m.at(current + 1) == 'H' {
+                    m.add("J");
+                    current += 2;
+                    continue;
+                } else if m.string_at(current + 1, 2, &["ZO", "ZI", "ZA"])
+                    || (m.slavo_germanic && current > 0 && m.at(current - 1) != 'T')
+                {
+                    m.add_alt("S", "TS");
+                } else {
+                    m.add("S");
+                }
+                current += if m.at(current + 1) == 'Z' { 2 } else { 1 };
+            }
+            _ => {
+                current += 1;
+            }
+        }
+    }
+
+    m.primary.truncate(METAPHONE_LENGTH);
+    m.secondary.truncate(METAPHONE_LENGTH);
+    (m.primary, m.secondary)
+}
+
+fn metaphone_c(m: &mut Metaphone, current: isize) -> isize {
+    // германское -ach- (Bach, но не Bacher/Macher)
+    if current > 1
+        && !m.is_vowel(current - 2)
+        && m.string_at(current - 1, 3, &["ACH"])
+        && m.at(current + 2) != 'I'
+        && (m.at(current + 2) != 'E' || m.string_at(current - 2, 6, &["BACHER", "MACHER"]))
+    {
+        m.add("K");
+        return current + 2;
+    }
+
+    if current == 0 && m.string_at(current, 6, &["CAESAR"]) {
+        m.add("S");
+        return current + 2;
+    }
+
+    // итальянское Chianti
+    if m.string_at(current, 4, &["CHIA"]) {
+        m.add("K");
+        return current + 2;
+    }
+
+    if m.string_at(current, 2, &["CH"]) {
+        // Michael
+        if current > 0 && m.string_at(current, 4, &["CHAE"]) {
+            m.add_alt("K", "X");
+            return current + 2;
+        }
+
+        // греческие корни: Chemistry, Chorus
+        if current == 0
+            && (m.string_at(current + 1, 5, &["HARAC", "HARIS"])
+                || m.string_at(current + 1, 3, &["HOR", "HYM", "HIA", "HEM"]))
+            && !m.string_at(0, 5, &["CHORE"])
+        {
+            m.add("K");
+            return current + 2;
+        }
+
+        // германское или греческое ch = kh
+        if m.string_at(0, 4, &["VAN ", "VON "])
+            || m.string_at(0, 3, &["SCH"])
+            || m.string_at(current - 2, 6, &["ORCHES", "ARCHIT", "ORCHID"])
+            || m.string_at(current + 2, 1, &["T", "S"])
+            || ((m.string_at(current - 1, 1, &["A", "O", "U", "E"]) || current == 0)
+                && (m.string_at(
+                    current + 2,
+                    1,
+                    &["L", "R", "N", "M", "B", "H", "F", "V", "W"],
+                ) || current + 2 >= m.chars.len() as isize))
+        {
+            m.add("K");
+        } else if current > 0 {
+            if m.string_at(0, 2, &["MC"]) {
+                m.add("K");
+            } else {
+                m.add_alt("X", "K");
+            }
+        } else {
+            m.add("X");
+        }
+        return current + 2;
+    }
+
+    // Czerny
+    if m.string_at(current, 2, &["CZ"]) && !m.string_at(current - 2, 4, &["WICZ"]) {
+        m.add_alt("S", "X");
+        return current + 2;
+    }
+
+    // Focaccia
+    if m.string_at(current + 1, 3, &["CIA"]) {
+        m.add("X");
+        return current + 3;
+    }
+
+    // двойное C, но не McClellan
+    if m.string_at(current, 2, &["CC"]) && !(current == 1 && m.at(0) == 'M') {
+        if m.string_at(current + 2, 1, &["I", "E", "H"]) && !m.string_at(current + 2, 2, &["HU"]) {
+            // Accident, Succeed
+            if (current == 1 && m.at(current - 1) == 'A')
+                || m.string_at(current - 1, 5, &["UCCEE", "UCCES"])
+            {
+                m.add("KS");
+            } else {
+                // Bacci, Bertucci
+                m.add("X");
+            }
+            return current + 3;
+        }
+        m.add("K");
+        return current + 2;
+    }
+
+    if m.string_at(current, 2, &["CK", "CG", "CQ"]) {
+        m.add("K");
+        return current + 2;
+    }
+
+    if m.string_at(current, 2, &["CI", "CE", "CY"]) {
+        if m.string_at(current, 3, &["CIO", "CIE", "CIA"]) {
+            m.add_alt("S", "X");
+        } else {
+            m.add("S");
+        }
+        return current + 2;
+    }
+
+    m.add("K");
+
+    if m.string_at(current + 1, 1, &["C", "K", "Q"]) && !m.string_at(current + 1, 2, &["CE", "CI"])
+    {
+        current + 2
+    } else {
+        current + 1
+    }
+}
+
+fn metaphone_g(m: &mut Metaphone, current: isize) -> isize {
+    if m.at(current + 1) == 'H' {
+        if current > 0 && !m.is_vowel(current - 1) {
+            m.add("K");
+            return current + 2;
+        }
+
+        // Ghislane, Ghiradelli
+        if current == 0 {
+            if m.at(current + 2) == 'I' {
+                m.add("J");
+            } else {
+                m.add("K");
+            }
+            return current + 2;
+        }
+
+        // правило Паркера: Hugh, Bough, Broughton
+        if (current > 1 && m.string_at(current - 2, 1, &["B", "H", "D"]))
+            || (current > 2 && m.string_at(current - 3, 1, &["B", "H", "D"]))
+            || (current > 3 && m.string_at(current - 4, 1, &["B", "H"]))
+        {
+            return current + 2;
+        }
+
+        // Laugh, McLaughlin, Cough, Rough
+        if current > 2
+            && m.at(current - 1) == 'U'
+            && m.string_at(current - 3, 1, &["C", "G", "L", "R", "T"])
+        {
+            m.add("F");
+        } else if current > 0 && m.at(current - 1) != 'I' {
+            m.add("K");
+        }
+        return current + 2;
+    }
+
+    if m.at(current + 1) == 'N' {
+        if current == 1 && m.is_vowel(0) && !m.slavo_germanic {
+            m.add_alt("KN", "N");
+        } else if !m.string_at(current + 2, 2, &["EY"])
+            && m.at(current + 1) != 'Y'
+            && !m.slavo_germanic
+        {
+            m.add_alt("N", "KN");
+        } else {
+            m.add("KN");
+        }
+        return current + 2;
+    }
+
+    // Tagliaro
+    if m.string_at(current + 1, 2, &["LI"]) && !m.slavo_germanic {
+        m.add_alt("KL", "L");
+        return current + 2;
+    }
+
+    // -ges-, -gep-, -gel-, -gie- в начале
+    if current == 0
+        && (m.at(current + 1) == 'Y'
+            || m.string_at(
+                current + 1,
+                2,
+                &[
+                    "ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER",
+                ],
+            ))
+    {
+        m.add_alt("K", "J");
+        return current + 2;
+    }
+
+    // -ger-, -gy-
+    if (m.string_at(current + 1, 2, &["ER"]) || m.at(current + 1) == 'Y')
+        && !m.string_at(0, 6, &["DANGER", "RANGER", "MANGER"])
+        && !m.string_at(current - 1, 1, &["E", "I"])
+        && !m.string_at(current - 1, 3, &["RGY", "OGY"])
+    {
+        m.add_alt("K", "J");
+        return current + 2;
+    }
+
+    // итальянское Biaggi
+    if m.string_at(current + 1, 1, &["E", "I", "Y"])
+        || m.string_at(current - 1, 4, &["AGGI", "OGGI"])
+    {
+        if m.string_at(0, 4, &["VAN ", "VON "])
+            || m.string_at(0, 3, &["SCH"])
+            || m.string_at(current + 1, 2, &["ET"])
+        {
+            m.add("K");
+        } else if m.string_at(current + 1, 3, &["IER"]) && current + 4 >= m.chars.len() as isize {
+            m.add("J");
+        } else {
+            m.add_alt("J", "K");
+        }
+        return current + 2;
+    }
+
+    m.add("K");
+    if m.at(current + 1) == 'G' {
+        current + 2
+    } else {
+        current + 1
+    }
+}
+
+fn metaphone_j(m: &mut Metaphone, current: isize, last: isize) -> isize {
+    // испанское Jose, San Jacinto
+    if m.string_at(current, 4, &["JOSE"]) || m.string_at(0, 4, &["SAN "]) {
+        if (current == 0 && m.at(current + 4) == ' ') || m.string_at(0, 4, &["SAN "]) {
+            m.add("H");
+        } else {
+            m.add_alt("J", "H");
+        }
+        return current + 1;
+    }
+
+    if current == 0 {
+        // Yankelovich = Jankelowicz
+        m.add_alt("J", "A");
+    } else if m.is_vowel(current - 1)
+        && !m.slavo_germanic
+        && (m.at(current + 1) == 'A' || m.at(current + 1) == 'O')
+    {
+        // испанское Bajador
+        m.add_alt("J", "H");
+    } else if current == last {
+        m.add_alt("J", "");
+    } else if !m.string_at(current + 1, 1, &["L", "T", "K", "S", "N", "M", "B", "Z"])
+        && !m.string_at(current - 1, 1, &["S", "K", "L"])
+    {
+        m.add("J");
+    }
+
+    if m.at(current + 1) == 'J' {
+        current + 2
+    } else {
+        current + 1
+    }
+}
+
+fn metaphone_s(m: &mut Metaphone, current: isize, last: isize) -> isize {
+    // Island, Isle, Carlisle
+    if m.string_at(current - 1, 3, &["ISL", "YSL"]) {
+        return current + 1;
+    }
+
+    // Sugar
+    if current == 0 && m.string_at(current, 5, &["SUGAR"]) {
+        m.add_alt("X", "S");
+        return current + 1;
+    }
+
+    if m.string_at(current, 2, &["SH"]) {
+        // германское -sheim, -sholz
+        if m.string_at(current + 1, 4, &["HEIM", "HOEK", "HOLM", "HOLZ"]) {
+            m.add("S");
+        } else {
+            m.add("X");
+        }
+        return current + 2;
+    }
+
+    // итальянское и армянское -sio-, -sia-
+    if m.string_at(current, 3, &["SIO", "SIA"]) || m.string_at(current, 4, &["SIAN"]) {
+        if m.slavo_germanic {
+            m.add("S");
+        } else {
+            m.add_alt("S", "X");
+        }
+        return current + 3;
+    }
+
+    // Smith = Schmidt, Snider = Schneider; славянское -sz-
+    if (current == 0 && m.string_at(current + 1, 1, &["M", "N", "L", "W"]))
+        || m.string_at(current + 1, 1, &["Z"])
+    {
+        m.add_alt("S", "X");
+        return if m.string_at(current + 1, 1, &["Z"]) {
+            current + 2
+        } else {
+            current + 1
+        };
+    }
+
+    if m.string_at(current, 2, &["SC"]) {
+        // правило Шлезингера
+        if m.at(current + 2) == 'H' {
+            // голландское School, Schooner
+            if m.string_at(current + 3, 2, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+                // Schermerhorn, Schenker
+                if m.string_at(current + 3, 2, &["ER", "EN"]) {
+                    m.add_alt("X", "SK");
+                } else {
+                    m.add("SK");
+                }
+                return current + 3;
+            }
+
+            if current == 0 && !m.is_vowel(3) && m.at(3) != 'W' {
+                m.add_alt("X", "S");
+            } else {
+                m.add("X");
+            }
+            return current + 3;
+        }
+
+        if m.string_at(current + 2, 1, &["I", "E", "Y"]) {
+            m.add("S");
+            return current + 3;
+        }
+
+        m.add("SK");
+        return current + 3;
+    }
+
+    // французское Resnais, Artois
+    if current == last && m.string_at(current - 2, 2, &["AI", "OI"]) {
+        m.add_alt("", "S");
+    } else {
+        m.add("S");
+    }
+
+    if m.string_at(current + 1, 1, &["S", "Z"]) {
+        current + 2
+    } else {
+        current + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut codes: Vec<String>) -> Vec<String> {
+        codes.sort();
+        codes
+    }
+
+    #[test]
+    fn daitch_mokotoff_reference_codes() {
+        assert_eq!(daitch_mokotoff("Moskowitz"), vec!["645740"]);
+        assert_eq!(daitch_mokotoff("Moskovitz"), vec!["645740"]);
+        // RS кодируется и как 94, и как 4
+        assert_eq!(sorted(daitch_mokotoff("Peters")), vec!["734000", "739400"]);
+        assert_eq!(
+            sorted(daitch_mokotoff("Auerbach")),
+            vec!["097400", "097500"]
+        );
+        assert_eq!(sorted(daitch_mokotoff("Ohrbach")), vec!["097400", "097500"]);
+        assert_eq!(daitch_mokotoff("Lipshitz"), vec!["874400"]);
+    }
+
+    #[test]
+    fn daitch_mokotoff_transliterates_cyrillic() {
+        assert_eq!(daitch_mokotoff("Шевченко"), daitch_mokotoff("Shevchenko"));
+        assert!(daitch_mokotoff("").is_empty());
+    }
+
+    #[test]
+    fn double_metaphone_reference_keys() {
+        assert_eq!(double_metaphone("Smith"), ("SM0".into(), "XMT".into()));
+        assert_eq!(double_metaphone("Schmidt"), ("XMT".into(), "SMT".into()));
+        assert_eq!(double_metaphone("Thomas"), ("TMS".into(), "TMS".into()));
+        assert_eq!(double_metaphone("Knight"), ("NT".into(), "NT".into()));
+        assert_eq!(double_metaphone("Xavier"), ("SF".into(), "SFR".into()));
+        assert_eq!(double_metaphone(""), (String::new(), String::new()));
+    }
+}
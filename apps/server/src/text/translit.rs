@@ -0,0 +1,371 @@
+/// Системы транслитерации кириллицы, которые встречаются в архивных записях.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// ГОСТ 7.79-2000, система Б (только ASCII)
+    Gost,
+    /// BGN/PCGN — англоязычные документы, эмиграционные записи
+    BgnPcgn,
+    /// ISO 9:1995 (ГОСТ 7.79, система А), с диакритикой
+    Iso9,
+    /// Немецкая транскрипция (Duden)
+    German,
+}
+
+pub const SCHEMES: [Scheme; 4] = [Scheme::Gost, Scheme::BgnPcgn, Scheme::Iso9, Scheme::German];
+
+pub fn is_cyrillic(text: &str) -> bool {
+    text.chars().any(|c| matches!(c, '\u{0400}'..='\u{04FF}'))
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(
+        c,
+        'а' | 'е' | 'ё' | 'и' | 'о' | 'у' | 'ы' | 'э' | 'ю' | 'я' | 'і' | 'ї' | 'є'
+    )
+}
+
+pub fn transliterate(text: &str, scheme: Scheme) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len() * 2);
+
+    for (i, &c) in chars.iter().enumerate() {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let prev = i
+            .checked_sub(1)
+            .and_then(|p| chars.get(p))
+            .map(|p| p.to_lowercase().next().unwrap_or(*p));
+        let next = chars
+            .get(i + 1)
+            .map(|n| n.to_lowercase().next().unwrap_or(*n));
+
+        let Some(latin) = map_char(lower, prev, next, scheme) else {
+            out.push(c);
+            continue;
+        };
+
+        if c.is_uppercase() {
+            let mut latin_chars = latin.chars();
+            if let Some(first) = latin_chars.next() {
+                out.extend(first.to_uppercase());
+                out.push_str(latin_chars.as_str());
+            }
+        } else {
+            out.push_str(latin);
+        }
+    }
+
+    out
+}
+
+fn map_char(
+    c: char,
+    prev: Option<char>,
+    next: Option<char>,
+    scheme: Scheme,
+) -> Option<&'static str> {
+    // начало слова или после гласной/знака: е -> ye/je в BGN и немецкой системе
+    let iotated = match prev {
+        None => true,
+        Some(p) => is_vowel(p) || matches!(p, 'ъ' | 'ь' | 'й') || !p.is_alphabetic(),
+    };
+
+    let latin = match scheme {
+        Scheme::Gost => match c {
+            'а' => "a",
+            'б' => "b",
+            'в' => "v",
+            'г' => "g",
+            'д' => "d",
+            'е' => "e",
+            'ё' => "yo",
+            'ж' => "zh",
+            'з' => "z",
+            'и' => "i",
+            'й' => "j",
+            'к' => "k",
+            'л' => "l",
+            'м' => "m",
+            'н' => "n",
+            'о' => "o",
+            'п' => "p",
+            'р' => "r",
+            'с' => "s",
+            'т' => "t",
+            'у' => "u",
+            'ф' => "f",
+            'х' => "x",
+            'ц' if matches!(next, Some('е' | 'и' | 'ы' | 'й' | 'і' | 'є')) => "c",
+            'ц' => "cz",
+            'ч' => "ch",
+            'ш' => "sh",
+            'щ' => "shh",
+            'ъ' => "``",
+            'ы' => "y'",
+            'ь' => "`",
+            'э' => "e`",
+            'ю' => "yu",
+            'я' => "ya",
+            'і' => "i'",
+            'ї' => "yi",
+            'є' => "ye",
+            'ґ' => "g`",
+            _ => return None,
+        },
+        Scheme::BgnPcgn => match c {
+            'а' => "a",
+            'б' => "b",
+            'в' => "v",
+            'г' => "g",
+            'д' => "d",
+            'е' if iotated => "ye",
+            'е' => "e",
+            'ё' if iotated => "yë",
+            'ё' => "ë",
+            'ж' => "zh",
+            'з' => "z",
+            'и' => "i",
+            'й' => "y",
+            'к' => "k",
+            'л' => "l",
+            'м' => "m",
+            'н' => "n",
+            'о' => "o",
+            'п' => "p",
+            'р' => "r",
+            'с' => "s",
+            'т' => "t",
+            'у' => "u",
+            'ф' => "f",
+            'х' => "kh",
+            'ц' => "ts",
+            'ч' => "ch",
+            'ш' => "sh",
+            'щ' => "shch",
+            'ъ' => "”",
+            'ы' => "y",
+            'ь' => "’",
+            'э' => "e",
+            'ю' => "yu",
+            'я' => "ya",
+            'і' => "i",
+            'ї' => "yi",
+            'є' => "ye",
+            'ґ' => "g",
+            _ => return None,
+        },
+        Scheme::Iso9 => match c {
+            'а' => "a",
+            'б' => "b",
+            'в' => "v",
+            'г' => "g",
+            'д' => "d",
+            'е' => "e",
+            'ё' => "ë",
+            'ж' => "ž",
+            'з' => "z",
+            'и' => "i",
+            'й' => "j",
+            'к' => "k",
+            'л' => "l",
+            'м' => "m",
+            'н' => "n",
+            'о' => "o",
+            'п' => "p",
+            'р' => "r",
+            'с' => "s",
+            'т' => "t",
+            'у' => "u",
+            'ф' => "f",
+            'х' => "h",
+            'ц' => "c",
+            'ч' => "č",
+            'ш' => "š",
+            'щ' => "ŝ",
+            'ъ' => "ʺ",
+            'ы' => "y",
+            'ь' => "ʹ",
+            'э' => "è",
+            'ю' => "û",
+            'я' => "â",
+            'і' => "ì",
+            'ї' => "ï",
+            'є' => "ê",
+            'ґ' => "g̀",
+            _ => return None,
+        },
+        Scheme::German => match c {
+            'а' => "a",
+            'б' => "b",
+            'в' => "w",
+            'г' => "g",
+            'д' => "d",
+            'е' if iotated => "je",
+            'е' => "e",
+            'ё' if iotated => "jo",
+            'ё' => "o",
+            'ж' => "sch",
+            'з' => "s",
+            'и' => "i",
+            'й' if next.is_some_and(is_vowel) => "j",
+            'й' => "i",
+            'к' => "k",
+            'л' => "l",
+            'м' => "m",
+            'н' => "n",
+            'о' => "o",
+            'п' => "p",
+            'р' => "r",
+            'с' if prev.is_some_and(is_vowel) && next.is_some_and(is_vowel) => "ss",
+            'с' => "s",
+            'т' => "t",
+            'у' => "u",
+            'ф' => "f",
+            'х' => "ch",
+            'ц' => "z",
+            'ч' => "tsch",
+            'ш' => "sch",
+            'щ' => "schtsch",
+            'ъ' => "",
+            'ы' => "y",
+            'ь' => "",
+            'э' => "e",
+            'ю' => "ju",
+            'я' => "ja",
+            'і' => "i",
+            'ї' => "ji",
+            'є' => "je",
+            'ґ' => "g",
+            _ => return None,
+        },
+    };
+
+    Some(latin)
+}
+
+fn strip_diacritic(c: char) -> Option<&'static str> {
+    let plain = match c {
+        'á' | 'à' | 'â' | 'ä' | 'ą' => "a",
+        'č' | 'ć' | 'ç' => "c",
+        'é' | 'è' | 'ê' | 'ë' | 'ę' | 'ě' => "e",
+        'í' | 'ì' | 'î' | 'ï' => "i",
+        'ł' => "l",
+        'ń' | 'ň' => "n",
+        'ó' | 'ò' | 'ô' | 'ö' => "o",
+        'ř' => "r",
+        'š' | 'ś' | 'ŝ' => "s",
+        'ú' | 'ù' | 'û' | 'ü' | 'ů' => "u",
+        'ý' => "y",
+        'ž' | 'ź' | 'ż' => "z",
+        'ß' => "ss",
+        _ => return None,
+    };
+
+    Some(plain)
+}
+
+// Порядок важен: длинные сочетания раньше коротких, которые в них входят
+const FOLD_CLUSTERS: &[(&str, &str)] = &[
+    ("schtsch", "sc"),
+    ("shch", "sc"),
+    ("shh", "sc"),
+    ("tsch", "c"),
+    ("sch", "s"),
+    ("sz", "s"),
+    ("zh", "z"),
+    ("kh", "h"),
+    ("ch", "c"),
+    ("sh", "s"),
+    ("cz", "c"),
+    ("tz", "c"),
+    ("ts", "c"),
+    ("ph", "f"),
+    ("w", "v"),
+    ("x", "ks"),
+    ("j", "y"),
+];
+
+/// Грубый латинский ключ слова, общий для разных систем транслитерации:
+/// Shevchenko, Ševčenko, Sevcenko и Schewtschenko дают один и тот же "sevcenko".
+pub fn fold(word: &str) -> String {
+    let mut plain = String::with_capacity(word.len());
+    for c in word.to_lowercase().chars() {
+        if let Some(stripped) = strip_diacritic(c) {
+            plain.push_str(stripped);
+        } else if c.is_ascii_alphanumeric() {
+            plain.push(c);
+        }
+    }
+
+    for (from, to) in FOLD_CLUSTERS {
+        plain = plain.replace(from, to);
+    }
+
+    // y перед гласной (ya, ye, yu...) — это йотация, сама гласная важнее; остальные y -> i
+    let chars: Vec<char> = plain.chars().collect();
+    let mut folded = String::with_capacity(chars.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let c = if c == 'y' {
+            match chars.get(i + 1) {
+                Some('a' | 'e' | 'i' | 'o' | 'u') => continue,
+                _ => 'i',
+            }
+        } else {
+            c
+        };
+
+        // удвоенные буквы схлопываем: Tolstoii -> tolstoi, Russo -> ruso
+        if folded.ends_with(c) {
+            continue;
+        }
+        folded.push(c);
+    }
+
+    folded
+}
+
+/// Все ключи, под которыми слово может встретиться в записях:
+/// кириллица прогоняется через каждую систему транслитерации, латиница просто сворачивается.
+pub fn search_variants(word: &str) -> Vec<String> {
+    let mut variants: Vec<String> = if is_cyrillic(word) {
+        SCHEMES
+            .iter()
+            .map(|scheme| fold(&transliterate(word, *scheme)))
+            .collect()
+    } else {
+        vec![fold(word)]
+    };
+
+    variants.retain(|v| !v.is_empty());
+    variants.sort();
+    variants.dedup();
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transliterates_with_each_scheme() {
+        assert_eq!(transliterate("Шевченко", Scheme::Gost), "Shevchenko");
+        assert_eq!(transliterate("Шевченко", Scheme::BgnPcgn), "Shevchenko");
+        assert_eq!(transliterate("Шевченко", Scheme::Iso9), "Ševčenko");
+        assert_eq!(transliterate("Шевченко", Scheme::German), "Schewtschenko");
+    }
+
+    #[test]
+    fn spellings_from_records_share_a_search_key() {
+        let key = fold("Shevchenko");
+        for spelling in ["Sevcenko", "Ševčenko", "Schewtschenko", "SHEVCHENKO"] {
+            assert_eq!(fold(spelling), key, "{}", spelling);
+        }
+        assert!(search_variants("Шевченко").contains(&key));
+    }
+
+    #[test]
+    fn latin_words_are_only_folded() {
+        assert_eq!(search_variants("Shevchenko"), vec![fold("Shevchenko")]);
+        assert!(!is_cyrillic("Shevchenko"));
+        assert!(is_cyrillic("Шевченко"));
+    }
+}